@@ -0,0 +1,181 @@
+use alloy_primitives::{hex, Bytes};
+use raiko_lib::{
+    consts::VerifierType,
+    input::{GuestInput, GuestOutput, TierProof},
+    protocol_instance::ProtocolInstance,
+    prover::{Proof, ProverError},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    interfaces::{ProofType, RaikoError, RaikoResult},
+    Raiko,
+};
+
+/// Length of a SGX proof: 4b(id)+20b(pubkey)+65b(signature).
+const SGX_PROOF_LEN: usize = 89;
+
+/// A `proveBlock(blockId, input)` call submitting a proof to the TaikoL1 contract.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProveBlockCall {
+    pub block_id: u64,
+    /// The tier the proof is submitted with.
+    pub tier: u16,
+    /// The ABI-encoded `(BlockMetadata, Transition, TierProof)`.
+    pub input: Bytes,
+    /// The ABI-encoded `proveBlock(blockId, input)` call.
+    pub calldata: Bytes,
+}
+
+impl From<ProofType> for VerifierType {
+    fn from(proof_type: ProofType) -> Self {
+        match proof_type {
+            ProofType::Native => VerifierType::None,
+            ProofType::Sgx => VerifierType::SGX,
+            ProofType::Sp1 => VerifierType::SP1,
+            ProofType::Risc0 => VerifierType::RISC0,
+        }
+    }
+}
+
+/// Returns the proof bytes of a prover response in the format the verifier contract of the
+/// proof type expects.
+pub fn tier_proof_data(proof_type: &ProofType, proof: &Proof) -> RaikoResult<Vec<u8>> {
+    let field = |name: &str, hint: &str| {
+        let data = proof
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| RaikoError::Conversion(format!("No {name} in the proof, {hint}")))?;
+        hex::decode(data).map_err(|e| RaikoError::Conversion(format!("Invalid {name}: {e}")))
+    };
+    match proof_type {
+        // Optimistic proofs don't carry any data
+        ProofType::Native => Ok(Vec::new()),
+        ProofType::Sgx => {
+            let data = field("proof", "the SGX prover failed")?;
+            if data.len() != SGX_PROOF_LEN {
+                return Err(RaikoError::Conversion(format!(
+                    "Invalid SGX proof length {}, expected {SGX_PROOF_LEN}",
+                    data.len()
+                )));
+            }
+            Ok(data)
+        }
+        ProofType::Risc0 => field("seal", "enable `snark` to create an on-chain proof"),
+        ProofType::Sp1 => field(
+            "wrapped_proof",
+            "use the `groth16` or `plonk` recursion to create an on-chain proof",
+        ),
+    }
+}
+
+/// Builds the `proveBlock(blockId, input)` call submitting the proof of the block to L1. The
+/// tier defaults to the tier of the verifier of the proof type.
+pub fn prove_block_call(
+    input: &GuestInput,
+    output: &GuestOutput,
+    proof_type: &ProofType,
+    proof: &Proof,
+    tier: Option<u16>,
+) -> RaikoResult<ProveBlockCall> {
+    let GuestOutput::Success { header, .. } = output else {
+        return Err(RaikoError::Guest(ProverError::GuestError(
+            "Cannot submit a proof for a block that failed to build".to_owned(),
+        )));
+    };
+    let verifier_type = VerifierType::from(proof_type.clone());
+    let tier = tier.unwrap_or_else(|| verifier_type.tier());
+    let pi = ProtocolInstance::new(input, header, verifier_type)?;
+    let tier_proof = TierProof {
+        tier,
+        data: tier_proof_data(proof_type, proof)?.into(),
+    };
+    Ok(ProveBlockCall {
+        block_id: pi.block_metadata.block_id(),
+        tier,
+        input: pi.prove_block_input(tier_proof.clone()).into(),
+        calldata: pi.prove_block_calldata(tier_proof).into(),
+    })
+}
+
+impl Raiko {
+    /// Builds the `proveBlock(blockId, input)` call for a proof of the requested proof type.
+    pub fn prove_block_call(
+        &self,
+        input: &GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+        tier: Option<u16>,
+    ) -> RaikoResult<ProveBlockCall> {
+        prove_block_call(input, output, &self.request.proof_type, proof, tier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_sol_types::{SolCall, SolValue};
+    use raiko_lib::input::{proveBlockCall, BlockMetadata, Transition};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tier_proof_data() {
+        let sgx_proof = [vec![0u8, 0, 0, 1], vec![2u8; 20], vec![3u8; 65]].concat();
+        let proof = json!({ "proof": format!("0x{}", hex::encode(&sgx_proof)), "quote": "" });
+        assert_eq!(tier_proof_data(&ProofType::Sgx, &proof).unwrap(), sgx_proof);
+        let proof = json!({ "proof": "0x0102" });
+        assert!(tier_proof_data(&ProofType::Sgx, &proof).is_err());
+
+        let proof = json!({ "proof": "0x01", "seal": "0xabcd" });
+        assert_eq!(
+            tier_proof_data(&ProofType::Risc0, &proof).unwrap(),
+            vec![0xab, 0xcd]
+        );
+        let proof = json!({ "proof": "0x01", "seal": null });
+        assert!(tier_proof_data(&ProofType::Risc0, &proof).is_err());
+
+        let proof = json!({ "proof": "{}", "wrapped_proof": "0x1234" });
+        assert_eq!(
+            tier_proof_data(&ProofType::Sp1, &proof).unwrap(),
+            vec![0x12, 0x34]
+        );
+        assert!(tier_proof_data(&ProofType::Native, &json!(null))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prove_block_call() {
+        let input = GuestInput::default();
+        let output = GuestOutput::Success {
+            header: Default::default(),
+            hash: Default::default(),
+        };
+        let sgx_proof = [vec![0u8; 4], vec![1u8; 20], vec![2u8; 65]].concat();
+        let proof = json!({ "proof": hex::encode(&sgx_proof) });
+        let call = prove_block_call(&input, &output, &ProofType::Sgx, &proof, None).unwrap();
+        assert_eq!(call.tier, VerifierType::SGX.tier());
+
+        // The calldata decodes back to the block, the transition and the proof
+        let decoded = proveBlockCall::abi_decode(&call.calldata, true).unwrap();
+        assert_eq!(decoded.blockId, call.block_id);
+        assert_eq!(decoded.input, call.input);
+        let (_meta, transition, tier_proof) =
+            <(BlockMetadata, Transition, TierProof)>::abi_decode_params(&call.input, true).unwrap();
+        let pi = ProtocolInstance::new(&input, &Default::default(), VerifierType::SGX).unwrap();
+        assert_eq!(transition.abi_encode(), pi.transition.abi_encode());
+        assert_eq!(tier_proof.tier, VerifierType::SGX.tier());
+        assert_eq!(tier_proof.data.to_vec(), sgx_proof);
+
+        // The tier can be overridden
+        let call = prove_block_call(&input, &output, &ProofType::Sgx, &proof, Some(1000)).unwrap();
+        let (_, _, tier_proof) =
+            <(BlockMetadata, Transition, TierProof)>::abi_decode_params(&call.input, true).unwrap();
+        assert_eq!(tier_proof.tier, 1000);
+
+        let failure = GuestOutput::Failure;
+        assert!(prove_block_call(&input, &failure, &ProofType::Sgx, &proof, None).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use alloy_primitives::B256;
+use alloy_sol_types::SolCall;
+use raiko_lib::{
+    input::{getTransitionCall, GuestInput, GuestOutput, TransitionState},
+    prover::{Proof, ProverError},
+    utils::HeaderHasher,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    calldata::ProveBlockCall,
+    interfaces::{RaikoError, RaikoResult},
+    provider::rpc::RpcBlockDataProvider,
+    Raiko,
+};
+
+/// The result of re-proving a block that already has a proven transition on L1.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContestResponse {
+    pub block_id: u64,
+    pub parent_hash: B256,
+    /// The block hash of the transition proven on L1.
+    pub proven_block_hash: B256,
+    /// The state root of the transition proven on L1.
+    pub proven_state_root: B256,
+    /// The tier the transition on L1 is proven with.
+    pub proven_tier: u16,
+    /// The block hash from our re-execution.
+    pub block_hash: B256,
+    /// The state root from our re-execution.
+    pub state_root: B256,
+    /// Whether our re-execution agrees with the transition proven on L1.
+    pub agrees: bool,
+    pub proof: Proof,
+    /// The `proveBlock(blockId, input)` call submitting our transition and proof.
+    pub prove_block: ProveBlockCall,
+}
+
+impl Raiko {
+    /// Reads the transition of the block currently proven on L1.
+    pub async fn get_proven_transition(&self, input: &GuestInput) -> RaikoResult<TransitionState> {
+        let Some(l1_contract) = self.taiko_chain_spec.l1_contract else {
+            return Err(RaikoError::InvalidRequestConfig(
+                "No L1 contract address in the chain spec".to_owned(),
+            ));
+        };
+        let provider = RpcBlockDataProvider::new(&self.l1_chain_spec.rpc, 0)?;
+        let call = getTransitionCall {
+            blockId: input.block_number,
+            parentHash: input.parent_header.hash(),
+        };
+        let result = provider
+            .eth_call(l1_contract, call.abi_encode().into())
+            .await?;
+        let transition = getTransitionCall::abi_decode_returns(&result, true)
+            .map_err(|e| RaikoError::Conversion(format!("Invalid transition: {e}")))?
+            ._0;
+        Ok(transition)
+    }
+
+    /// Re-executes a block that already has a proven transition on L1, and proves our
+    /// transition with the requested proof type at `tier`.
+    ///
+    /// When our re-execution disagrees with L1 the proof contests the transition, otherwise it
+    /// proves the same transition at a higher tier.
+    pub async fn contest(
+        &self,
+        input: GuestInput,
+        output: &GuestOutput,
+        tier: u16,
+    ) -> RaikoResult<ContestResponse> {
+        let GuestOutput::Success { header, .. } = output else {
+            return Err(RaikoError::Guest(ProverError::GuestError(
+                "Cannot contest a block that failed to build".to_owned(),
+            )));
+        };
+        let proven = self.get_proven_transition(&input).await?;
+        let agrees = header.hash() == proven.blockHash && header.state_root == proven.stateRoot;
+        info!(
+            "Block {} is proven on L1 at tier {} with block hash {}, our re-execution {}",
+            input.block_number,
+            proven.tier,
+            proven.blockHash,
+            if agrees { "agrees" } else { "disagrees" }
+        );
+        if tier < proven.tier || (agrees && tier == proven.tier) {
+            return Err(RaikoError::InvalidRequestConfig(format!(
+                "Cannot prove at tier {tier}, the block is already proven at tier {}",
+                proven.tier
+            )));
+        }
+
+        let proof = self.prove(input.clone(), output).await?;
+        let prove_block = self.prove_block_call(&input, output, &proof, Some(tier))?;
+
+        Ok(ContestResponse {
+            block_id: prove_block.block_id,
+            parent_hash: input.parent_header.hash(),
+            proven_block_hash: proven.blockHash,
+            proven_state_root: proven.stateRoot,
+            proven_tier: proven.tier,
+            block_hash: header.hash(),
+            state_root: header.state_root,
+            agrees,
+            proof,
+            prove_block,
+        })
+    }
+}
@@ -0,0 +1,351 @@
+use std::{collections::HashMap, fmt};
+
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use raiko_lib::{
+    builder::{BlockBuildOutput, ChainStrategy},
+    input::GuestInput,
+    mem_db::AccountState,
+};
+use raiko_primitives::{keccak::KECCAK_EMPTY, receipt::Receipt};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::{rpc::RpcBlockDataProvider, BlockDataProvider},
+    Raiko,
+};
+
+/// A value of the built block that differs from the block on the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldDiff {
+    pub field: String,
+    /// The value on the node.
+    pub expected: String,
+    /// The value we built.
+    pub found: String,
+}
+
+impl FieldDiff {
+    pub fn new(field: &str, expected: impl fmt::Debug, found: impl fmt::Debug) -> Self {
+        Self {
+            field: field.to_owned(),
+            expected: format!("{expected:?}"),
+            found: format!("{found:?}"),
+        }
+    }
+}
+
+/// The first transaction whose execution differs from the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxDiff {
+    /// The index of the transaction in the block.
+    pub index: usize,
+    /// The hash of the transaction on the node, if the node has a transaction at the index.
+    pub expected_tx_hash: Option<B256>,
+    /// The hash of the transaction we included, if we included one at the index.
+    pub found_tx_hash: Option<B256>,
+    /// The receipt fields that differ.
+    pub fields: Vec<FieldDiff>,
+}
+
+/// An account whose post-execution state differs from the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub fields: Vec<FieldDiff>,
+}
+
+/// A storage slot whose post-execution value differs from the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageDiff {
+    pub address: Address,
+    pub slot: U256,
+    pub expected: U256,
+    pub found: U256,
+}
+
+/// Describes how a built block differs from the block on the node.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MismatchReport {
+    pub block_number: u64,
+    /// The block hash on the node.
+    pub expected_hash: B256,
+    /// The block hash we built.
+    pub found_hash: B256,
+    /// The header fields that differ.
+    pub header: Vec<FieldDiff>,
+    /// Whether the receipts and the state were compared against the node.
+    pub diagnosed: bool,
+    /// The first transaction whose receipt differs.
+    pub first_diverging_tx: Option<TxDiff>,
+    /// The accounts whose nonce, balance or code differ.
+    pub accounts: Vec<AccountDiff>,
+    /// The storage slots whose value differs.
+    pub storage: Vec<StorageDiff>,
+}
+
+impl fmt::Display for MismatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block hash unexpected for block {}, expected {} but built {}",
+            self.block_number, self.expected_hash, self.found_hash
+        )?;
+        let fields: Vec<_> = self.header.iter().map(|diff| diff.field.as_str()).collect();
+        if !fields.is_empty() {
+            write!(f, ", differing fields: {}", fields.join(", "))?;
+        }
+        if let Some(tx) = &self.first_diverging_tx {
+            write!(f, ", first diverging tx: {}", tx.index)?;
+        }
+        if self.diagnosed {
+            write!(
+                f,
+                ", {} differing accounts and {} differing storage slots",
+                self.accounts.len(),
+                self.storage.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A log of a receipt returned by `eth_getBlockReceipts`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NodeLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+/// A receipt returned by `eth_getBlockReceipts`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeReceipt {
+    pub transaction_hash: B256,
+    pub status: U64,
+    pub cumulative_gas_used: U256,
+    pub gas_used: U256,
+    pub logs: Vec<NodeLog>,
+}
+
+/// Returns the first transaction whose hash, status, gas used or logs differ from the node.
+pub fn compare_receipts(
+    tx_hashes: &[B256],
+    receipts: &[Receipt],
+    node_receipts: &[NodeReceipt],
+) -> Option<TxDiff> {
+    let num_txs = receipts.len().max(node_receipts.len());
+    let mut prev_cumulative_gas_used = U256::ZERO;
+    for index in 0..num_txs {
+        let (receipt, node_receipt) = match (receipts.get(index), node_receipts.get(index)) {
+            (Some(receipt), Some(node_receipt)) => (receipt, node_receipt),
+            (_, node_receipt) => {
+                // One of the blocks has more transactions
+                return Some(TxDiff {
+                    index,
+                    expected_tx_hash: node_receipt.map(|receipt| receipt.transaction_hash),
+                    found_tx_hash: tx_hashes.get(index).copied(),
+                    fields: Vec::new(),
+                });
+            }
+        };
+
+        let payload = &receipt.payload;
+        let gas_used = payload.cumulative_gas_used - prev_cumulative_gas_used;
+        prev_cumulative_gas_used = payload.cumulative_gas_used;
+        let mut fields = Vec::new();
+        let expected_success = node_receipt.status == U64::from(1);
+        if payload.success != expected_success {
+            fields.push(FieldDiff::new("status", expected_success, payload.success));
+        }
+        if gas_used != node_receipt.gas_used {
+            fields.push(FieldDiff::new("gas_used", node_receipt.gas_used, gas_used));
+        }
+        let logs_match = payload.logs.len() == node_receipt.logs.len()
+            && payload
+                .logs
+                .iter()
+                .zip(&node_receipt.logs)
+                .all(|(log, node_log)| {
+                    log.address == node_log.address
+                        && log.topics == node_log.topics
+                        && log.data == node_log.data
+                });
+        if !logs_match {
+            fields.push(FieldDiff::new(
+                "logs",
+                &node_receipt.logs,
+                payload
+                    .logs
+                    .iter()
+                    .map(|log| (log.address, &log.topics, &log.data))
+                    .collect::<Vec<_>>(),
+            ));
+        }
+
+        let tx_hash = tx_hashes.get(index).copied();
+        if !fields.is_empty() || tx_hash != Some(node_receipt.transaction_hash) {
+            return Some(TxDiff {
+                index,
+                expected_tx_hash: Some(node_receipt.transaction_hash),
+                found_tx_hash: tx_hash,
+                fields,
+            });
+        }
+    }
+    None
+}
+
+impl Raiko {
+    /// Builds the block of a mismatching input again and compares the receipts and the
+    /// post-execution state against the block on the node, to find where the execution
+    /// diverged.
+    pub async fn diagnose(
+        &self,
+        input: &GuestInput,
+        mut report: MismatchReport,
+    ) -> RaikoResult<MismatchReport> {
+        let BlockBuildOutput {
+            db,
+            tx_hashes,
+            receipts,
+            ..
+        } = ChainStrategy::build_with_output(input)
+            .map_err(|e| RaikoError::Preflight(format!("Failed to build the block: {e}")))?;
+        let block_hash = input.block_hash_reference;
+        let provider = RpcBlockDataProvider::new(&self.taiko_chain_spec.rpc, input.block_number)?;
+
+        info!("Comparing the receipts of block {} ...", input.block_number);
+        let node_receipts = provider.get_block_receipts(block_hash).await?;
+        report.first_diverging_tx = compare_receipts(&tx_hashes, &receipts, &node_receipts);
+
+        info!("Comparing the state of block {} ...", input.block_number);
+        let touched: HashMap<Address, Vec<U256>> = db
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.state != AccountState::None)
+            .map(|(address, account)| (*address, account.storage.keys().copied().collect()))
+            .collect();
+        let num_storage_proofs = touched.values().map(Vec::len).sum();
+        let proofs = provider
+            .get_merkle_proofs(block_hash, touched, 0, num_storage_proofs)
+            .await?;
+        for (address, proof) in &proofs {
+            let Some(account) = db.accounts.get(address) else {
+                continue;
+            };
+            // Deleted accounts don't exist on the node either
+            let info = account.info().unwrap_or_default();
+            // Some nodes return a zero code hash for accounts that don't exist
+            let expected_code_hash = if proof.code_hash == B256::ZERO {
+                KECCAK_EMPTY
+            } else {
+                proof.code_hash
+            };
+            let mut fields = Vec::new();
+            if U256::from(proof.nonce) != U256::from(info.nonce) {
+                fields.push(FieldDiff::new("nonce", proof.nonce, info.nonce));
+            }
+            if proof.balance != info.balance {
+                fields.push(FieldDiff::new("balance", proof.balance, info.balance));
+            }
+            if expected_code_hash != info.code_hash {
+                fields.push(FieldDiff::new(
+                    "code_hash",
+                    expected_code_hash,
+                    info.code_hash,
+                ));
+            }
+            if !fields.is_empty() {
+                report.accounts.push(AccountDiff {
+                    address: *address,
+                    fields,
+                });
+            }
+            for storage_proof in &proof.storage_proof {
+                let slot = U256::from_be_bytes(storage_proof.key.0 .0);
+                let found = account.storage.get(&slot).copied().unwrap_or_default();
+                if storage_proof.value != found {
+                    report.storage.push(StorageDiff {
+                        address: *address,
+                        slot,
+                        expected: storage_proof.value,
+                        found,
+                    });
+                }
+            }
+        }
+        report.accounts.sort_by_key(|diff| diff.address);
+        report.storage.sort_by_key(|diff| (diff.address, diff.slot));
+        report.diagnosed = true;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raiko_primitives::receipt::Log;
+
+    use super::*;
+
+    fn node_receipt(tx_hash: u8, status: u64, cumulative: u64, gas: u64) -> NodeReceipt {
+        NodeReceipt {
+            transaction_hash: B256::with_last_byte(tx_hash),
+            status: U64::from(status),
+            cumulative_gas_used: U256::from(cumulative),
+            gas_used: U256::from(gas),
+            logs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compare_receipts() {
+        let tx_hashes = [B256::with_last_byte(1), B256::with_last_byte(2)];
+        let receipts = [
+            Receipt::new(2, true, U256::from(21_000), Vec::new()),
+            Receipt::new(2, true, U256::from(50_000), Vec::new()),
+        ];
+        let node_receipts = [
+            node_receipt(1, 1, 21_000, 21_000),
+            node_receipt(2, 1, 50_000, 29_000),
+        ];
+        assert_eq!(
+            compare_receipts(&tx_hashes, &receipts, &node_receipts),
+            None
+        );
+
+        // The second transaction used a different amount of gas and failed on the node
+        let node_receipts = [
+            node_receipt(1, 1, 21_000, 21_000),
+            node_receipt(2, 0, 45_000, 24_000),
+        ];
+        let diff = compare_receipts(&tx_hashes, &receipts, &node_receipts).unwrap();
+        assert_eq!(diff.index, 1);
+        let fields: Vec<_> = diff.fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, ["status", "gas_used"]);
+
+        // Different logs
+        let receipts = [Receipt::new(
+            2,
+            true,
+            U256::from(21_000),
+            vec![Log {
+                address: Address::with_last_byte(1),
+                topics: Vec::new(),
+                data: Bytes::new(),
+            }],
+        )];
+        let diff = compare_receipts(&tx_hashes, &receipts, &node_receipts[..1]).unwrap();
+        assert_eq!(diff.index, 0);
+        assert_eq!(diff.fields[0].field, "logs");
+
+        // The node included a transaction we skipped
+        let receipts = [Receipt::new(2, true, U256::from(21_000), Vec::new())];
+        let diff = compare_receipts(&tx_hashes[..1], &receipts, &node_receipts).unwrap();
+        assert_eq!(diff.index, 1);
+        assert_eq!(diff.expected_tx_hash, Some(B256::with_last_byte(2)));
+        assert_eq!(diff.found_tx_hash, None);
+    }
+}
@@ -0,0 +1,268 @@
+use std::str::FromStr;
+
+use alloy_primitives::{B256, U256};
+use raiko_lib::{
+    builder::{
+        trace::{TraceConfig, TraceStyle, TxTrace},
+        ChainStrategy,
+    },
+    input::GuestInput,
+    prover::ProverError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    diagnostics::FieldDiff,
+    interfaces::{RaikoError, RaikoResult},
+};
+
+/// The number of matching steps before a divergence included in the report.
+const CONTEXT_STEPS: usize = 3;
+
+/// An executed opcode, in the fields both the EIP-3155 traces of raiko and the struct logs
+/// of geth have.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub pc: u64,
+    pub op: String,
+    /// The gas left before executing the opcode.
+    pub gas: u64,
+    pub depth: u64,
+    pub stack: Vec<U256>,
+}
+
+/// Reads a number that is either a JSON number or a hex string.
+fn parse_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(hex) => u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok(),
+        _ => None,
+    }
+}
+
+fn parse_stack(value: &Value) -> Option<Vec<U256>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|item| U256::from_str(item.as_str()?).ok())
+        .collect()
+}
+
+impl TraceStep {
+    /// Parses a step of an EIP-3155 trace. Returns `None` for the summary of the transaction.
+    pub fn from_eip3155(step: &Value) -> Option<Self> {
+        Some(Self {
+            pc: parse_u64(step.get("pc")?)?,
+            op: step.get("opName")?.as_str()?.to_owned(),
+            gas: parse_u64(step.get("gas")?)?,
+            depth: parse_u64(step.get("depth")?)?,
+            stack: parse_stack(step.get("stack")?)?,
+        })
+    }
+
+    /// Parses a step of the struct logs returned by `debug_traceTransaction` of geth.
+    pub fn from_struct_log(step: &Value) -> Option<Self> {
+        Some(Self {
+            pc: parse_u64(step.get("pc")?)?,
+            op: step.get("op")?.as_str()?.to_owned(),
+            gas: parse_u64(step.get("gas")?)?,
+            depth: parse_u64(step.get("depth")?)?,
+            stack: step
+                .get("stack")
+                .map(parse_stack)
+                .unwrap_or(Some(Vec::new()))?,
+        })
+    }
+}
+
+/// The first opcode executed differently by raiko and the reference.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Divergence {
+    /// The index of the transaction in the block.
+    pub tx_index: usize,
+    pub tx_hash: Option<B256>,
+    /// The index of the diverging step in the trace of the transaction.
+    pub step: usize,
+    /// The differing values, with the value of the reference as the expected one.
+    pub diff: FieldDiff,
+    /// The steps before the divergence, on which both agree.
+    pub context: Vec<TraceStep>,
+    /// The diverging step of raiko, if it executed one.
+    pub ours: Option<TraceStep>,
+    /// The diverging step of the reference, if it executed one.
+    pub reference: Option<TraceStep>,
+}
+
+/// The result of comparing the execution of a block against the reference.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifferentialReport {
+    pub block_number: u64,
+    pub block_hash: B256,
+    /// The number of compared transactions.
+    pub num_txs: usize,
+    pub divergence: Option<Divergence>,
+}
+
+/// Returns the first step, and the field of it, where the traces differ.
+pub fn compare_steps(ours: &[TraceStep], reference: &[TraceStep]) -> Option<(usize, FieldDiff)> {
+    for index in 0..ours.len().max(reference.len()) {
+        let (ours, reference) = match (ours.get(index), reference.get(index)) {
+            (Some(ours), Some(reference)) => (ours, reference),
+            (ours, reference) => {
+                return Some((
+                    index,
+                    FieldDiff::new(
+                        "end_of_trace",
+                        reference.map(|step| &step.op),
+                        ours.map(|step| &step.op),
+                    ),
+                ))
+            }
+        };
+        let diff = if ours.pc != reference.pc {
+            FieldDiff::new("pc", reference.pc, ours.pc)
+        } else if ours.op != reference.op {
+            FieldDiff::new("op", &reference.op, &ours.op)
+        } else if ours.depth != reference.depth {
+            FieldDiff::new("depth", reference.depth, ours.depth)
+        } else if ours.gas != reference.gas {
+            FieldDiff::new("gas", reference.gas, ours.gas)
+        } else if ours.stack != reference.stack {
+            FieldDiff::new("stack", &reference.stack, &ours.stack)
+        } else {
+            continue;
+        };
+        return Some((index, diff));
+    }
+    None
+}
+
+/// Returns the steps of the transaction traced with `debug_traceBlockByHash`.
+fn reference_steps(trace: &Value) -> Option<Vec<TraceStep>> {
+    // Some nodes wrap the trace of every transaction in `result`
+    let trace = trace.get("result").unwrap_or(trace);
+    trace
+        .get("structLogs")?
+        .as_array()?
+        .iter()
+        .map(TraceStep::from_struct_log)
+        .collect()
+}
+
+fn our_steps(trace: &TxTrace) -> Vec<TraceStep> {
+    trace
+        .trace
+        .as_array()
+        .map(|steps| steps.iter().filter_map(TraceStep::from_eip3155).collect())
+        .unwrap_or_default()
+}
+
+/// Executes the block of the input with EIP-3155 tracing and compares every transaction
+/// opcode by opcode against the reference traces of the node, the result of
+/// `debug_traceBlockByHash` for the block.
+pub fn diff_block(input: &GuestInput, reference: &[Value]) -> RaikoResult<DifferentialReport> {
+    let config = TraceConfig {
+        style: TraceStyle::Eip3155,
+        tx_indices: Vec::new(),
+    };
+    let traces = ChainStrategy::trace(input, &config)
+        .map_err(|e| RaikoError::Guest(ProverError::GuestError(e.to_string())))?;
+
+    let mut report = DifferentialReport {
+        block_number: input.block_number,
+        block_hash: input.block_hash_reference,
+        num_txs: traces.len().max(reference.len()),
+        divergence: None,
+    };
+    for tx_index in 0..report.num_txs {
+        let ours = traces.get(tx_index);
+        let reference_trace = reference.get(tx_index);
+        let tx_hash = ours.map(|trace| trace.tx_hash).or_else(|| {
+            reference_trace
+                .and_then(|trace| trace.get("txHash"))
+                .and_then(|hash| serde_json::from_value(hash.clone()).ok())
+        });
+        let ours = ours.map(our_steps).unwrap_or_default();
+        let reference_steps = match reference_trace {
+            Some(trace) => reference_steps(trace).ok_or_else(|| {
+                RaikoError::RPC(format!(
+                    "Invalid reference trace of tx {tx_index} in block {}",
+                    input.block_number
+                ))
+            })?,
+            None => Vec::new(),
+        };
+        if let Some((step, diff)) = compare_steps(&ours, &reference_steps) {
+            report.divergence = Some(Divergence {
+                tx_index,
+                tx_hash,
+                step,
+                diff,
+                context: ours[step.saturating_sub(CONTEXT_STEPS)..step.min(ours.len())].to_vec(),
+                ours: ours.get(step).cloned(),
+                reference: reference_steps.get(step).cloned(),
+            });
+            break;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_steps() {
+        let eip3155 = json!({
+            "pc": 2, "op": 96, "gas": "0x5208", "gasCost": "0x3", "stack": ["0x1"],
+            "depth": 1, "opName": "PUSH1"
+        });
+        let struct_log = json!({
+            "pc": 2, "op": "PUSH1", "gas": 21000, "gasCost": 3, "depth": 1, "stack": ["0x1"]
+        });
+        let step = TraceStep::from_eip3155(&eip3155).unwrap();
+        assert_eq!(step, TraceStep::from_struct_log(&struct_log).unwrap());
+        assert_eq!(step.gas, 21000);
+        assert_eq!(step.stack, [U256::from(1)]);
+
+        // The summary of an EIP-3155 trace isn't a step
+        let summary = json!({ "output": "0x", "gasUsed": "0x5208", "pass": true });
+        assert_eq!(TraceStep::from_eip3155(&summary), None);
+    }
+
+    #[test]
+    fn test_compare_steps() {
+        let step = |pc: u64, op: &str, gas: u64| TraceStep {
+            pc,
+            op: op.to_owned(),
+            gas,
+            depth: 1,
+            stack: Vec::new(),
+        };
+        let reference = [
+            step(0, "PUSH1", 100),
+            step(2, "SLOAD", 97),
+            step(3, "STOP", 0),
+        ];
+        assert_eq!(compare_steps(&reference, &reference), None);
+
+        // Different gas for the SLOAD shows up at the step after it
+        let ours = [
+            step(0, "PUSH1", 100),
+            step(2, "SLOAD", 97),
+            step(3, "STOP", 2000),
+        ];
+        let (index, diff) = compare_steps(&ours, &reference).unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(diff, FieldDiff::new("gas", 0u64, 2000u64));
+
+        // We stopped early
+        let (index, diff) = compare_steps(&ours[..2], &reference).unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(diff.field, "end_of_trace");
+    }
+}
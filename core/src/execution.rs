@@ -0,0 +1,81 @@
+use alloy_primitives::B256;
+use raiko_lib::{
+    builder::{BlockBuildOutput, ChainStrategy, SkippedTx},
+    input::GuestInput,
+    mem_db::AccountChange,
+    prover::ProverError,
+    utils::HeaderHasher,
+};
+use raiko_primitives::receipt::Receipt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    Raiko,
+};
+
+/// A transaction included in the block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutedTx {
+    /// The index of the transaction in the block.
+    pub index: usize,
+    pub tx_hash: B256,
+    pub gas_used: u64,
+    pub receipt: Receipt,
+}
+
+/// The transactions included in and dropped from a block, and the state they changed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockExecution {
+    pub block_number: u64,
+    /// The hash of the built block.
+    pub block_hash: B256,
+    /// Whether the built block is the block on the node.
+    pub matches_node: bool,
+    pub transactions: Vec<ExecutedTx>,
+    /// The transactions of the tx list that the Taiko rules dropped from the block.
+    pub skipped: Vec<SkippedTx>,
+    /// The accounts changed by the block.
+    pub state_changes: Vec<AccountChange>,
+}
+
+impl BlockExecution {
+    pub fn new(
+        input: &GuestInput,
+        output: BlockBuildOutput,
+        state_changes: Vec<AccountChange>,
+    ) -> Self {
+        let block_hash = output.header.hash();
+        let transactions = output
+            .tx_hashes
+            .into_iter()
+            .zip(output.gas_used)
+            .zip(output.receipts)
+            .enumerate()
+            .map(|(index, ((tx_hash, gas_used), receipt))| ExecutedTx {
+                index,
+                tx_hash,
+                gas_used,
+                receipt,
+            })
+            .collect();
+        Self {
+            block_number: input.block_number,
+            block_hash,
+            matches_node: block_hash == input.block_hash_reference,
+            transactions,
+            skipped: output.skipped,
+            state_changes,
+        }
+    }
+}
+
+impl Raiko {
+    /// Executes the block of the input and returns the transactions that were included and
+    /// skipped, and the state changes of the block.
+    pub fn get_execution(&self, input: &GuestInput) -> RaikoResult<BlockExecution> {
+        let (output, state_changes) = ChainStrategy::build_with_state_changes(input)
+            .map_err(|e| RaikoError::Guest(ProverError::GuestError(e.to_string())))?;
+        Ok(BlockExecution::new(input, output, state_changes))
+    }
+}
@@ -3,15 +3,16 @@ use std::{collections::HashMap, path::Path, str::FromStr};
 use alloy_primitives::{Address, B256};
 use clap::{Args, ValueEnum};
 use raiko_lib::{
-    input::{GuestInput, GuestOutput},
-    prover::{Proof, Prover, ProverError},
+    builder::trace::TraceConfig,
+    input::{BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput},
+    prover::{GuestEstimate, Proof, Prover, ProverError},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_with::{serde_as, DisplayFromStr};
 use utoipa::ToSchema;
 
-use crate::{merge, prover::NativeProver};
+use crate::{diagnostics::MismatchReport, merge, prover::NativeProver};
 
 #[derive(Debug, thiserror::Error, ToSchema)]
 pub enum RaikoError {
@@ -40,6 +41,20 @@ pub enum RaikoError {
     #[error("There was an error running the preflight: {0}")]
     Preflight(String),
 
+    /// For errors when fetching or verifying blob data.
+    #[error("There was an error fetching the blob data: {0}")]
+    Blob(String),
+
+    /// For blocks pinned during preflight that were reorged out. The request can be retried.
+    #[error("Block {0} is no longer part of the canonical chain, please retry")]
+    #[schema(value_type = Value)]
+    Reorg(B256),
+
+    /// For blocks that were built differently than the block on the node.
+    #[error("The built block does not match the node: {0}")]
+    #[schema(value_type = Value)]
+    BlockMismatch(Box<MismatchReport>),
+
     /// For errors produced by the guest provers.
     #[error("There was an error with a guest prover: {0}")]
     #[schema(value_type = Value)]
@@ -160,6 +175,139 @@ impl ProofType {
             }
         }
     }
+
+    /// Run the batch prover driver depending on the proof type.
+    pub async fn run_batch_prover(
+        &self,
+        batch: BatchGuestInput,
+        output: &BatchGuestOutput,
+        config: &Value,
+    ) -> RaikoResult<Proof> {
+        match self {
+            ProofType::Native => NativeProver::batch_run(batch, output, config)
+                .await
+                .map_err(|e| e.into()),
+            ProofType::Sp1 => {
+                #[cfg(feature = "sp1")]
+                return sp1_driver::Sp1Prover::batch_run(batch, output, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Risc0 => {
+                #[cfg(feature = "risc0")]
+                return risc0_driver::Risc0Prover::batch_run(batch, output, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Sgx => {
+                #[cfg(feature = "sgx")]
+                return sgx_prover::SgxProver::batch_run(batch, output, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+        }
+    }
+
+    /// Aggregates the proofs of multiple blocks created with the proof type into a single
+    /// proof. Only supported by RISC0.
+    pub async fn aggregate(
+        &self,
+        proofs: Vec<(GuestOutput, Proof)>,
+        config: &Value,
+    ) -> RaikoResult<Proof> {
+        match self {
+            ProofType::Risc0 => {
+                #[cfg(feature = "risc0")]
+                return {
+                    let receipts = proofs
+                        .into_iter()
+                        .map(|(output, proof)| {
+                            risc0_driver::BlockReceipt::from_proof(output, &proof)
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    risc0_driver::Risc0Prover::aggregate(receipts, config)
+                        .await
+                        .map_err(|e| e.into())
+                };
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Native | ProofType::Sp1 | ProofType::Sgx => {
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+        }
+    }
+
+    /// Run the guest program of the proof type without proving it, to estimate the proving
+    /// resources. Only supported by the zkVM provers.
+    pub async fn estimate(&self, input: GuestInput, config: &Value) -> RaikoResult<GuestEstimate> {
+        match self {
+            ProofType::Sp1 => {
+                #[cfg(feature = "sp1")]
+                return sp1_driver::Sp1Prover::estimate(input, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Risc0 => {
+                #[cfg(feature = "risc0")]
+                return risc0_driver::Risc0Prover::estimate(input, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Native | ProofType::Sgx => {
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+        }
+    }
+
+    /// Verify a proof of the proof type against the expected output of the input.
+    pub async fn verify(
+        &self,
+        input: GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+        config: &Value,
+    ) -> RaikoResult<()> {
+        match self {
+            ProofType::Native => NativeProver::verify(input, output, proof, config)
+                .await
+                .map_err(|e| e.into()),
+            ProofType::Sp1 => {
+                #[cfg(feature = "sp1")]
+                return sp1_driver::Sp1Prover::verify(input, output, proof, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Risc0 => {
+                #[cfg(feature = "risc0")]
+                return risc0_driver::Risc0Prover::verify(input, output, proof, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+            ProofType::Sgx => {
+                #[cfg(feature = "sgx")]
+                return sgx_prover::SgxProver::verify(input, output, proof, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
+        }
+    }
 }
 
 #[serde_as]
@@ -206,6 +354,19 @@ pub struct ProofRequestOpt {
     #[arg(long, require_equals = true)]
     /// The proof type.
     pub proof_type: Option<String>,
+    #[arg(long, require_equals = true)]
+    /// Whether to also return the `proveBlock` calldata to submit the proof with.
+    pub calldata: Option<bool>,
+    #[arg(long, require_equals = true)]
+    /// Whether to compare the receipts and the state against the node when the built block
+    /// doesn't match, to report where the execution diverged.
+    pub diagnose: Option<bool>,
+    #[arg(long, require_equals = true)]
+    #[schema(value_type = Option<Value>)]
+    /// The transactions to trace while executing the block, e.g.
+    /// `{"style": "call_tracer", "tx_indices": [1, 2]}`. The style is `eip3155` or
+    /// `call_tracer`, and all transactions are traced without `tx_indices`.
+    pub trace: Option<TraceConfig>,
     #[command(flatten)]
     #[serde(flatten)]
     /// Any additional prover params in JSON format.
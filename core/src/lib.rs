@@ -1,27 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use alloy_primitives::{Address, FixedBytes};
+use alloy_consensus::Header as AlloyConsensusHeader;
+use alloy_primitives::Address;
 use alloy_rpc_types::EIP1186AccountProofResponse;
 use raiko_lib::{
-    builder::{BlockBuilderStrategy, TaikoStrategy},
+    builder::{
+        trace::{TraceConfig, TxTrace},
+        ChainStrategy,
+    },
     consts::{ChainSpec, VerifierType},
-    input::{GuestInput, GuestOutput, TaikoProverData},
-    protocol_instance::ProtocolInstance,
-    prover::Proof,
+    input::{BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput, TaikoProverData},
+    protocol_instance::{BatchProtocolInstance, ProtocolInstance},
+    prover::{GuestEstimate, Proof},
     utils::HeaderHasher,
 };
 use serde_json::Value;
 use tracing::{error, info, warn};
 
+pub mod calldata;
+pub mod contest;
+pub mod diagnostics;
+pub mod differential;
+pub mod execution;
 pub mod interfaces;
 pub mod preflight;
 pub mod prover;
 pub mod provider;
 
 use crate::{
-    interfaces::{ProofRequest, RaikoError, RaikoResult},
-    preflight::preflight,
-    provider::BlockDataProvider,
+    diagnostics::{FieldDiff, MismatchReport},
+    interfaces::{ProofRequest, ProofType, RaikoError, RaikoResult},
+    preflight::{batch_preflight, preflight, PreflightReport},
+    provider::{blob::BlobSource, BlockDataProvider},
 };
 
 pub type MerkleProof = HashMap<Address, EIP1186AccountProofResponse>;
@@ -30,6 +40,7 @@ pub struct Raiko {
     l1_chain_spec: ChainSpec,
     taiko_chain_spec: ChainSpec,
     request: ProofRequest,
+    blob_sources: Vec<BlobSource>,
 }
 
 impl Raiko {
@@ -42,13 +53,21 @@ impl Raiko {
             l1_chain_spec,
             taiko_chain_spec,
             request,
+            blob_sources: Vec::new(),
         }
     }
 
+    /// Fetches the blobs of the L1 chain from `blob_sources` in fallback order instead of only
+    /// from the `beacon_rpc` of the L1 chain spec.
+    pub fn with_blob_sources(mut self, blob_sources: Vec<BlobSource>) -> Self {
+        self.blob_sources = blob_sources;
+        self
+    }
+
     pub async fn generate_input<BDP: BlockDataProvider>(
         &self,
         provider: BDP,
-    ) -> RaikoResult<GuestInput> {
+    ) -> RaikoResult<(GuestInput, PreflightReport)> {
         preflight(
             provider,
             self.request.block_number,
@@ -58,13 +77,14 @@ impl Raiko {
                 graffiti: self.request.graffiti,
                 prover: self.request.prover,
             },
+            &self.blob_sources,
         )
         .await
         .map_err(Into::<RaikoError>::into)
     }
 
     pub fn get_output(&self, input: &GuestInput) -> RaikoResult<GuestOutput> {
-        match TaikoStrategy::build_from(input) {
+        match ChainStrategy::build_from(input) {
             Ok((header, _mpt_node)) => {
                 info!("Verifying final state using provider data ...");
                 info!("Final block hash derived successfully. {}", header.hash());
@@ -72,59 +92,18 @@ impl Raiko {
                 let pi = ProtocolInstance::new(input, &header, VerifierType::None)?.instance_hash();
 
                 // Check against the expected value of all fields for easy debugability
-                let exp = &input.block_header_reference;
-                check_eq(&exp.parent_hash, &header.parent_hash, "base_fee_per_gas");
-                check_eq(&exp.ommers_hash, &header.ommers_hash, "ommers_hash");
-                check_eq(&exp.beneficiary, &header.beneficiary, "beneficiary");
-                check_eq(&exp.state_root, &header.state_root, "state_root");
-                check_eq(
-                    &exp.transactions_root,
-                    &header.transactions_root,
-                    "transactions_root",
-                );
-                check_eq(&exp.receipts_root, &header.receipts_root, "receipts_root");
-                check_eq(
-                    &exp.withdrawals_root,
-                    &header.withdrawals_root,
-                    "withdrawals_root",
-                );
-                check_eq(&exp.logs_bloom, &header.logs_bloom, "logs_bloom");
-                check_eq(&exp.difficulty, &header.difficulty, "difficulty");
-                check_eq(&exp.number, &header.number, "number");
-                check_eq(&exp.gas_limit, &header.gas_limit, "gas_limit");
-                check_eq(&exp.gas_used, &header.gas_used, "gas_used");
-                check_eq(&exp.timestamp, &header.timestamp, "timestamp");
-                check_eq(&exp.mix_hash, &header.mix_hash, "mix_hash");
-                check_eq(&exp.nonce, &header.nonce, "nonce");
-                check_eq(
-                    &exp.base_fee_per_gas,
-                    &header.base_fee_per_gas,
-                    "base_fee_per_gas",
-                );
-                check_eq(&exp.blob_gas_used, &header.blob_gas_used, "blob_gas_used");
-                check_eq(
-                    &exp.excess_blob_gas,
-                    &header.excess_blob_gas,
-                    "excess_blob_gas",
-                );
-                check_eq(
-                    &exp.parent_beacon_block_root,
-                    &header.parent_beacon_block_root,
-                    "parent_beacon_block_root",
-                );
-                check_eq(
-                    &exp.extra_data.clone(),
-                    &header.extra_data.clone(),
-                    "extra_data",
-                );
+                let header_diffs = check_header(&input.block_header_reference, &header);
 
                 // Make sure the blockhash from the node matches the one from the builder
-                assert_eq!(
-                    Into::<FixedBytes<32>>::into(header.hash().0),
-                    input.block_hash_reference,
-                    "block hash unexpected for block {}",
-                    input.block_number,
-                );
+                if header.hash() != input.block_hash_reference {
+                    return Err(RaikoError::BlockMismatch(Box::new(MismatchReport {
+                        block_number: input.block_number,
+                        expected_hash: input.block_hash_reference,
+                        found_hash: header.hash(),
+                        header: header_diffs,
+                        ..Default::default()
+                    })));
+                }
                 let output = GuestOutput::Success { header, hash: pi };
 
                 Ok(output)
@@ -138,6 +117,73 @@ impl Raiko {
         }
     }
 
+    /// Like [Raiko::get_output], but when the built block doesn't match the block on the node
+    /// the receipts and the state are compared against the node to report where the execution
+    /// diverged.
+    pub async fn get_output_with_diagnostics(
+        &self,
+        input: &GuestInput,
+    ) -> RaikoResult<GuestOutput> {
+        match self.get_output(input) {
+            Err(RaikoError::BlockMismatch(report)) => Err(RaikoError::BlockMismatch(Box::new(
+                self.diagnose(input, *report).await?,
+            ))),
+            output => output,
+        }
+    }
+
+    /// Executes the block of the input again and returns the traces of the transactions
+    /// selected by `config`.
+    pub fn trace(&self, input: &GuestInput, config: &TraceConfig) -> RaikoResult<Vec<TxTrace>> {
+        ChainStrategy::trace(input, config).map_err(|e| {
+            RaikoError::Guest(raiko_lib::prover::ProverError::GuestError(e.to_string()))
+        })
+    }
+
+    /// Generates the input to prove `num_blocks` consecutive blocks, starting at the requested
+    /// block, in a single proof.
+    pub async fn generate_batch_input<BDP: BlockDataProvider>(
+        &self,
+        provider: BDP,
+        num_blocks: u64,
+    ) -> RaikoResult<(BatchGuestInput, PreflightReport)> {
+        batch_preflight(
+            provider,
+            self.request.block_number,
+            num_blocks,
+            self.l1_chain_spec.clone(),
+            self.taiko_chain_spec.clone(),
+            TaikoProverData {
+                graffiti: self.request.graffiti,
+                prover: self.request.prover,
+            },
+            &self.blob_sources,
+        )
+        .await
+    }
+
+    pub fn get_batch_output(&self, batch: &BatchGuestInput) -> RaikoResult<BatchGuestOutput> {
+        let headers = ChainStrategy::build_batch_from(batch).map_err(|e| {
+            warn!("Proving bad batch construction!");
+            RaikoError::Guest(raiko_lib::prover::ProverError::GuestError(e.to_string()))
+        })?;
+        for (input, header) in batch.inputs.iter().zip(&headers) {
+            info!("Block {} of the batch derived successfully.", header.number);
+            let header_diffs = check_header(&input.block_header_reference, header);
+            if header.hash() != input.block_hash_reference {
+                return Err(RaikoError::BlockMismatch(Box::new(MismatchReport {
+                    block_number: input.block_number,
+                    expected_hash: input.block_hash_reference,
+                    found_hash: header.hash(),
+                    header: header_diffs,
+                    ..Default::default()
+                })));
+            }
+        }
+        let hash = BatchProtocolInstance::new(batch, &headers, VerifierType::None)?.instance_hash();
+        Ok(BatchGuestOutput::Success { headers, hash })
+    }
+
     pub async fn prove(&self, input: GuestInput, output: &GuestOutput) -> RaikoResult<Proof> {
         self.request
             .proof_type
@@ -148,12 +194,136 @@ impl Raiko {
             )
             .await
     }
+
+    /// Proves all blocks of the batch in a single proof of the requested proof type.
+    pub async fn prove_batch(
+        &self,
+        batch: BatchGuestInput,
+        output: &BatchGuestOutput,
+    ) -> RaikoResult<Proof> {
+        self.request
+            .proof_type
+            .run_batch_prover(batch, output, &serde_json::to_value(self.request.clone())?)
+            .await
+    }
+
+    /// Aggregates the proofs of multiple blocks, together with their outputs, into a single
+    /// proof of the requested proof type.
+    pub async fn aggregate(&self, proofs: Vec<(GuestOutput, Proof)>) -> RaikoResult<Proof> {
+        self.request
+            .proof_type
+            .aggregate(proofs, &serde_json::to_value(self.request.clone())?)
+            .await
+    }
+
+    /// Verifies a proof of the requested proof type against the expected output of the input.
+    ///
+    /// The L1 RPC is passed on to the verifier as `l1_rpc` so that proofs can be checked
+    /// against state on L1, like the registered SGX instances.
+    pub async fn verify(
+        &self,
+        input: GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+    ) -> RaikoResult<()> {
+        let mut config = serde_json::to_value(self.request.clone())?;
+        config["l1_rpc"] = self.l1_chain_spec.rpc.clone().into();
+        self.request
+            .proof_type
+            .verify(input, output, proof, &config)
+            .await
+    }
+
+    /// Estimates the resources needed to prove the input with every zkVM prover enabled in
+    /// this build, independent of the requested proof type.
+    pub async fn estimate(
+        &self,
+        input: GuestInput,
+    ) -> RaikoResult<BTreeMap<String, GuestEstimate>> {
+        let config = serde_json::to_value(self.request.clone())?;
+        let mut estimates = BTreeMap::new();
+        for proof_type in [ProofType::Risc0, ProofType::Sp1] {
+            match proof_type.estimate(input.clone(), &config).await {
+                Ok(estimate) => {
+                    estimates.insert(proof_type.to_string(), estimate);
+                }
+                Err(RaikoError::FeatureNotSupportedError(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if estimates.is_empty() {
+            return Err(RaikoError::InvalidRequestConfig(
+                "No zkVM prover enabled to estimate with".to_owned(),
+            ));
+        }
+        Ok(estimates)
+    }
+}
+
+/// Checks the built header against the expected value of all fields for easy debugability,
+/// and returns the fields that differ.
+fn check_header(exp: &AlloyConsensusHeader, header: &AlloyConsensusHeader) -> Vec<FieldDiff> {
+    [
+        check_eq(&exp.parent_hash, &header.parent_hash, "parent_hash"),
+        check_eq(&exp.ommers_hash, &header.ommers_hash, "ommers_hash"),
+        check_eq(&exp.beneficiary, &header.beneficiary, "beneficiary"),
+        check_eq(&exp.state_root, &header.state_root, "state_root"),
+        check_eq(
+            &exp.transactions_root,
+            &header.transactions_root,
+            "transactions_root",
+        ),
+        check_eq(&exp.receipts_root, &header.receipts_root, "receipts_root"),
+        check_eq(
+            &exp.withdrawals_root,
+            &header.withdrawals_root,
+            "withdrawals_root",
+        ),
+        check_eq(&exp.logs_bloom, &header.logs_bloom, "logs_bloom"),
+        check_eq(&exp.difficulty, &header.difficulty, "difficulty"),
+        check_eq(&exp.number, &header.number, "number"),
+        check_eq(&exp.gas_limit, &header.gas_limit, "gas_limit"),
+        check_eq(&exp.gas_used, &header.gas_used, "gas_used"),
+        check_eq(&exp.timestamp, &header.timestamp, "timestamp"),
+        check_eq(&exp.mix_hash, &header.mix_hash, "mix_hash"),
+        check_eq(&exp.nonce, &header.nonce, "nonce"),
+        check_eq(
+            &exp.base_fee_per_gas,
+            &header.base_fee_per_gas,
+            "base_fee_per_gas",
+        ),
+        check_eq(&exp.blob_gas_used, &header.blob_gas_used, "blob_gas_used"),
+        check_eq(
+            &exp.excess_blob_gas,
+            &header.excess_blob_gas,
+            "excess_blob_gas",
+        ),
+        check_eq(
+            &exp.parent_beacon_block_root,
+            &header.parent_beacon_block_root,
+            "parent_beacon_block_root",
+        ),
+        check_eq(
+            &exp.extra_data.clone(),
+            &header.extra_data.clone(),
+            "extra_data",
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
 }
 
-fn check_eq<T: std::cmp::PartialEq + std::fmt::Debug>(expected: &T, actual: &T, message: &str) {
+fn check_eq<T: std::cmp::PartialEq + std::fmt::Debug>(
+    expected: &T,
+    actual: &T,
+    message: &str,
+) -> Option<FieldDiff> {
     if expected != actual {
         error!("Assertion failed: {message} - Expected: {expected:?}, Found: {actual:?}");
+        return Some(FieldDiff::new(message, expected, actual));
     }
+    None
 }
 
 /// Merges two json's together, overwriting `a` with the values of `b`
@@ -179,10 +349,16 @@ mod tests {
     };
     use alloy_primitives::Address;
     use clap::ValueEnum;
-    use raiko_lib::consts::{Network, SupportedChainSpecs};
+    use raiko_lib::{
+        consts::{Network, SupportedChainSpecs, VerifierType},
+        input::{get_input_path, BlockProposedFork, GuestInput, GuestOutput},
+        input_file::{self, Compression},
+        protocol_instance::ProtocolInstance,
+    };
     use raiko_primitives::B256;
+    use rstest::rstest;
     use serde_json::{json, Value};
-    use std::{collections::HashMap, env};
+    use std::{collections::HashMap, env, path::PathBuf};
 
     fn get_proof_type_from_env() -> ProofType {
         let proof_type = env::var("TARGET").unwrap_or("native".to_string());
@@ -230,7 +406,7 @@ mod tests {
             RpcBlockDataProvider::new(&taiko_chain_spec.rpc, proof_request.block_number - 1)
                 .expect("Could not create RpcBlockDataProvider");
         let raiko = Raiko::new(l1_chain_spec, taiko_chain_spec, proof_request.clone());
-        let mut input = raiko
+        let (mut input, _) = raiko
             .generate_input(provider)
             .await
             .expect("input generation failed");
@@ -297,4 +473,126 @@ mod tests {
             prove_block(l1_chain_spec, taiko_chain_spec, proof_request).await;
         }
     }
+
+    /// Directory with cached inputs of real blocks. Missing fixtures are fetched from the RPC
+    /// of the chain spec and stored, commit them so later runs don't need a node.
+    fn fixtures_dir(l1_network: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/fixtures/{l1_network}"))
+    }
+
+    async fn get_l1_fixture(network: &str, block_number: u64) -> GuestInput {
+        get_fixture(network, network, block_number).await
+    }
+
+    async fn get_fixture(l1_network: &str, network: &str, block_number: u64) -> GuestInput {
+        let dir = fixtures_dir(if l1_network == network {
+            "l1"
+        } else {
+            l1_network
+        });
+        let path = get_input_path(&dir, block_number, network);
+        if let Ok(bytes) = std::fs::read(&path) {
+            return input_file::decode(&bytes).expect("invalid fixture").1;
+        }
+
+        let chain_specs = SupportedChainSpecs::default();
+        let chain_spec = chain_specs.get_chain_spec(network).unwrap();
+        let l1_chain_spec = chain_specs.get_chain_spec(l1_network).unwrap();
+        let proof_request = ProofRequest {
+            block_number,
+            network: network.to_string(),
+            graffiti: B256::ZERO,
+            prover: Address::ZERO,
+            l1_network: l1_network.to_string(),
+            proof_type: ProofType::Native,
+            prover_args: test_proof_params(),
+        };
+        let provider = RpcBlockDataProvider::new(&chain_spec.rpc, block_number - 1)
+            .expect("Could not create RpcBlockDataProvider");
+        let raiko = Raiko::new(l1_chain_spec, chain_spec, proof_request);
+        let (input, _) = raiko
+            .generate_input(provider)
+            .await
+            .expect("input generation failed");
+
+        std::fs::create_dir_all(&dir).expect("Could not create fixtures dir");
+        let bytes = input_file::encode(&input, Compression::default()).expect("invalid input");
+        std::fs::write(&path, bytes).expect("Could not write fixture");
+        input
+    }
+
+    #[rstest]
+    #[case::ethereum_shanghai(Network::Ethereum, 17034870)]
+    #[case::ethereum_last_shanghai(Network::Ethereum, 19426586)]
+    #[case::ethereum_cancun(Network::Ethereum, 19426587)]
+    #[case::ethereum_cancun_blobs(Network::Ethereum, 19707175)]
+    #[case::holesky_merge(Network::Holesky, 1000)]
+    #[case::holesky_cancun(Network::Holesky, 1000000)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_l1_block_output(#[case] network: Network, #[case] block_number: u64) {
+        let network = network.to_string();
+        let input = get_l1_fixture(&network, block_number).await;
+        let raiko = Raiko::new(
+            input.chain_spec.clone(),
+            input.chain_spec.clone(),
+            ProofRequest {
+                block_number,
+                network: network.clone(),
+                graffiti: B256::ZERO,
+                prover: Address::ZERO,
+                l1_network: network,
+                proof_type: ProofType::Native,
+                prover_args: test_proof_params(),
+            },
+        );
+        let output = raiko.get_output(&input).expect("output generation failed");
+        let GuestOutput::Success { header, .. } = output else {
+            panic!("block building failed for block {block_number}");
+        };
+        assert_eq!(header, input.block_header_reference);
+    }
+
+    /// Builds real blocks proposed with the V2 (Ontake) protocol and checks their metadata
+    /// against the `BlockProposedV2` event and their anchor transaction.
+    #[rstest]
+    #[case::taiko_a7_first_ontake(Network::Holesky, Network::TaikoA7, 840512)]
+    #[case::taiko_a7_ontake(Network::Holesky, Network::TaikoA7, 850000)]
+    #[ignore = "needs a Taiko and an L1 node for the missing fixtures"]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_v2_block_output(
+        #[case] l1_network: Network,
+        #[case] network: Network,
+        #[case] block_number: u64,
+    ) {
+        let (l1_network, network) = (l1_network.to_string(), network.to_string());
+        let input = get_fixture(&l1_network, &network, block_number).await;
+        assert!(matches!(
+            input.taiko.block_proposed,
+            BlockProposedFork::V2(_)
+        ));
+        let raiko = Raiko::new(
+            SupportedChainSpecs::default()
+                .get_chain_spec(&l1_network)
+                .unwrap(),
+            input.chain_spec.clone(),
+            ProofRequest {
+                block_number,
+                network,
+                graffiti: B256::ZERO,
+                prover: Address::ZERO,
+                l1_network,
+                proof_type: ProofType::Native,
+                prover_args: test_proof_params(),
+            },
+        );
+        // Building the block checks the anchorV2 call, the protocol instance checks the
+        // metadata against the event
+        let output = raiko.get_output(&input).expect("output generation failed");
+        let GuestOutput::Success { header, .. } = output else {
+            panic!("block building failed for block {block_number}");
+        };
+        assert_eq!(header, input.block_header_reference);
+        ProtocolInstance::new(&input, &header, VerifierType::None)
+            .expect("the metadata does not match the BlockProposedV2 event");
+    }
 }
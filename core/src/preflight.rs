@@ -1,59 +1,306 @@
 use alloy_consensus::{
-    SignableTransaction, TxEip1559, TxEip2930, TxEip4844, TxEip4844Variant, TxEnvelope, TxLegacy,
+    Header as AlloyConsensusHeader, SignableTransaction, TxEip1559, TxEip2930, TxEip4844,
+    TxEip4844Variant, TxEnvelope, TxLegacy,
 };
 pub use alloy_primitives::*;
 use alloy_provider::{Provider, ReqwestProvider};
 use alloy_rpc_types::{Block, BlockTransactions, Filter, Transaction as AlloyRpcTransaction};
-use alloy_sol_types::{SolCall, SolEvent};
 use anyhow::{anyhow, bail, Result};
-use c_kzg::{Blob, KzgCommitment};
 use raiko_lib::{
-    builder::{
-        prepare::TaikoHeaderPrepStrategy, BlockBuilder, OptimisticDatabase, TkoTxExecStrategy,
-    },
-    consts::ChainSpec,
+    builder::{BlockBuilder, OptimisticDatabase},
+    consts::{ChainSpec, ProtocolVersion},
     input::{
-        decode_anchor, proposeBlockCall, BlockProposed, GuestInput, TaikoGuestInput,
-        TaikoProverData,
+        decode_anchor, AnchorCallFork, BatchGuestInput, BlockProposedFork, GuestInput,
+        TaikoGuestInput, TaikoProverData,
     },
-    utils::{generate_transactions, to_header, zlib_compress_data},
+    utils::{generate_transactions, to_header},
     Measurement,
 };
-use raiko_primitives::{
-    eip4844::{kzg_to_versioned_hash, MAINNET_KZG_TRUSTED_SETUP},
-    mpt::proofs_to_tries,
-};
+use raiko_primitives::mpt::proofs_to_tries;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, sync::Arc};
-use tracing::{info, warn};
+use std::{collections::HashSet, mem::take};
+use tracing::info;
 
 use crate::{
     interfaces::{RaikoError, RaikoResult},
-    provider::{db::ProviderDb, rpc::RpcBlockDataProvider, BlockDataProvider},
+    provider::{
+        blob::{BlobSource, FallbackBlobProvider},
+        check_canonical, check_chain,
+        db::ProviderDb,
+        rpc::RpcBlockDataProvider,
+        BlockDataProvider,
+    },
 };
 
+/// The maximum number of L1 blocks between the L1 state block of a V2 block and the L1 block it
+/// is proposed in.
+const MAX_ANCHOR_HEIGHT_OFFSET: u64 = 64;
+
+/// Records where the data of a preflight came from, to be able to debug mismatches between the
+/// built block and the block on chain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreflightReport {
+    /// The RPC endpoint of the chain of the block.
+    pub rpc: String,
+    /// The RPC endpoint of the L1 chain, for Taiko blocks.
+    pub l1_rpc: Option<String>,
+    /// The hash of the block.
+    pub block_hash: B256,
+    /// The hash of the parent block the state was fetched at.
+    pub parent_hash: B256,
+    /// The hash of the L1 block the anchor transaction references, for Taiko blocks.
+    pub l1_state_block_hash: Option<B256>,
+    /// The hash of the L1 block the block was proposed in, for Taiko blocks.
+    pub l1_inclusion_block_hash: Option<B256>,
+    /// The source the blob with the tx list was fetched from, if a blob was used.
+    pub blob_source: Option<BlobSource>,
+    /// The data fetched in each optimistic execution iteration.
+    pub iterations: Vec<PreflightIteration>,
+    /// The number of account proofs fetched at the parent block.
+    pub num_parent_account_proofs: usize,
+    /// The number of account proofs fetched at the block.
+    pub num_account_proofs: usize,
+    /// The total number of storage proofs fetched.
+    pub num_storage_proofs: usize,
+    /// The number of ancestor headers in the input, to authenticate the block hashes read.
+    pub num_ancestor_headers: usize,
+    /// The time spent in each phase of the preflight.
+    pub timings: Vec<PhaseTiming>,
+}
+
+/// The data fetched after a single optimistic execution of the block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreflightIteration {
+    pub accounts: Vec<Address>,
+    pub slots: Vec<(Address, U256)>,
+    pub block_hashes: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub millis: u128,
+}
+
+impl PreflightReport {
+    fn add_timing(&mut self, phase: &str, duration: std::time::Duration) {
+        self.timings.push(PhaseTiming {
+            phase: phase.to_owned(),
+            millis: duration.as_millis(),
+        });
+    }
+}
+
 pub async fn preflight<BDP: BlockDataProvider>(
     provider: BDP,
     block_number: u64,
     l1_chain_spec: ChainSpec,
     taiko_chain_spec: ChainSpec,
     prover_data: TaikoProverData,
-) -> RaikoResult<GuestInput> {
+    blob_sources: &[BlobSource],
+) -> RaikoResult<(GuestInput, PreflightReport)> {
+    let (batch, report) = batch_preflight(
+        provider,
+        block_number,
+        1,
+        l1_chain_spec,
+        taiko_chain_spec,
+        prover_data,
+        blob_sources,
+    )
+    .await?;
+    let input = batch
+        .inputs
+        .into_iter()
+        .next()
+        .expect("A batch of one block has one input");
+    Ok((input, report))
+}
+
+/// Fetches the data to prove `num_blocks` consecutive blocks starting at `block_number`.
+///
+/// The blocks are executed one after the other on top of a single database at the state of the
+/// parent of the first block, so a single state witness is fetched that covers all blocks.
+/// The report describes the whole batch, the L1 and blob data in it are those of the last block.
+pub async fn batch_preflight<BDP: BlockDataProvider>(
+    mut provider: BDP,
+    block_number: u64,
+    num_blocks: u64,
+    l1_chain_spec: ChainSpec,
+    taiko_chain_spec: ChainSpec,
+    prover_data: TaikoProverData,
+    blob_sources: &[BlobSource],
+) -> RaikoResult<(BatchGuestInput, PreflightReport)> {
+    if num_blocks == 0 {
+        return Err(RaikoError::Preflight("Empty batch".to_owned()));
+    }
+    let mut report = PreflightReport {
+        rpc: taiko_chain_spec.rpc.clone(),
+        ..Default::default()
+    };
     let measurement = Measurement::start("Fetching block data...", false);
 
-    // Get the block and the parent block
+    // Get the blocks and the parent of the first block. The parent is fetched by hash and the
+    // blocks have to be linked to it so all of them are guaranteed to be on the same chain,
+    // all other data is pinned to these hashes.
     let blocks = provider
-        .get_blocks(&[(block_number, true), (block_number - 1, false)])
+        .get_blocks(
+            &(block_number..block_number + num_blocks)
+                .map(|block_number| (block_number, true))
+                .collect::<Vec<_>>(),
+        )
         .await?;
-    let (block, parent_block) = (
-        blocks.first().ok_or_else(|| {
-            RaikoError::Preflight("No block data for the requested block".to_owned())
-        })?,
-        &blocks.get(1).ok_or_else(|| {
-            RaikoError::Preflight("No parent block data for the requested block".to_owned())
-        })?,
-    );
+    let first_block = blocks
+        .first()
+        .ok_or_else(|| RaikoError::Preflight("No block data for the requested block".to_owned()))?;
+    let parent_hash = first_block.header.parent_hash;
+
+    let parent_blocks = provider.get_blocks_by_hash(&[(parent_hash, false)]).await?;
+    let parent_block = parent_blocks.first().ok_or_else(|| {
+        RaikoError::Preflight("No parent block data for the requested block".to_owned())
+    })?;
+    let mut chain = vec![parent_block.clone()];
+    chain.extend(blocks.iter().cloned());
+    check_chain(&chain)?;
+    provider.pin_block_hash(parent_hash);
+
+    let mut inputs = Vec::with_capacity(blocks.len());
+    let mut parent_header = to_header(&parent_block.header);
+    for (block_number, block) in (block_number..).zip(blocks.iter()) {
+        let input = prepare_block_input(
+            &l1_chain_spec,
+            &taiko_chain_spec,
+            block_number,
+            block,
+            parent_header,
+            prover_data.clone(),
+            blob_sources,
+            &mut report,
+        )
+        .await?;
+        parent_header = input.block_header_reference.clone();
+        inputs.push(input);
+    }
+    let last_input = inputs.last().expect("The batch is not empty");
+    let (last_block_number, hash) = (last_input.block_number, last_input.block_hash_reference);
+    report.block_hash = hash;
+    report.parent_hash = parent_hash;
+    report.add_timing("block data", measurement.stop());
 
+    // Create the database, all blocks are executed on top of the parent of the first block
+    let mut provider_db = ProviderDb::new(
+        provider,
+        taiko_chain_spec,
+        if let Some(parent_block_number) = parent_block.header.number {
+            parent_block_number
+        } else {
+            return Err(RaikoError::Preflight(
+                "No parent block number for the requested block".to_owned(),
+            ));
+        },
+        parent_hash,
+        hash,
+    )
+    .await?;
+
+    // Optimize data gathering by executing the transactions multiple times so data can be requested in batches
+    let measurement = Measurement::start("Executing transactions...", false);
+    for input in &inputs {
+        let mut builder = BlockBuilder::new(input)
+            .with_db(provider_db)
+            .prepare_chain_header()?;
+
+        // The state changes of the previous blocks in the batch have to survive invalid runs
+        let base_db = builder.mut_db().unwrap().current_db.clone();
+        let is_local = false;
+        let max_iterations = if is_local { 1 } else { 50 };
+        let mut done = false;
+        let mut num_iterations = 0;
+        while !done {
+            info!(
+                "Execution iteration {num_iterations} of block {}...",
+                input.block_number
+            );
+            builder.mut_db().unwrap().optimistic = num_iterations + 1 < max_iterations;
+            builder = builder.execute_chain_transactions()?;
+            if builder.mut_db().unwrap().fetch_data().await? {
+                done = true;
+            } else {
+                builder.mut_db().unwrap().current_db = base_db.clone();
+            }
+            num_iterations += 1;
+        }
+        provider_db = builder.take_db().expect("DB initialized");
+    }
+    report.iterations = take(&mut provider_db.iterations);
+    report.add_timing("execution", measurement.stop());
+
+    // Gather inclusion proofs for the initial and final state
+    let measurement = Measurement::start("Fetching storage proofs...", true);
+    let (parent_proofs, proofs, num_storage_proofs) = match provider_db.get_proofs().await {
+        Ok(proofs) => proofs,
+        Err(err) => {
+            // Nodes fail proof requests for non-canonical blocks, report those as a reorg
+            check_canonical(&provider_db.provider, last_block_number, hash).await?;
+            return Err(err);
+        }
+    };
+    report.num_parent_account_proofs = parent_proofs.len();
+    report.num_account_proofs = proofs.len();
+    report.num_storage_proofs = num_storage_proofs;
+    let duration = measurement.stop_with_count(&format!(
+        "[{} Account/{num_storage_proofs} Storage]",
+        parent_proofs.len() + proofs.len(),
+    ));
+    report.add_timing("storage proofs", duration);
+
+    // Construct the state trie and storage from the storage proofs.
+    let measurement = Measurement::start("Constructing MPT...", true);
+    let (state_trie, storage) =
+        proofs_to_tries(inputs[0].parent_header.state_root, parent_proofs, proofs)?;
+    report.add_timing("mpt", measurement.stop());
+
+    // Gather proofs for block history
+    let measurement = Measurement::start("Fetching historical block headers...", true);
+    let ancestor_headers = provider_db.get_ancestor_headers().await?;
+    report.num_ancestor_headers = ancestor_headers.len();
+    report.add_timing("historical headers", measurement.stop());
+
+    // Get the contracts from the initial db.
+    let measurement = Measurement::start("Fetching contract code...", true);
+    let mut contracts = HashSet::new();
+    let initial_db = &provider_db.initial_db;
+    for account in initial_db.accounts.values() {
+        let code = &account.info.code;
+        if let Some(code) = code {
+            contracts.insert(code.bytecode.0.clone());
+        }
+    }
+    report.add_timing("contract code", measurement.stop());
+
+    // Make sure the blocks weren't reorged out while we were fetching their data
+    check_canonical(&provider_db.provider, last_block_number, hash).await?;
+
+    // Add the collected data to the input of the first block, it's shared by the whole batch
+    let first_input = &mut inputs[0];
+    first_input.parent_state_trie = state_trie;
+    first_input.parent_storage = storage;
+    first_input.contracts = contracts.into_iter().map(Bytes).collect();
+    first_input.ancestor_headers = ancestor_headers;
+    Ok((BatchGuestInput { inputs }, report))
+}
+
+/// Creates the input of a block without the state witness.
+async fn prepare_block_input(
+    l1_chain_spec: &ChainSpec,
+    taiko_chain_spec: &ChainSpec,
+    block_number: u64,
+    block: &Block,
+    parent_header: AlloyConsensusHeader,
+    prover_data: TaikoProverData,
+    blob_sources: &[BlobSource],
+    report: &mut PreflightReport,
+) -> RaikoResult<GuestInput> {
     let hash = block.header.hash.ok_or_else(|| {
         RaikoError::Preflight("No block hash for the requested block".to_string())
     })?;
@@ -63,26 +310,27 @@ pub async fn preflight<BDP: BlockDataProvider>(
     info!("block gas used: {:?}", block.header.gas_used);
     info!("block transactions: {:?}", block.transactions.len());
 
-    let taiko_guest_input = if taiko_chain_spec.is_taiko() {
-        prepare_taiko_chain_input(
-            &l1_chain_spec,
-            &taiko_chain_spec,
+    let (transactions, taiko_guest_input) = if taiko_chain_spec.is_taiko() {
+        // Taiko blocks generate their transactions from the proposed tx list
+        let taiko_guest_input = prepare_taiko_chain_input(
+            l1_chain_spec,
+            taiko_chain_spec,
             block_number,
             block,
             prover_data,
+            blob_sources,
+            report,
         )
-        .await?
+        .await?;
+        (Vec::new(), taiko_guest_input)
     } else {
-        // For Ethereum blocks we just convert the block transactions in a tx_list
-        // so that we don't have to supports separate paths.
-        TaikoGuestInput {
-            tx_data: zlib_compress_data(&alloy_rlp::encode(&get_transactions_from_block(block)?))?,
-            ..Default::default()
-        }
+        (
+            get_transactions_from_block(block)?,
+            TaikoGuestInput::default(),
+        )
     };
-    measurement.stop();
 
-    let input = GuestInput {
+    Ok(GuestInput {
         chain_spec: taiko_chain_spec.clone(),
         block_number,
         block_hash_reference: hash,
@@ -104,7 +352,7 @@ pub async fn preflight<BDP: BlockDataProvider>(
         parent_state_trie: Default::default(),
         parent_storage: Default::default(),
         contracts: Default::default(),
-        parent_header: to_header(&parent_block.header),
+        parent_header,
         ancestor_headers: Default::default(),
         base_fee_per_gas: block.header.base_fee_per_gas.map_or_else(
             || {
@@ -135,81 +383,8 @@ pub async fn preflight<BDP: BlockDataProvider>(
             },
         )?,
         parent_beacon_block_root: block.header.parent_beacon_block_root,
+        transactions,
         taiko: taiko_guest_input,
-    };
-
-    // Create the block builder, run the transactions and extract the DB
-    let provider_db = ProviderDb::new(
-        provider,
-        taiko_chain_spec,
-        if let Some(parent_block_number) = parent_block.header.number {
-            parent_block_number
-        } else {
-            return Err(RaikoError::Preflight(
-                "No parent block number for the requested block".to_owned(),
-            ));
-        },
-    )
-    .await?;
-
-    let mut builder = BlockBuilder::new(&input)
-        .with_db(provider_db)
-        .prepare_header::<TaikoHeaderPrepStrategy>()?;
-
-    // Optimize data gathering by executing the transactions multiple times so data can be requested in batches
-    let is_local = false;
-    let max_iterations = if is_local { 1 } else { 50 };
-    let mut done = false;
-    let mut num_iterations = 0;
-    while !done {
-        info!("Execution iteration {num_iterations}...");
-        builder.mut_db().unwrap().optimistic = num_iterations + 1 < max_iterations;
-        builder = builder.execute_transactions::<TkoTxExecStrategy>()?;
-        if builder.mut_db().unwrap().fetch_data().await {
-            done = true;
-        }
-        num_iterations += 1;
-    }
-    let provider_db = builder.mut_db().unwrap();
-
-    // Gather inclusion proofs for the initial and final state
-    let measurement = Measurement::start("Fetching storage proofs...", true);
-    let (parent_proofs, proofs, num_storage_proofs) = provider_db.get_proofs().await?;
-    measurement.stop_with_count(&format!(
-        "[{} Account/{num_storage_proofs} Storage]",
-        parent_proofs.len() + proofs.len(),
-    ));
-
-    // Construct the state trie and storage from the storage proofs.
-    let measurement = Measurement::start("Constructing MPT...", true);
-    let (state_trie, storage) =
-        proofs_to_tries(input.parent_header.state_root, parent_proofs, proofs)?;
-    measurement.stop();
-
-    // Gather proofs for block history
-    let measurement = Measurement::start("Fetching historical block headers...", true);
-    let ancestor_headers = provider_db.get_ancestor_headers().await?;
-    measurement.stop();
-
-    // Get the contracts from the initial db.
-    let measurement = Measurement::start("Fetching contract code...", true);
-    let mut contracts = HashSet::new();
-    let initial_db = &provider_db.initial_db;
-    for account in initial_db.accounts.values() {
-        let code = &account.info.code;
-        if let Some(code) = code {
-            contracts.insert(code.bytecode.0.clone());
-        }
-    }
-    measurement.stop();
-
-    // Add the collected data to the input
-    Ok(GuestInput {
-        parent_state_trie: state_trie,
-        parent_storage: storage,
-        contracts: contracts.into_iter().map(Bytes).collect(),
-        ancestor_headers,
-        ..input
     })
 }
 
@@ -220,8 +395,11 @@ async fn prepare_taiko_chain_input(
     block_number: u64,
     block: &Block,
     prover_data: TaikoProverData,
+    blob_sources: &[BlobSource],
+    report: &mut PreflightReport,
 ) -> RaikoResult<TaikoGuestInput> {
     let provider_l1 = RpcBlockDataProvider::new(&l1_chain_spec.rpc, block_number)?;
+    report.l1_rpc = Some(l1_chain_spec.rpc.clone());
 
     // Decode the anchor tx to find out which L1 blocks we need to fetch
     let anchor_tx = match &block.transactions {
@@ -229,74 +407,150 @@ async fn prepare_taiko_chain_input(
         _ => unreachable!(),
     };
     let anchor_call = decode_anchor(anchor_tx.input.as_ref())?;
+    // The block proposal data and the anchor call depend on the active protocol version
+    let protocol_version =
+        taiko_chain_spec.active_protocol_version(block_number, block.header.timestamp);
+    info!("protocol version: {protocol_version:?}");
+    if anchor_call.version() != protocol_version {
+        return Err(RaikoError::Preflight(format!(
+            "The anchor call is of protocol version {:?}, expected {protocol_version:?}",
+            anchor_call.version()
+        )));
+    }
     // The L1 blocks we need
-    let l1_state_block_number = anchor_call.l1BlockId;
-    let l1_inclusion_block_number = l1_state_block_number + 1;
-
-    info!("anchor L1 block id: {:?}", anchor_call.l1BlockId);
-    info!("anchor L1 state root: {:?}", anchor_call.l1StateRoot);
-
-    // Get the L1 block in which the L2 block was included so we can fetch the DA data.
-    // Also get the L1 state block header so that we can prove the L1 state root.
-    let l1_blocks = provider_l1
-        .get_blocks(&[
-            (l1_inclusion_block_number, false),
-            (l1_state_block_number, false),
-        ])
-        .await?;
-    let (l1_inclusion_block, l1_state_block) = (&l1_blocks[0], &l1_blocks[1]);
+    let l1_state_block_number = anchor_call.l1_block_id();
 
+    info!("anchor L1 block id: {l1_state_block_number:?}");
+    info!("anchor L1 state root: {:?}", anchor_call.l1_state_root());
+
+    let (l1_state_block, l1_inclusion_block, proposal_tx, proposal_event) = match &anchor_call {
+        AnchorCallFork::V1(call) => {
+            // Get the L1 state block header so that we can prove the L1 state root. The anchor
+            // transaction commits to its hash, so fetch it by hash.
+            let l1_state_block = get_l1_block(
+                provider_l1
+                    .get_blocks_by_hash(&[(call.l1Hash, false)])
+                    .await?,
+                "state",
+            )?;
+            // Get the L1 block in which the L2 block was included so we can fetch the DA data.
+            // It has to build on top of the L1 state block, otherwise the L1 chain was reorged.
+            let l1_inclusion_block = get_l1_block(
+                provider_l1
+                    .get_blocks(&[(l1_state_block_number + 1, false)])
+                    .await?,
+                "inclusion",
+            )?;
+            check_chain(&[l1_state_block.clone(), l1_inclusion_block.clone()])?;
+            let l1_inclusion_block_hash = l1_inclusion_block.header.hash.ok_or_else(|| {
+                RaikoError::Preflight(
+                    "No L1 inclusion block hash for the requested block".to_owned(),
+                )
+            })?;
+            let (proposal_tx, proposal_event) = get_block_proposed_event(
+                provider_l1.provider(),
+                taiko_chain_spec.clone(),
+                protocol_version,
+                Filter::new().at_block_hash(l1_inclusion_block_hash),
+                block_number,
+            )
+            .await?;
+            (
+                l1_state_block,
+                l1_inclusion_block,
+                proposal_tx,
+                proposal_event,
+            )
+        }
+        AnchorCallFork::V2(_) => {
+            // The anchor transaction only references the L1 state block by number, the
+            // metadata of the block commits to its hash
+            let l1_state_block = get_l1_block(
+                provider_l1
+                    .get_blocks(&[(l1_state_block_number, false)])
+                    .await?,
+                "state",
+            )?;
+            // The block can be proposed up to `MAX_ANCHOR_HEIGHT_OFFSET` L1 blocks after the
+            // L1 state block
+            let (proposal_tx, proposal_event) = get_block_proposed_event(
+                provider_l1.provider(),
+                taiko_chain_spec.clone(),
+                protocol_version,
+                Filter::new()
+                    .from_block(l1_state_block_number + 1)
+                    .to_block(l1_state_block_number + MAX_ANCHOR_HEIGHT_OFFSET),
+                block_number,
+            )
+            .await?;
+            let BlockProposedFork::V2(event) = &proposal_event else {
+                unreachable!("V2 events are decoded for V2 anchor calls")
+            };
+            if Some(event.meta.anchorBlockHash) != l1_state_block.header.hash {
+                return Err(RaikoError::Preflight(format!(
+                    "The L1 state block {l1_state_block_number} is not the anchor block {} of the proposal",
+                    event.meta.anchorBlockHash
+                )));
+            }
+            let l1_inclusion_block = get_l1_block(
+                provider_l1
+                    .get_blocks(&[(event.meta.proposedIn, false)])
+                    .await?,
+                "inclusion",
+            )?;
+            (
+                l1_state_block,
+                l1_inclusion_block,
+                proposal_tx,
+                proposal_event,
+            )
+        }
+    };
     let l1_state_block_hash = l1_state_block.header.hash.ok_or_else(|| {
         RaikoError::Preflight("No L1 state block hash for the requested block".to_owned())
     })?;
-
-    info!("l1_state_root_block hash: {l1_state_block_hash:?}");
-
     let l1_inclusion_block_hash = l1_inclusion_block.header.hash.ok_or_else(|| {
         RaikoError::Preflight("No L1 inclusion block hash for the requested block".to_owned())
     })?;
-
-    // Get the block proposal data
-    let (proposal_tx, proposal_event) = get_block_proposed_event(
-        provider_l1.provider(),
-        taiko_chain_spec.clone(),
-        l1_inclusion_block_hash,
-        block_number,
-    )
-    .await?;
+    info!("l1_state_root_block hash: {l1_state_block_hash:?}");
+    report.l1_state_block_hash = Some(l1_state_block_hash);
+    report.l1_inclusion_block_hash = Some(l1_inclusion_block_hash);
 
     // Fetch the tx data from either calldata or blobdata
-    let (tx_data, tx_blob_hash) = if proposal_event.meta.blobUsed {
+    let (tx_data, tx_blob_hash) = if proposal_event.blob_used() {
         info!("blob active");
         // Get the blob hashes attached to the propose tx
         let blob_hashes = proposal_tx.blob_versioned_hashes.unwrap_or_default();
-        assert!(!blob_hashes.is_empty());
-        // Currently the protocol enforces the first blob hash to be used
-        let blob_hash = blob_hashes[0];
+        let blob_hash = *blob_hashes
+            .get(proposal_event.blob_index())
+            .ok_or_else(|| {
+                RaikoError::Preflight("No blob attached to the propose tx".to_owned())
+            })?;
         // Get the blob data for this block
         let slot_id = block_time_to_block_slot(
             l1_inclusion_block.header.timestamp,
             l1_chain_spec.genesis_time,
             l1_chain_spec.seconds_per_slot,
         )?;
-        let beacon_rpc_url: String = l1_chain_spec.beacon_rpc.clone().ok_or_else(|| {
-            RaikoError::Preflight("Beacon RPC URL is required for Taiko chains".to_owned())
-        })?;
-        let blob = get_blob_data(&beacon_rpc_url, slot_id, blob_hash).await?;
+        let (blob, blob_source) = FallbackBlobProvider::new(l1_chain_spec, blob_sources)
+            .get_blob_with_source(slot_id, blob_hash)
+            .await?;
+        report.blob_source = Some(blob_source);
         (blob, Some(blob_hash))
     } else {
         // Get the tx list data directly from the propose transaction data
-        let proposal_call = proposeBlockCall::abi_decode(&proposal_tx.input, false)
+        let tx_list = proposal_event
+            .decode_tx_list(&proposal_tx.input)
             .map_err(|_| RaikoError::Preflight("Could not decode proposeBlockCall".to_owned()))?;
-        (proposal_call.txList.as_ref().to_owned(), None)
+        (tx_list, None)
     };
 
     // Create the transactions from the proposed tx list
-    let transactions = generate_transactions(
+    let (transactions, _) = generate_transactions(
         taiko_chain_spec,
-        proposal_event.meta.blobUsed,
+        &proposal_event,
         &tx_data,
-        Some(anchor_tx.clone()),
+        anchor_tx.clone(),
     );
     // Do a sanity check using the transactions returned by the node
     assert!(
@@ -317,7 +571,7 @@ async fn prepare_taiko_chain_input(
 }
 
 // block_time_to_block_slot returns the slots of the given timestamp.
-fn block_time_to_block_slot(
+pub fn block_time_to_block_slot(
     block_time: u64,
     genesis_time: u64,
     block_per_slot: u64,
@@ -335,144 +589,31 @@ fn block_time_to_block_slot(
     }
 }
 
-fn blob_to_bytes(blob_str: &str) -> Vec<u8> {
-    match hex::decode(blob_str.to_lowercase().trim_start_matches("0x")) {
-        Ok(b) => b,
-        Err(_) => Vec::new(),
-    }
-}
-
-fn calc_blob_versioned_hash(blob_str: &str) -> [u8; 32] {
-    let blob_bytes: Vec<u8> = hex::decode(blob_str.to_lowercase().trim_start_matches("0x"))
-        .expect("Could not decode blob");
-    let kzg_settings = Arc::clone(&*MAINNET_KZG_TRUSTED_SETUP);
-    let blob = Blob::from_bytes(&blob_bytes).expect("Could not create blob");
-    let kzg_commit = KzgCommitment::blob_to_kzg_commitment(&blob, &kzg_settings)
-        .expect("Could not create kzg commitment from blob");
-    let version_hash: [u8; 32] = kzg_to_versioned_hash(&kzg_commit).0;
-    version_hash
-}
-
-async fn get_blob_data(
-    beacon_rpc_url: &str,
-    block_id: u64,
-    blob_hash: FixedBytes<32>,
-) -> Result<Vec<u8>> {
-    if beacon_rpc_url.contains("blobscan.com") {
-        get_blob_data_blobscan(beacon_rpc_url, block_id, blob_hash).await
-    } else {
-        get_blob_data_beacon(beacon_rpc_url, block_id, blob_hash).await
-    }
-}
-
-async fn get_blob_data_beacon(
-    beacon_rpc_url: &str,
-    block_id: u64,
-    blob_hash: FixedBytes<32>,
-) -> Result<Vec<u8>> {
-    // Blob data from the beacon chain
-    // type Sidecar struct {
-    // Index                    string                   `json:"index"`
-    // Blob                     string                   `json:"blob"`
-    // SignedBeaconBlockHeader  *SignedBeaconBlockHeader `json:"signed_block_header"`
-    // KzgCommitment            string                   `json:"kzg_commitment"`
-    // KzgProof                 string                   `json:"kzg_proof"`
-    // CommitmentInclusionProof []string
-    // `json:"kzg_commitment_inclusion_proof"` }
-    #[derive(Clone, Debug, Deserialize, Serialize)]
-    struct GetBlobData {
-        pub index: String,
-        pub blob: String,
-        // pub signed_block_header: SignedBeaconBlockHeader, // ignore for now
-        pub kzg_commitment: String,
-        pub kzg_proof: String,
-        //pub kzg_commitment_inclusion_proof: Vec<String>,
-    }
-
-    #[derive(Clone, Debug, Deserialize, Serialize)]
-    struct GetBlobsResponse {
-        pub data: Vec<GetBlobData>,
-    }
-
-    let url = format!(
-        "{}/eth/v1/beacon/blob_sidecars/{block_id}",
-        beacon_rpc_url.trim_end_matches('/'),
-    );
-    info!("Retrieve blob from {url}.");
-    let response = reqwest::get(url.clone()).await?;
-    if response.status().is_success() {
-        let blobs: GetBlobsResponse = response.json().await?;
-        assert!(!blobs.data.is_empty(), "blob data not available anymore");
-        // Get the blob data for the blob storing the tx list
-        let tx_blob = blobs
-            .data
-            .iter()
-            .find(|blob| {
-                // calculate from plain blob
-                blob_hash == calc_blob_versioned_hash(&blob.blob)
-            })
-            .cloned();
-        assert!(tx_blob.is_some());
-        Ok(blob_to_bytes(&tx_blob.unwrap().blob))
-    } else {
-        warn!(
-            "Request {url} failed with status code: {}",
-            response.status()
-        );
-        Err(anyhow::anyhow!(
-            "Request failed with status code: {}",
-            response.status()
-        ))
-    }
-}
-
-async fn get_blob_data_blobscan(
-    beacon_rpc_url: &str,
-    _block_id: u64,
-    blob_hash: FixedBytes<32>,
-) -> Result<Vec<u8>> {
-    // https://api.blobscan.com/#/
-    #[derive(Clone, Debug, Deserialize, Serialize)]
-    struct BlobScanData {
-        pub commitment: String,
-        pub data: String,
-    }
-
-    let url = format!("{}/blobs/{blob_hash}", beacon_rpc_url.trim_end_matches('/'),);
-    let response = reqwest::get(url.clone()).await?;
-    if response.status().is_success() {
-        let blob: BlobScanData = response.json().await?;
-        Ok(blob_to_bytes(&blob.data))
-    } else {
-        println!(
-            "Request {url} failed with status code: {}",
-            response.status()
-        );
-        Err(anyhow::anyhow!(
-            "Request failed with status code: {}",
-            response.status()
-        ))
-    }
+/// Returns the L1 block of the response, the `kind` of the block is used in the error.
+fn get_l1_block(blocks: Vec<Block>, kind: &str) -> RaikoResult<Block> {
+    blocks
+        .into_iter()
+        .next()
+        .ok_or_else(|| RaikoError::Preflight(format!("No L1 {kind} block for the requested block")))
 }
 
+/// Finds the proposal of the L2 block in the L1 blocks selected by `blocks`.
 async fn get_block_proposed_event(
     provider: &ReqwestProvider,
     chain_spec: ChainSpec,
-    block_hash: B256,
+    protocol_version: ProtocolVersion,
+    blocks: Filter,
     l2_block_number: u64,
-) -> Result<(AlloyRpcTransaction, BlockProposed)> {
+) -> Result<(AlloyRpcTransaction, BlockProposedFork)> {
     // Get the address that emitted the event
     let Some(l1_address) = chain_spec.l1_contract else {
         bail!("No L1 contract address in the chain spec");
     };
 
     // Get the event signature (value can differ between chains)
-    let event_signature = BlockProposed::SIGNATURE_HASH;
+    let event_signature = BlockProposedFork::signature_hash(protocol_version);
     // Setup the filter to get the relevant events
-    let filter = Filter::new()
-        .address(l1_address)
-        .at_block_hash(block_hash)
-        .event_signature(event_signature);
+    let filter = blocks.address(l1_address).event_signature(event_signature);
     // Now fetch the events
     let logs = provider.get_logs(&filter).await?;
 
@@ -486,9 +627,9 @@ async fn get_block_proposed_event(
         ) else {
             bail!("Could not create log")
         };
-        let event = BlockProposed::decode_log(&log_struct, false)
+        let event = BlockProposedFork::decode_log(protocol_version, &log_struct)
             .map_err(|_| RaikoError::Anyhow(anyhow!("Could not decode log")))?;
-        if event.blockId == raiko_primitives::U256::from(l2_block_number) {
+        if event.block_id() == raiko_primitives::U256::from(l2_block_number) {
             let Some(log_tx_hash) = log.transaction_hash else {
                 bail!("No transaction hash in the log")
             };
@@ -496,7 +637,7 @@ async fn get_block_proposed_event(
                 .get_transaction_by_hash(log_tx_hash)
                 .await
                 .expect("Could not find the propose tx");
-            return Ok((tx, event.data));
+            return Ok((tx, event));
         }
     }
     bail!("No BlockProposed event found for block {l2_block_number}");
@@ -612,14 +753,21 @@ fn from_block_tx(tx: &AlloyRpcTransaction) -> RaikoResult<TxEnvelope> {
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
+    use c_kzg::KzgCommitment;
     use ethers_core::types::Transaction;
     use raiko_lib::{
         consts::{Network, SupportedChainSpecs},
         utils::decode_transactions,
     };
-    use raiko_primitives::{eip4844::parse_kzg_trusted_setup, kzg::KzgSettings};
+    use raiko_primitives::{
+        eip4844::{kzg_to_versioned_hash, parse_kzg_trusted_setup, MAINNET_KZG_TRUSTED_SETUP},
+        kzg::KzgSettings,
+    };
 
     use super::*;
+    use crate::provider::blob::blob_to_bytes;
 
     #[allow(dead_code)]
     fn calc_commit_versioned_hash(commitment: &str) -> [u8; 32] {
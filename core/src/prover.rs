@@ -1,7 +1,8 @@
 use raiko_lib::{
+    builder::{BlockBuildOutput, ChainStrategy, SkippedTx},
     consts::VerifierType,
-    input::{GuestInput, GuestOutput},
-    protocol_instance::ProtocolInstance,
+    input::{BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput},
+    protocol_instance::{BatchProtocolInstance, ProtocolInstance},
     prover::{to_proof, Proof, Prover, ProverError, ProverResult},
 };
 use serde::{Deserialize, Serialize};
@@ -12,6 +13,9 @@ pub struct NativeProver;
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NativeResponse {
     pub output: GuestOutput,
+    /// The transactions of the tx list that were not included in the block.
+    #[serde(default)]
+    pub skipped: Vec<SkippedTx>,
 }
 
 impl Prover for NativeProver {
@@ -29,8 +33,66 @@ impl Prover for NativeProver {
         ProtocolInstance::new(&input, &header, VerifierType::None)
             .map_err(|e| ProverError::GuestError(e.to_string()))?;
 
+        // Execute the block to report the transactions the Taiko rules dropped
+        let BlockBuildOutput {
+            header: built_header,
+            skipped,
+            ..
+        } = ChainStrategy::build_with_output(&input)
+            .map_err(|e| ProverError::GuestError(e.to_string()))?;
+        if built_header != header {
+            return Err(ProverError::GuestError(
+                "The built block does not match the output".to_owned(),
+            ));
+        }
+
         to_proof(Ok(NativeResponse {
             output: output.clone(),
+            skipped,
         }))
     }
+
+    async fn batch_run(
+        batch: BatchGuestInput,
+        output: &BatchGuestOutput,
+        _request: &serde_json::Value,
+    ) -> ProverResult<Proof> {
+        let BatchGuestOutput::Success { headers, hash } = output else {
+            return Err(ProverError::GuestError("Unexpected output".to_owned()));
+        };
+
+        let built_headers = ChainStrategy::build_batch_from(&batch)
+            .map_err(|e| ProverError::GuestError(e.to_string()))?;
+        if &built_headers != headers {
+            return Err(ProverError::GuestError(
+                "The built blocks do not match the output".to_owned(),
+            ));
+        }
+        let batch_hash = BatchProtocolInstance::new(&batch, &built_headers, VerifierType::None)
+            .map_err(|e| ProverError::GuestError(e.to_string()))?
+            .instance_hash();
+        if &batch_hash != hash {
+            return Err(ProverError::GuestError(
+                "The batch instance hash does not match the output".to_owned(),
+            ));
+        }
+
+        to_proof(Ok(output.clone()))
+    }
+
+    async fn verify(
+        _input: GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+        _config: &serde_json::Value,
+    ) -> ProverResult<()> {
+        let response = NativeResponse::deserialize(proof)
+            .map_err(|e| ProverError::GuestError(format!("Invalid native proof: {e}")))?;
+        if &response.output != output {
+            return Err(ProverError::GuestError(
+                "The proof is for a different output".to_owned(),
+            ));
+        }
+        Ok(())
+    }
 }
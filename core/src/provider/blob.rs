@@ -0,0 +1,311 @@
+use std::{path::PathBuf, sync::Arc};
+
+use alloy_primitives::{hex, B256};
+use c_kzg::{Blob, KzgCommitment};
+use raiko_lib::consts::ChainSpec;
+use raiko_primitives::eip4844::{kzg_to_versioned_hash, MAINNET_KZG_TRUSTED_SETUP};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::interfaces::{RaikoError, RaikoResult};
+
+/// A source blobs can be fetched from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BlobSource {
+    /// The beacon node at `beacon_rpc`, only serves blobs within the ~18 day retention window.
+    Beacon,
+    /// The Blobscan API.
+    Blobscan(String),
+    /// A local directory containing blobs stored by versioned hash.
+    LocalArchive(String),
+    /// An HTTP server serving blobs stored by versioned hash.
+    HttpArchive(String),
+}
+
+#[allow(async_fn_in_trait)]
+pub trait BlobProvider {
+    /// Fetches the raw blob with the given versioned hash, which was included in `slot_id`.
+    async fn get_blob(&self, slot_id: u64, blob_hash: B256) -> RaikoResult<Vec<u8>>;
+}
+
+/// The beacon node `blob_sidecars` API.
+pub struct BeaconBlobProvider {
+    pub url: String,
+}
+
+impl BlobProvider for BeaconBlobProvider {
+    async fn get_blob(&self, slot_id: u64, blob_hash: B256) -> RaikoResult<Vec<u8>> {
+        // Blob data from the beacon chain
+        // type Sidecar struct {
+        // Index                    string                   `json:"index"`
+        // Blob                     string                   `json:"blob"`
+        // SignedBeaconBlockHeader  *SignedBeaconBlockHeader `json:"signed_block_header"`
+        // KzgCommitment            string                   `json:"kzg_commitment"`
+        // KzgProof                 string                   `json:"kzg_proof"`
+        // CommitmentInclusionProof []string
+        // `json:"kzg_commitment_inclusion_proof"` }
+        #[derive(Clone, Debug, Deserialize, Serialize)]
+        struct GetBlobData {
+            pub index: String,
+            pub blob: String,
+            // pub signed_block_header: SignedBeaconBlockHeader, // ignore for now
+            pub kzg_commitment: String,
+            pub kzg_proof: String,
+            //pub kzg_commitment_inclusion_proof: Vec<String>,
+        }
+
+        #[derive(Clone, Debug, Deserialize, Serialize)]
+        struct GetBlobsResponse {
+            pub data: Vec<GetBlobData>,
+        }
+
+        let url = format!(
+            "{}/eth/v1/beacon/blob_sidecars/{slot_id}",
+            self.url.trim_end_matches('/'),
+        );
+        let response = http_get(&url).await?;
+        let blobs: GetBlobsResponse = response.json().await.map_err(to_blob_error)?;
+        // Get the blob data for the blob storing the tx list
+        blobs
+            .data
+            .iter()
+            .map(|blob| blob_to_bytes(&blob.blob))
+            .find(|blob| calc_blob_versioned_hash(blob).ok() == Some(blob_hash))
+            .ok_or_else(|| {
+                RaikoError::Blob(format!("Blob {blob_hash} not available in slot {slot_id}"))
+            })
+    }
+}
+
+/// The [Blobscan](https://api.blobscan.com/#/) API.
+pub struct BlobscanBlobProvider {
+    pub url: String,
+}
+
+impl BlobProvider for BlobscanBlobProvider {
+    async fn get_blob(&self, _slot_id: u64, blob_hash: B256) -> RaikoResult<Vec<u8>> {
+        #[derive(Clone, Debug, Deserialize, Serialize)]
+        struct BlobScanData {
+            pub commitment: String,
+            pub data: String,
+        }
+
+        let url = format!("{}/blobs/{blob_hash}", self.url.trim_end_matches('/'));
+        let response = http_get(&url).await?;
+        let blob: BlobScanData = response.json().await.map_err(to_blob_error)?;
+        Ok(blob_to_bytes(&blob.data))
+    }
+}
+
+/// A directory containing the raw blobs, each stored in a file named after its versioned hash.
+pub struct LocalArchiveBlobProvider {
+    pub dir: PathBuf,
+}
+
+impl LocalArchiveBlobProvider {
+    /// The path a blob is stored at in the archive.
+    pub fn blob_path(&self, blob_hash: B256) -> PathBuf {
+        self.dir.join(blob_hash.to_string())
+    }
+
+    /// Stores a blob in the archive. The blob is written to a temporary file first so readers
+    /// never see a partially written blob.
+    pub async fn store_blob(&self, blob_hash: B256, blob: &[u8]) -> RaikoResult<()> {
+        let path = self.blob_path(blob_hash);
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, blob).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+}
+
+impl BlobProvider for LocalArchiveBlobProvider {
+    async fn get_blob(&self, _slot_id: u64, blob_hash: B256) -> RaikoResult<Vec<u8>> {
+        let path = self.blob_path(blob_hash);
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| RaikoError::Blob(format!("Failed reading {}: {e}", path.display())))
+    }
+}
+
+/// An HTTP server with the same layout as [LocalArchiveBlobProvider], serving the raw blob at
+/// `{url}/{versioned hash}`.
+pub struct HttpArchiveBlobProvider {
+    pub url: String,
+}
+
+impl BlobProvider for HttpArchiveBlobProvider {
+    async fn get_blob(&self, _slot_id: u64, blob_hash: B256) -> RaikoResult<Vec<u8>> {
+        let url = format!("{}/{blob_hash}", self.url.trim_end_matches('/'));
+        let response = http_get(&url).await?;
+        Ok(response.bytes().await.map_err(to_blob_error)?.to_vec())
+    }
+}
+
+/// Returns the blob sources of a chain without configured sources, only `beacon_rpc`, which
+/// can also point to Blobscan.
+pub fn default_blob_sources(chain_spec: &ChainSpec) -> Vec<BlobSource> {
+    match &chain_spec.beacon_rpc {
+        Some(url) if url.contains("blobscan.com") => vec![BlobSource::Blobscan(url.clone())],
+        Some(_) => vec![BlobSource::Beacon],
+        None => Vec::new(),
+    }
+}
+
+/// Tries all blob sources in order and returns the first blob that matches the versioned
+/// hash.
+pub struct FallbackBlobProvider {
+    beacon_rpc: Option<String>,
+    sources: Vec<BlobSource>,
+}
+
+impl FallbackBlobProvider {
+    /// Fetches the blobs of the L1 chain from `sources`, or from [default_blob_sources] if
+    /// there are none.
+    pub fn new(chain_spec: &ChainSpec, sources: &[BlobSource]) -> Self {
+        Self {
+            beacon_rpc: chain_spec.beacon_rpc.clone(),
+            sources: if sources.is_empty() {
+                default_blob_sources(chain_spec)
+            } else {
+                sources.to_vec()
+            },
+        }
+    }
+
+    async fn get_blob_from(
+        &self,
+        source: &BlobSource,
+        slot_id: u64,
+        blob_hash: B256,
+    ) -> RaikoResult<Vec<u8>> {
+        match source {
+            BlobSource::Beacon => {
+                let url = self.beacon_rpc.clone().ok_or_else(|| {
+                    RaikoError::Blob("Beacon RPC URL is required for the beacon source".to_owned())
+                })?;
+                BeaconBlobProvider { url }
+                    .get_blob(slot_id, blob_hash)
+                    .await
+            }
+            BlobSource::Blobscan(url) => {
+                BlobscanBlobProvider { url: url.clone() }
+                    .get_blob(slot_id, blob_hash)
+                    .await
+            }
+            BlobSource::LocalArchive(dir) => {
+                LocalArchiveBlobProvider { dir: dir.into() }
+                    .get_blob(slot_id, blob_hash)
+                    .await
+            }
+            BlobSource::HttpArchive(url) => {
+                HttpArchiveBlobProvider { url: url.clone() }
+                    .get_blob(slot_id, blob_hash)
+                    .await
+            }
+        }
+    }
+
+    /// Like [BlobProvider::get_blob], but also returns the source the blob was fetched from.
+    pub async fn get_blob_with_source(
+        &self,
+        slot_id: u64,
+        blob_hash: B256,
+    ) -> RaikoResult<(Vec<u8>, BlobSource)> {
+        if self.sources.is_empty() {
+            return Err(RaikoError::Blob(
+                "No blob sources configured for the chain".to_owned(),
+            ));
+        }
+        for source in &self.sources {
+            info!("Retrieve blob {blob_hash} from {source:?}.");
+            let result = self
+                .get_blob_from(source, slot_id, blob_hash)
+                .await
+                .and_then(|blob| verify_blob(&blob, blob_hash).map(|_| blob));
+            match result {
+                Ok(blob) => return Ok((blob, source.clone())),
+                Err(e) => warn!("Failed retrieving blob from {source:?}: {e}"),
+            }
+        }
+        Err(RaikoError::Blob(format!(
+            "Blob {blob_hash} not available from any blob source"
+        )))
+    }
+}
+
+impl BlobProvider for FallbackBlobProvider {
+    async fn get_blob(&self, slot_id: u64, blob_hash: B256) -> RaikoResult<Vec<u8>> {
+        Ok(self.get_blob_with_source(slot_id, blob_hash).await?.0)
+    }
+}
+
+/// Checks that the KZG commitment of the blob matches the versioned hash.
+pub fn verify_blob(blob: &[u8], blob_hash: B256) -> RaikoResult<()> {
+    let versioned_hash = calc_blob_versioned_hash(blob)?;
+    if versioned_hash != blob_hash {
+        return Err(RaikoError::Blob(format!(
+            "Blob versioned hash mismatch: expected {blob_hash}, got {versioned_hash}"
+        )));
+    }
+    Ok(())
+}
+
+pub fn calc_blob_versioned_hash(blob: &[u8]) -> RaikoResult<B256> {
+    let kzg_settings = Arc::clone(&*MAINNET_KZG_TRUSTED_SETUP);
+    let blob = Blob::from_bytes(blob)
+        .map_err(|e| RaikoError::Blob(format!("Could not create blob: {e:?}")))?;
+    let kzg_commit = KzgCommitment::blob_to_kzg_commitment(&blob, &kzg_settings)
+        .map_err(|e| RaikoError::Blob(format!("Could not create kzg commitment: {e:?}")))?;
+    Ok(kzg_to_versioned_hash(&kzg_commit))
+}
+
+pub(crate) fn blob_to_bytes(blob_str: &str) -> Vec<u8> {
+    hex::decode(blob_str.to_lowercase().trim_start_matches("0x")).unwrap_or_default()
+}
+
+async fn http_get(url: &str) -> RaikoResult<reqwest::Response> {
+    let response = reqwest::get(url).await.map_err(to_blob_error)?;
+    if !response.status().is_success() {
+        return Err(RaikoError::Blob(format!(
+            "Request {url} failed with status code: {}",
+            response.status()
+        )));
+    }
+    Ok(response)
+}
+
+fn to_blob_error(e: reqwest::Error) -> RaikoError {
+    RaikoError::Blob(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use c_kzg::BYTES_PER_BLOB;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_local_archive_blob() {
+        let dir = std::env::temp_dir().join("raiko_blob_archive_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let blob = vec![0u8; BYTES_PER_BLOB];
+        let blob_hash = calc_blob_versioned_hash(&blob).unwrap();
+        let archive = LocalArchiveBlobProvider { dir: dir.clone() };
+        std::fs::write(archive.blob_path(blob_hash), &blob).unwrap();
+
+        let provider = FallbackBlobProvider::new(
+            &ChainSpec::default(),
+            &[
+                BlobSource::HttpArchive("http://localhost:1".to_owned()),
+                BlobSource::LocalArchive(dir.to_string_lossy().into_owned()),
+            ],
+        );
+        assert_eq!(provider.get_blob(0, blob_hash).await.unwrap(), blob);
+
+        // A blob not matching the versioned hash is rejected
+        let wrong_hash = B256::with_last_byte(1);
+        std::fs::write(archive.blob_path(wrong_hash), &blob).unwrap();
+        assert!(provider.get_blob(0, wrong_hash).await.is_err());
+    }
+}
@@ -25,13 +25,16 @@ use tokio::runtime::Handle;
 
 use crate::{
     interfaces::{RaikoError, RaikoResult},
-    provider::BlockDataProvider,
+    preflight::PreflightIteration,
+    provider::{check_canonical, check_chain, BlockDataProvider},
     MerkleProof,
 };
 
 pub struct ProviderDb<BDP: BlockDataProvider> {
     pub provider: BDP,
     pub block_number: u64,
+    pub parent_hash: B256,
+    pub block_hash: B256,
     pub initial_db: MemDb,
     pub initial_headers: HashMap<u64, AlloyConsensusHeader>,
     pub current_db: MemDb,
@@ -42,13 +45,29 @@ pub struct ProviderDb<BDP: BlockDataProvider> {
     pub pending_accounts: HashSet<Address>,
     pub pending_slots: HashSet<(Address, U256)>,
     pub pending_block_hashes: HashSet<u64>,
+    /// The block numbers read with `BLOCKHASH`, the input only contains the headers needed to
+    /// authenticate their hashes.
+    pub accessed_block_hashes: HashSet<u64>,
+    /// The data fetched in each optimistic iteration.
+    pub iterations: Vec<PreflightIteration>,
 }
 
 impl<BDP: BlockDataProvider> ProviderDb<BDP> {
-    pub async fn new(provider: BDP, chain_spec: ChainSpec, block_number: u64) -> RaikoResult<Self> {
+    /// Creates a database with the state of block `block_number` with hash `parent_hash`,
+    /// for executing the block with hash `block_hash` on top of it.
+    pub async fn new(
+        mut provider: BDP,
+        chain_spec: ChainSpec,
+        block_number: u64,
+        parent_hash: B256,
+        block_hash: B256,
+    ) -> RaikoResult<Self> {
+        provider.pin_block_hash(parent_hash);
         let mut provider_db = ProviderDb {
             provider,
             block_number,
+            parent_hash,
+            block_hash,
             async_executor: Handle::current(),
             // defaults
             optimistic: false,
@@ -59,15 +78,26 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
             pending_accounts: HashSet::new(),
             pending_slots: HashSet::new(),
             pending_block_hashes: HashSet::new(),
+            accessed_block_hashes: HashSet::new(),
+            iterations: Vec::new(),
         };
         if chain_spec.is_taiko() {
             // Get the 256 history block hashes from the provider at first time for anchor
-            // transaction.
+            // transaction. Only the headers of the hashes actually read end up in the input.
             let start = block_number.saturating_sub(255);
             let block_numbers = (start..=block_number)
                 .map(|block_number| (block_number, false))
                 .collect::<Vec<_>>();
             let initial_history_blocks = provider_db.provider.get_blocks(&block_numbers).await?;
+            // The history has to lead up to the pinned parent block
+            check_chain(&initial_history_blocks)?;
+            if initial_history_blocks
+                .last()
+                .and_then(|block| block.header.hash)
+                != Some(parent_hash)
+            {
+                return Err(RaikoError::Reorg(parent_hash));
+            }
             for block in initial_history_blocks {
                 let block_number: u64 = block
                     .header
@@ -114,7 +144,7 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
         let initial_proofs = self
             .provider
             .get_merkle_proofs(
-                self.block_number,
+                self.parent_hash,
                 self.initial_db.storage_keys(),
                 0,
                 num_storage_proofs,
@@ -123,7 +153,7 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
         let latest_proofs = self
             .provider
             .get_merkle_proofs(
-                self.block_number + 1,
+                self.block_hash,
                 storage_keys,
                 num_initial_values,
                 num_storage_proofs,
@@ -133,11 +163,14 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
         Ok((initial_proofs, latest_proofs, num_storage_proofs))
     }
 
+    /// Returns the headers from the parent of the pinned block down to the oldest block whose
+    /// hash was read, which link the hashes to the pinned block.
     pub async fn get_ancestor_headers(&mut self) -> RaikoResult<Vec<AlloyConsensusHeader>> {
+        // Blocks after the pinned block are built in the same batch
         let earliest_block = self
-            .initial_db
-            .block_hashes
-            .keys()
+            .accessed_block_hashes
+            .iter()
+            .filter(|block_number| **block_number <= self.block_number)
             .min()
             .unwrap_or(&self.block_number);
 
@@ -145,6 +178,11 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
             usize::try_from(self.block_number - *earliest_block)
                 .map_err(|_| RaikoError::Conversion("Could not convert u64 to usize".to_owned()))?,
         );
+        // The ancestors have to be linked by hash to the pinned parent block
+        let mut expected_hash = self
+            .initial_headers
+            .get(&self.block_number)
+            .map(|header| header.parent_hash);
         for block_number in (*earliest_block..self.block_number).rev() {
             if let std::collections::hash_map::Entry::Vacant(e) =
                 self.initial_headers.entry(block_number)
@@ -152,16 +190,35 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
                 let block = &self.provider.get_blocks(&[(block_number, false)]).await?[0];
                 e.insert(to_header(&block.header));
             }
-            headers.push(
-                self.initial_headers
-                    .get(&block_number)
-                    .expect("The header is inserted if it was not present")
-                    .clone(),
-            );
+            let header = self
+                .initial_headers
+                .get(&block_number)
+                .expect("The header is inserted if it was not present");
+            if let Some(expected_hash) = expected_hash {
+                if header.hash_slow() != expected_hash {
+                    return Err(RaikoError::Reorg(expected_hash));
+                }
+            }
+            expected_hash = Some(header.parent_hash);
+            headers.push(header.clone());
         }
         Ok(headers)
     }
 
+    /// Returns [RaikoError::Reorg] for a failed fetch if the parent block is no longer
+    /// canonical, nodes reject requests pinned to a reorged block hash like any other request.
+    async fn check_fetch<T>(&self, result: RaikoResult<T>) -> RaikoResult<T> {
+        let err = match result {
+            Ok(value) => return Ok(value),
+            Err(err @ RaikoError::Reorg(_)) => return Err(err),
+            Err(err) => err,
+        };
+        match check_canonical(&self.provider, self.block_number, self.parent_hash).await {
+            Err(reorg @ RaikoError::Reorg(_)) => Err(reorg),
+            _ => Err(err),
+        }
+    }
+
     pub fn is_valid_run(&self) -> bool {
         self.pending_accounts.is_empty()
             && self.pending_slots.is_empty()
@@ -260,6 +317,7 @@ impl<BDP: BlockDataProvider> Database for ProviderDb<BDP> {
         let block_number: u64 = number
             .try_into()
             .map_err(|_| RaikoError::Conversion("Could not convert U256 to u64".to_owned()))?;
+        self.accessed_block_hashes.insert(block_number);
 
         // Check if the block hash is in the current database.
         if let Ok(block_hash) = self.initial_db.block_hash(number) {
@@ -306,7 +364,7 @@ impl<BDP: BlockDataProvider> DatabaseCommit for ProviderDb<BDP> {
 }
 
 impl<BDP: BlockDataProvider> OptimisticDatabase for ProviderDb<BDP> {
-    async fn fetch_data(&mut self) -> bool {
+    async fn fetch_data(&mut self) -> RaikoResult<bool> {
         //println!("all accounts touched: {:?}", self.pending_accounts);
         //println!("all slots touched: {:?}", self.pending_slots);
         //println!("all block hashes touched: {:?}", self.pending_block_hashes);
@@ -314,13 +372,17 @@ impl<BDP: BlockDataProvider> OptimisticDatabase for ProviderDb<BDP> {
         // This run was valid when no pending work was scheduled
         let valid_run = self.is_valid_run();
 
-        let Ok(accounts) = self
+        self.iterations.push(PreflightIteration {
+            accounts: self.pending_accounts.iter().copied().collect(),
+            slots: self.pending_slots.iter().copied().collect(),
+            block_hashes: self.pending_block_hashes.iter().copied().collect(),
+        });
+
+        let accounts = self
             .provider
             .get_accounts(&self.pending_accounts.iter().copied().collect::<Vec<_>>())
-            .await
-        else {
-            return false;
-        };
+            .await;
+        let accounts = self.check_fetch(accounts).await?;
         for (address, account) in take(&mut self.pending_accounts)
             .into_iter()
             .zip(accounts.iter())
@@ -329,20 +391,18 @@ impl<BDP: BlockDataProvider> OptimisticDatabase for ProviderDb<BDP> {
                 .insert_account_info(address, account.clone());
         }
 
-        let Ok(slots) = self
+        let slots = self
             .provider
             .get_storage_values(&self.pending_slots.iter().copied().collect::<Vec<_>>())
-            .await
-        else {
-            return false;
-        };
+            .await;
+        let slots = self.check_fetch(slots).await?;
         for ((address, index), value) in take(&mut self.pending_slots).into_iter().zip(slots.iter())
         {
             self.staging_db
                 .insert_account_storage(&address, index, *value);
         }
 
-        let Ok(blocks) = self
+        let blocks = self
             .provider
             .get_blocks(
                 &self
@@ -352,10 +412,8 @@ impl<BDP: BlockDataProvider> OptimisticDatabase for ProviderDb<BDP> {
                     .map(|block_number| (block_number, false))
                     .collect::<Vec<_>>(),
             )
-            .await
-        else {
-            return false;
-        };
+            .await;
+        let blocks = self.check_fetch(blocks).await?;
         for (block_number, block) in take(&mut self.pending_block_hashes)
             .into_iter()
             .zip(blocks.iter())
@@ -371,7 +429,7 @@ impl<BDP: BlockDataProvider> OptimisticDatabase for ProviderDb<BDP> {
             self.current_db = Default::default();
         }
 
-        valid_run
+        Ok(valid_run)
     }
 
     fn is_optimistic(&self) -> bool {
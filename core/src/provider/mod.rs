@@ -1,10 +1,14 @@
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{Address, B256, U256};
 use alloy_rpc_types::Block;
 use revm::primitives::AccountInfo;
 use std::collections::HashMap;
 
-use crate::{interfaces::RaikoResult, MerkleProof};
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    MerkleProof,
+};
 
+pub mod blob;
 pub mod db;
 pub mod rpc;
 
@@ -12,15 +16,50 @@ pub mod rpc;
 pub trait BlockDataProvider {
     async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>>;
 
+    /// Fetches blocks by hash, failing with [RaikoError::Reorg] if the node doesn't know a
+    /// block anymore.
+    async fn get_blocks_by_hash(&self, blocks_to_fetch: &[(B256, bool)])
+        -> RaikoResult<Vec<Block>>;
+
+    /// Pins all account and storage queries to the state of the given block hash.
+    fn pin_block_hash(&mut self, block_hash: B256);
+
     async fn get_accounts(&self, accounts: &[Address]) -> RaikoResult<Vec<AccountInfo>>;
 
     async fn get_storage_values(&self, accounts: &[(Address, U256)]) -> RaikoResult<Vec<U256>>;
 
     async fn get_merkle_proofs(
         &self,
-        block_number: u64,
+        block_hash: B256,
         accounts: HashMap<Address, Vec<U256>>,
         offset: usize,
         num_storage_proofs: usize,
     ) -> RaikoResult<MerkleProof>;
 }
+
+/// Checks that the block with the given number still has the expected hash on the node.
+pub async fn check_canonical<BDP: BlockDataProvider>(
+    provider: &BDP,
+    block_number: u64,
+    block_hash: B256,
+) -> RaikoResult<()> {
+    let blocks = provider.get_blocks(&[(block_number, false)]).await?;
+    match blocks.first().and_then(|block| block.header.hash) {
+        Some(hash) if hash == block_hash => Ok(()),
+        _ => Err(RaikoError::Reorg(block_hash)),
+    }
+}
+
+/// Checks that the given blocks, ordered by ascending block number, form a single chain.
+pub fn check_chain(blocks: &[Block]) -> RaikoResult<()> {
+    for pair in blocks.windows(2) {
+        let hash = pair[0]
+            .header
+            .hash
+            .ok_or_else(|| RaikoError::RPC("No block hash".to_owned()))?;
+        if pair[1].header.parent_hash != hash {
+            return Err(RaikoError::Reorg(hash));
+        }
+    }
+    Ok(())
+}
@@ -1,15 +1,19 @@
 use std::collections::HashMap;
 
-use alloy_primitives::{Address, Bytes, StorageKey, Uint, U256};
+use alloy_primitives::{Address, Bytes, StorageKey, Uint, B256, U256};
 use alloy_provider::{ProviderBuilder, ReqwestProvider, RootProvider};
 use alloy_rpc_client::{ClientBuilder, RpcClient};
-use alloy_rpc_types::{Block, BlockId, BlockNumberOrTag, EIP1186AccountProofResponse};
+use alloy_rpc_types::{
+    Block, BlockId, BlockNumberOrTag, EIP1186AccountProofResponse, RpcBlockHash,
+};
 use alloy_transport_http::Http;
 use raiko_lib::{clear_line, inplace_print};
 use reqwest_alloy::Client;
 use revm::primitives::{AccountInfo, Bytecode};
+use serde_json::Value;
 
 use crate::{
+    diagnostics::NodeReceipt,
     interfaces::{RaikoError, RaikoResult},
     provider::BlockDataProvider,
     MerkleProof,
@@ -18,7 +22,7 @@ use crate::{
 pub struct RpcBlockDataProvider {
     pub provider: ReqwestProvider,
     pub client: RpcClient<Http<Client>>,
-    block_number: u64,
+    block_id: BlockId,
 }
 
 impl RpcBlockDataProvider {
@@ -28,13 +32,84 @@ impl RpcBlockDataProvider {
         Ok(Self {
             provider: ProviderBuilder::new().on_provider(RootProvider::new_http(url.clone())),
             client: ClientBuilder::default().http(url),
-            block_number,
+            block_id: BlockId::from(block_number),
         })
     }
 
     pub fn provider(&self) -> &ReqwestProvider {
         &self.provider
     }
+
+    /// Calls a contract at the latest block and returns the raw return data.
+    pub async fn eth_call(&self, to: Address, data: Bytes) -> RaikoResult<Bytes> {
+        let mut batch = self.client.new_batch();
+        let request = batch
+            .add_call::<_, Bytes>(
+                "eth_call",
+                &(
+                    serde_json::json!({ "to": to, "data": data }),
+                    BlockNumberOrTag::Latest,
+                ),
+            )
+            .map_err(|_| RaikoError::RPC("Failed adding eth_call call to batch".to_owned()))?;
+        batch
+            .send()
+            .await
+            .map_err(|_| RaikoError::RPC("Error sending batch request".to_owned()))?;
+        request
+            .await
+            .map_err(|e| RaikoError::RPC(format!("eth_call to {to} failed: {e}")))
+    }
+
+    /// Returns the receipts of all transactions of the block.
+    pub async fn get_block_receipts(&self, block_hash: B256) -> RaikoResult<Vec<NodeReceipt>> {
+        let mut batch = self.client.new_batch();
+        let request = batch
+            .add_call::<_, Vec<NodeReceipt>>(
+                "eth_getBlockReceipts",
+                &(canonical_block_id(block_hash),),
+            )
+            .map_err(|_| {
+                RaikoError::RPC("Failed adding eth_getBlockReceipts call to batch".to_owned())
+            })?;
+        batch
+            .send()
+            .await
+            .map_err(|_| RaikoError::RPC("Error sending batch request".to_owned()))?;
+        request.await.map_err(|e| {
+            RaikoError::RPC(format!("eth_getBlockReceipts of {block_hash} failed: {e}"))
+        })
+    }
+
+    /// Returns the struct logs of all transactions of the block, traced by the node with
+    /// `debug_traceBlockByHash`.
+    pub async fn debug_trace_block(&self, block_hash: B256) -> RaikoResult<Vec<Value>> {
+        let mut batch = self.client.new_batch();
+        let request = batch
+            .add_call::<_, Vec<Value>>(
+                "debug_traceBlockByHash",
+                &(
+                    block_hash,
+                    serde_json::json!({
+                        "disableStorage": true,
+                        "enableMemory": false,
+                        "enableReturnData": false,
+                    }),
+                ),
+            )
+            .map_err(|_| {
+                RaikoError::RPC("Failed adding debug_traceBlockByHash call to batch".to_owned())
+            })?;
+        batch
+            .send()
+            .await
+            .map_err(|_| RaikoError::RPC("Error sending batch request".to_owned()))?;
+        request.await.map_err(|e| {
+            RaikoError::RPC(format!(
+                "debug_traceBlockByHash of {block_hash} failed: {e}"
+            ))
+        })
+    }
 }
 
 impl BlockDataProvider for RpcBlockDataProvider {
@@ -82,6 +157,54 @@ impl BlockDataProvider for RpcBlockDataProvider {
         Ok(all_blocks)
     }
 
+    async fn get_blocks_by_hash(
+        &self,
+        blocks_to_fetch: &[(B256, bool)],
+    ) -> RaikoResult<Vec<Block>> {
+        let mut all_blocks = Vec::with_capacity(blocks_to_fetch.len());
+
+        let max_batch_size = 32;
+        for blocks_to_fetch in blocks_to_fetch.chunks(max_batch_size) {
+            let mut batch = self.client.new_batch();
+            let mut requests = Vec::with_capacity(max_batch_size);
+
+            for (block_hash, full) in blocks_to_fetch {
+                requests.push((
+                    *block_hash,
+                    Box::pin(
+                        batch
+                            .add_call::<_, Option<Block>>("eth_getBlockByHash", &(block_hash, full))
+                            .map_err(|_| {
+                                RaikoError::RPC(
+                                    "Failed adding eth_getBlockByHash call to batch".to_owned(),
+                                )
+                            })?,
+                    ),
+                ));
+            }
+
+            batch
+                .send()
+                .await
+                .map_err(|_| RaikoError::RPC("Error sending batch request".to_owned()))?;
+
+            // Collect the data from the batch, a missing block means it was reorged out
+            for (block_hash, request) in requests {
+                let block = request
+                    .await
+                    .map_err(|_| RaikoError::RPC("Error collecting request data".to_owned()))?
+                    .ok_or(RaikoError::Reorg(block_hash))?;
+                all_blocks.push(block);
+            }
+        }
+
+        Ok(all_blocks)
+    }
+
+    fn pin_block_hash(&mut self, block_hash: B256) {
+        self.block_id = canonical_block_id(block_hash);
+    }
+
     async fn get_accounts(&self, accounts: &[Address]) -> RaikoResult<Vec<AccountInfo>> {
         let mut all_accounts = Vec::with_capacity(accounts.len());
 
@@ -98,7 +221,7 @@ impl BlockDataProvider for RpcBlockDataProvider {
                     batch
                         .add_call::<_, Uint<64, 1>>(
                             "eth_getTransactionCount",
-                            &(address, Some(BlockId::from(self.block_number))),
+                            &(address, Some(self.block_id)),
                         )
                         .map_err(|_| {
                             RaikoError::RPC(
@@ -110,7 +233,7 @@ impl BlockDataProvider for RpcBlockDataProvider {
                     batch
                         .add_call::<_, Uint<256, 4>>(
                             "eth_getBalance",
-                            &(address, Some(BlockId::from(self.block_number))),
+                            &(address, Some(self.block_id)),
                         )
                         .map_err(|_| {
                             RaikoError::RPC("Failed adding eth_getBalance call to batch".to_owned())
@@ -118,10 +241,7 @@ impl BlockDataProvider for RpcBlockDataProvider {
                 ));
                 code_requests.push(Box::pin(
                     batch
-                        .add_call::<_, Bytes>(
-                            "eth_getCode",
-                            &(address, Some(BlockId::from(self.block_number))),
-                        )
+                        .add_call::<_, Bytes>("eth_getCode", &(address, Some(self.block_id)))
                         .map_err(|_| {
                             RaikoError::RPC("Failed adding eth_getCode call to batch".to_owned())
                         })?,
@@ -183,7 +303,7 @@ impl BlockDataProvider for RpcBlockDataProvider {
                     batch
                         .add_call::<_, U256>(
                             "eth_getStorageAt",
-                            &(address, key, Some(BlockId::from(self.block_number))),
+                            &(address, key, Some(self.block_id)),
                         )
                         .map_err(|_| {
                             RaikoError::RPC(
@@ -216,7 +336,7 @@ impl BlockDataProvider for RpcBlockDataProvider {
 
     async fn get_merkle_proofs(
         &self,
-        block_number: u64,
+        block_hash: B256,
         accounts: HashMap<Address, Vec<U256>>,
         offset: usize,
         num_storage_proofs: usize,
@@ -269,7 +389,7 @@ impl BlockDataProvider for RpcBlockDataProvider {
                                 &(
                                     *address,
                                     keys_to_process.clone(),
-                                    BlockId::from(block_number),
+                                    canonical_block_id(block_hash),
                                 ),
                             )
                             .map_err(|_| {
@@ -314,3 +434,9 @@ impl BlockDataProvider for RpcBlockDataProvider {
         Ok(storage_proofs)
     }
 }
+
+/// [EIP-1898](https://eips.ethereum.org/EIPS/eip-1898) block id which makes the node reject
+/// the request when the block is no longer part of the canonical chain.
+fn canonical_block_id(block_hash: B256) -> BlockId {
+    BlockId::Hash(RpcBlockHash::from_hash(block_hash, Some(true)))
+}
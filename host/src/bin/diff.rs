@@ -0,0 +1,132 @@
+//! Compares the execution of cached inputs opcode by opcode against the traces of the node.
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use raiko_core::{
+    differential::{diff_block, DifferentialReport},
+    provider::rpc::RpcBlockDataProvider,
+};
+use raiko_lib::{input::GuestInput, input_file};
+use serde_json::Value;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "raiko-diff",
+    about = "Compares the execution of cached inputs opcode by opcode against the node"
+)]
+struct Args {
+    /// The directory with the cached `input-<network>-<block_number>.bin` inputs.
+    #[arg(long)]
+    inputs: PathBuf,
+
+    /// The RPC of the L2 node to record missing reference traces from, with the `debug`
+    /// namespace enabled. The traces are stored next to the inputs.
+    #[arg(long)]
+    rpc: Option<String>,
+
+    /// Write the reports as JSON lines instead of a summary.
+    #[arg(long)]
+    json: bool,
+}
+
+fn get_reference_path(dir: &Path, block_number: u64, network: &str) -> PathBuf {
+    dir.join(format!("geth-trace-{network}-{block_number}.json"))
+}
+
+/// Returns the recorded reference traces of the block, recording them first if needed.
+async fn get_reference(
+    dir: &Path,
+    input: &GuestInput,
+    rpc: &Option<String>,
+) -> Result<Option<Vec<Value>>> {
+    let path = get_reference_path(dir, input.block_number, &input.chain_spec.network());
+    if path.exists() {
+        return Ok(Some(serde_json::from_reader(File::open(&path)?)?));
+    }
+    let Some(rpc) = rpc else {
+        return Ok(None);
+    };
+    let provider = RpcBlockDataProvider::new(rpc, input.block_number)?;
+    let traces = provider
+        .debug_trace_block(input.block_hash_reference)
+        .await?;
+    serde_json::to_writer(File::create(&path)?, &traces)?;
+    Ok(Some(traces))
+}
+
+fn print_report(report: &DifferentialReport) {
+    let Some(divergence) = &report.divergence else {
+        println!(
+            "block {}: {} transactions match",
+            report.block_number, report.num_txs
+        );
+        return;
+    };
+    println!(
+        "block {}: tx {} ({}) diverges at step {}, {}: expected {}, found {}",
+        report.block_number,
+        divergence.tx_index,
+        divergence
+            .tx_hash
+            .map(|hash| hash.to_string())
+            .unwrap_or_default(),
+        divergence.step,
+        divergence.diff.field,
+        divergence.diff.expected,
+        divergence.diff.found,
+    );
+    for step in &divergence.context {
+        println!("    {step:?}");
+    }
+    println!("  > ours:      {:?}", divergence.ours);
+    println!("  > reference: {:?}", divergence.reference);
+}
+
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(&args.inputs)
+        .with_context(|| format!("Could not read {:?}", args.inputs))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("input-") && name.ends_with(".bin"))
+        })
+        .collect();
+    paths.sort();
+
+    let mut num_diverging = 0;
+    for path in paths {
+        let (_, input) = input_file::decode(&fs::read(&path)?)
+            .with_context(|| format!("Could not read the input {path:?}"))?;
+        let Some(reference) = get_reference(&args.inputs, &input, &args.rpc).await? else {
+            eprintln!(
+                "block {}: no reference trace, pass --rpc to record it",
+                input.block_number
+            );
+            continue;
+        };
+        let report = diff_block(&input, &reference)?;
+        if report.divergence.is_some() {
+            num_diverging += 1;
+        }
+        if args.json {
+            println!("{}", serde_json::to_string(&report)?);
+        } else {
+            print_report(&report);
+        }
+    }
+
+    Ok(if num_diverging == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
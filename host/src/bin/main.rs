@@ -23,6 +23,13 @@ async fn main() -> HostResult<()> {
     info!("Start config:\n{:#?}", state.opts.proof_request_opt);
     info!("Args:\n{:#?}", state.opts);
 
+    if let Some(blob_archiver) = state.blob_archiver()? {
+        tokio::spawn(blob_archiver.run());
+    }
+    if let Some(submitter) = state.submitter.clone() {
+        tokio::spawn(submitter.run());
+    }
+
     serve(state).await?;
     Ok(())
 }
@@ -0,0 +1,323 @@
+//! Replays cached inputs without access to a node.
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use raiko_core::interfaces::ProofType;
+use raiko_lib::{
+    builder::ChainStrategy,
+    consts::VerifierType,
+    input::{GuestInput, GuestOutput},
+    input_file::{self, Compression},
+    protocol_instance::ProtocolInstance,
+    utils::HeaderHasher,
+    witness::{GuestWitness, WitnessFormat},
+};
+use serde_json::Value;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "raiko-replay",
+    about = "Replays the inputs cached by the host without access to a node"
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Builds the blocks of the inputs and prints their header and instance hash.
+    Run {
+        /// The input files, `input-<network>-<block_number>.bin` or JSON.
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Also run the prover driver of the proof type on every input.
+        #[arg(long)]
+        proof_type: Option<ProofType>,
+
+        /// A JSON file with the prover params, in the format of `config.json` of the host.
+        #[arg(long, requires = "proof_type")]
+        config_path: Option<PathBuf>,
+    },
+    /// Converts the inputs between bincode and JSON, and migrates bincode inputs of older
+    /// versions to the current one.
+    Convert {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// The format to convert to.
+        #[arg(long)]
+        to: Format,
+
+        /// The directory the converted inputs are written to, next to the inputs by default.
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+    /// Prints what the inputs contain.
+    Summary {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+    },
+    /// Compares the size and decoding time of the full and the compact witness of the inputs.
+    Witness {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// The number of times every witness is decoded.
+        #[arg(long, default_value_t = 10)]
+        iterations: u32,
+
+        /// Also compare the cycles of the guest of the zkVM with both witnesses.
+        #[arg(long)]
+        proof_type: Option<ProofType>,
+
+        /// A JSON file with the prover params, in the format of `config.json` of the host.
+        #[arg(long, requires = "proof_type")]
+        config_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Bincode,
+    Json,
+}
+
+impl Format {
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            _ => Format::Bincode,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Bincode => "bin",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// Reads an input and returns it with its schema version. JSON inputs always have the current
+/// schema.
+fn read_input(path: &Path) -> Result<(u16, GuestInput)> {
+    let bytes = fs::read(path).with_context(|| format!("Could not open {path:?}"))?;
+    let input = match Format::of(path) {
+        Format::Bincode => input_file::decode(&bytes).map_err(anyhow::Error::from),
+        Format::Json => serde_json::from_slice(&bytes)
+            .map(|input| (input_file::CURRENT_VERSION, input))
+            .map_err(anyhow::Error::from),
+    };
+    input.with_context(|| format!("Could not read the input {path:?}"))
+}
+
+fn write_input(path: &Path, input: &GuestInput, format: Format) -> Result<()> {
+    match format {
+        Format::Bincode => fs::write(path, input_file::encode(input, Compression::default())?)?,
+        Format::Json => serde_json::to_writer(BufWriter::new(File::create(path)?), input)?,
+    }
+    Ok(())
+}
+
+/// Builds the block of the input and returns the output the provers have to prove.
+fn replay(input: &GuestInput) -> Result<GuestOutput> {
+    let (header, _) = ChainStrategy::build_from(input)?;
+    let hash = header.hash();
+    println!("block {}: built block {hash}", input.block_number);
+    println!("  header: {header:?}");
+    if hash != input.block_hash_reference {
+        bail!(
+            "the built block does not match the block on the node {}",
+            input.block_hash_reference
+        );
+    }
+    let pi = ProtocolInstance::new(input, &header, VerifierType::None)?;
+    let instance_hash = pi.instance_hash();
+    println!("  instance hash: {instance_hash}");
+    Ok(GuestOutput::Success {
+        header,
+        hash: instance_hash,
+    })
+}
+
+fn print_summary(path: &Path, version: u16, input: &GuestInput) -> Result<()> {
+    let num_slots: usize = input
+        .parent_storage
+        .values()
+        .map(|(_, slots)| slots.len())
+        .sum();
+    println!("{}:", path.display());
+    println!("  size:             {} bytes", fs::metadata(path)?.len());
+    println!("  schema version:   {version}");
+    println!("  network:          {}", input.chain_spec.network());
+    println!(
+        "  block:            {} ({})",
+        input.block_number, input.block_hash_reference
+    );
+    println!("  l1 block:         {}", input.taiko.l1_header.number);
+    println!(
+        "  tx list:          {} bytes{}",
+        input.taiko.tx_data.len(),
+        if input.taiko.tx_blob_hash.is_some() {
+            " (blob)"
+        } else {
+            ""
+        }
+    );
+    println!("  accounts:         {}", input.parent_storage.len());
+    println!("  storage slots:    {num_slots}");
+    println!("  contracts:        {}", input.contracts.len());
+    println!("  ancestor headers: {}", input.ancestor_headers.len());
+    Ok(())
+}
+
+fn read_config(config_path: Option<PathBuf>) -> Result<Value> {
+    Ok(match config_path {
+        Some(path) => serde_json::from_reader(File::open(&path)?)
+            .with_context(|| format!("Could not read the config {path:?}"))?,
+        None => Value::Object(Default::default()),
+    })
+}
+
+/// Returns the size of the encoded witness and the average time to decode it and rebuild the
+/// input.
+fn bench_witness(witness: &GuestWitness, iterations: u32) -> Result<(usize, Duration)> {
+    let encoded = bincode::serialize(witness)?;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let witness: GuestWitness = bincode::deserialize(&encoded)?;
+        witness.into_input()?;
+    }
+    Ok((encoded.len(), start.elapsed() / iterations.max(1)))
+}
+
+async fn bench_witnesses(
+    path: &Path,
+    iterations: u32,
+    proof_type: &Option<ProofType>,
+    config: &Value,
+) -> Result<()> {
+    let (_, input) = read_input(path)?;
+    println!("block {}:", input.block_number);
+    for format in [WitnessFormat::Full, WitnessFormat::Compact] {
+        let witness = GuestWitness::new(input.clone(), format);
+        let (size, decode_time) = bench_witness(&witness, iterations)?;
+        print!("  {format:?}: {size} bytes, decoded in {decode_time:?}");
+        if let GuestWitness::Compact(witness) = &witness {
+            print!(
+                ", {} trie nodes, {} contracts",
+                witness.nodes.len(),
+                witness.contracts.len()
+            );
+        }
+        if let Some(proof_type) = proof_type {
+            let mut config = config.clone();
+            config["witness"] = serde_json::to_value(format)?;
+            let estimate = proof_type.estimate(input.clone(), &config).await?;
+            print!(", {} cycles", estimate.total_cycles);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+
+    match args.command {
+        Command::Run {
+            inputs,
+            proof_type,
+            config_path,
+        } => {
+            let config = read_config(config_path)?;
+            let mut num_failed = 0;
+            for path in inputs {
+                let input = match read_input(&path) {
+                    Ok((_, input)) => input,
+                    Err(e) => {
+                        eprintln!("{e:#}");
+                        num_failed += 1;
+                        continue;
+                    }
+                };
+                let output = match replay(&input) {
+                    Ok(output) => output,
+                    Err(e) => {
+                        eprintln!("block {}: {e}", input.block_number);
+                        num_failed += 1;
+                        continue;
+                    }
+                };
+                let Some(proof_type) = &proof_type else {
+                    continue;
+                };
+                match proof_type.run_prover(input.clone(), &output, &config).await {
+                    Ok(proof) => {
+                        println!("  {proof_type} proof: {}", serde_json::to_string(&proof)?)
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "block {}: {proof_type} prover failed: {e}",
+                            input.block_number
+                        );
+                        num_failed += 1;
+                    }
+                }
+            }
+            if num_failed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Convert {
+            inputs,
+            to,
+            out_dir,
+        } => {
+            for path in inputs {
+                let (version, input) = read_input(&path)?;
+                let out_path = match &out_dir {
+                    Some(dir) => dir.join(path.file_name().context("Not a file")?),
+                    None => path.clone(),
+                }
+                .with_extension(to.extension());
+                // Inputs of older versions are migrated in place
+                if out_path == path && version == input_file::CURRENT_VERSION {
+                    println!("{} is already {to:?}", path.display());
+                    continue;
+                }
+                write_input(&out_path, &input, to)?;
+                println!("{} -> {}", path.display(), out_path.display());
+            }
+        }
+        Command::Summary { inputs } => {
+            for path in inputs {
+                let (version, input) = read_input(&path)?;
+                print_summary(&path, version, &input)?;
+            }
+        }
+        Command::Witness {
+            inputs,
+            iterations,
+            proof_type,
+            config_path,
+        } => {
+            let config = read_config(config_path)?;
+            for path in inputs {
+                bench_witnesses(&path, iterations, &proof_type, &config).await?;
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,405 @@
+use std::{
+    collections::BTreeSet,
+    io::ErrorKind,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{Address, Log, B256};
+use alloy_provider::{Provider, ProviderBuilder, ReqwestProvider, RootProvider};
+use alloy_rpc_types::Filter;
+use raiko_core::{
+    preflight::block_time_to_block_slot,
+    provider::{
+        blob::{verify_blob, BeaconBlobProvider, BlobProvider, LocalArchiveBlobProvider},
+        rpc::RpcBlockDataProvider,
+        BlockDataProvider,
+    },
+};
+use raiko_lib::{
+    consts::{ChainSpec, ProtocolVersion},
+    input::BlockProposedFork,
+};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+use crate::interfaces::{HostError, HostResult};
+
+/// Name of the file in the archive that stores the last L1 block that was archived.
+const LAST_BLOCK_FILE: &str = "last_block";
+
+/// Name of the file in the archive that lists the blobs that could not be archived, one
+/// `<l1 block number> <versioned hash>` per line.
+const FAILED_BLOBS_FILE: &str = "failed_blobs";
+
+/// Maximum number of L1 blocks to query events for at once.
+const MAX_BLOCK_RANGE: u64 = 1000;
+
+/// Number of L1 blocks the archiver stays behind the head, so it only archives the blobs of
+/// blocks that are final (two epochs) and won't be reorged out anymore.
+const FINALITY_DEPTH: u64 = 64;
+
+/// How often the blobs that could not be archived are tried again.
+const FAILED_BLOBS_RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Follows the L1 chain and stores the blobs of all proposed L2 blocks in a local archive,
+/// so blocks can still be proven after the blobs are pruned by the beacon node.
+pub struct BlobArchiver {
+    l1_chain_spec: ChainSpec,
+    taiko_chain_spec: ChainSpec,
+    archive: LocalArchiveBlobProvider,
+    beacon: BeaconBlobProvider,
+    provider: ReqwestProvider,
+    block_provider: RpcBlockDataProvider,
+}
+
+impl BlobArchiver {
+    pub fn new(
+        l1_chain_spec: ChainSpec,
+        taiko_chain_spec: ChainSpec,
+        archive_path: PathBuf,
+    ) -> HostResult<Self> {
+        let beacon_rpc = l1_chain_spec.beacon_rpc.clone().ok_or_else(|| {
+            HostError::InvalidRequestConfig(
+                "Beacon RPC URL is required for the blob archiver".to_owned(),
+            )
+        })?;
+        let url = reqwest::Url::parse(&l1_chain_spec.rpc)
+            .map_err(|_| HostError::RPC("Invalid RPC URL".to_owned()))?;
+        let block_provider = RpcBlockDataProvider::new(&l1_chain_spec.rpc, 0)?;
+        std::fs::create_dir_all(&archive_path)?;
+        Ok(Self {
+            l1_chain_spec,
+            taiko_chain_spec,
+            archive: LocalArchiveBlobProvider { dir: archive_path },
+            beacon: BeaconBlobProvider { url: beacon_rpc },
+            provider: ProviderBuilder::new().on_provider(RootProvider::new_http(url)),
+            block_provider,
+        })
+    }
+
+    /// Archives blobs forever, resuming from the last archived L1 block.
+    pub async fn run(self) {
+        let poll_interval = Duration::from_secs(self.l1_chain_spec.seconds_per_slot.max(1));
+        let mut next_block = None;
+        let mut last_retry: Option<Instant> = None;
+        loop {
+            match self.archive_new_blocks(next_block).await {
+                Ok(block) => next_block = Some(block),
+                Err(e) => warn!("Blob archiver failed: {e}"),
+            }
+            if last_retry
+                .filter(|retry| retry.elapsed() < FAILED_BLOBS_RETRY_INTERVAL)
+                .is_none()
+            {
+                if let Err(e) = self.retry_failed_blobs().await {
+                    warn!("Blob archiver failed to retry failed blobs: {e}");
+                }
+                last_retry = Some(Instant::now());
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Archives the blobs of all final L1 blocks from `from_block` on and returns the next
+    /// block to archive.
+    async fn archive_new_blocks(&self, from_block: Option<u64>) -> HostResult<u64> {
+        let head = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| HostError::RPC(e.to_string()))?
+            .saturating_sub(FINALITY_DEPTH);
+        let mut from_block = match from_block {
+            Some(block) => block,
+            None => self.read_last_block().await.map_or(head, |block| block + 1),
+        };
+        while from_block <= head {
+            let to_block = (from_block + MAX_BLOCK_RANGE - 1).min(head);
+            self.archive_range(from_block, to_block).await?;
+            tokio::fs::write(self.archive.dir.join(LAST_BLOCK_FILE), to_block.to_string()).await?;
+            from_block = to_block + 1;
+        }
+        Ok(from_block)
+    }
+
+    async fn archive_range(&self, from_block: u64, to_block: u64) -> HostResult<()> {
+        let Some(l1_address) = self.taiko_chain_spec.l1_contract else {
+            return Err(HostError::InvalidRequestConfig(
+                "No L1 contract address in the chain spec".to_owned(),
+            ));
+        };
+        // Blocks can be proposed with any of the protocol versions of the chain
+        let mut versions = BTreeSet::from([ProtocolVersion::V1]);
+        versions.extend(self.taiko_chain_spec.protocol_forks.keys().copied());
+        for version in versions {
+            self.archive_events(l1_address, version, from_block, to_block)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn archive_events(
+        &self,
+        l1_address: Address,
+        version: ProtocolVersion,
+        from_block: u64,
+        to_block: u64,
+    ) -> HostResult<()> {
+        let filter = Filter::new()
+            .address(l1_address)
+            .from_block(from_block)
+            .to_block(to_block)
+            .event_signature(BlockProposedFork::signature_hash(version));
+        let logs = self
+            .provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| HostError::RPC(e.to_string()))?;
+
+        for log in logs {
+            let Some(log_struct) = Log::new(
+                log.address(),
+                log.topics().to_vec(),
+                log.data().data.clone(),
+            ) else {
+                return Err(HostError::Conversion("Could not create log".to_owned()));
+            };
+            let event = BlockProposedFork::decode_log(version, &log_struct)
+                .map_err(|_| HostError::Conversion("Could not decode log".to_owned()))?;
+            if !event.blob_used() {
+                continue;
+            }
+            let (Some(l1_block_number), Some(tx_hash)) = (log.block_number, log.transaction_hash)
+            else {
+                return Err(HostError::RPC(
+                    "Log without block or transaction".to_owned(),
+                ));
+            };
+            let tx = self
+                .provider
+                .get_transaction_by_hash(tx_hash)
+                .await
+                .map_err(|e| HostError::RPC(e.to_string()))?;
+            let Some(blob_hash) = tx
+                .blob_versioned_hashes
+                .and_then(|hashes| hashes.get(event.blob_index()).copied())
+            else {
+                warn!("No blob attached to the propose tx {tx_hash}");
+                continue;
+            };
+            // A blob that can't be fetched, e.g. because it was already pruned, must not stop
+            // the archiver from archiving the blobs after it
+            if let Err(e) = self.archive_blob(l1_block_number, blob_hash).await {
+                warn!("Could not archive blob {blob_hash} of L1 block {l1_block_number}: {e}");
+                self.record_failed_blob(l1_block_number, blob_hash).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn archive_blob(&self, l1_block_number: u64, blob_hash: B256) -> HostResult<()> {
+        if self.archive.blob_path(blob_hash).exists() {
+            return Ok(());
+        }
+        let blocks = self
+            .block_provider
+            .get_blocks(&[(l1_block_number, false)])
+            .await?;
+        let block = blocks
+            .first()
+            .ok_or_else(|| HostError::RPC(format!("No L1 block {l1_block_number}")))?;
+        let slot_id = block_time_to_block_slot(
+            block.header.timestamp,
+            self.l1_chain_spec.genesis_time,
+            self.l1_chain_spec.seconds_per_slot,
+        )?;
+        let blob = self.beacon.get_blob(slot_id, blob_hash).await?;
+        verify_blob(&blob, blob_hash)?;
+        self.archive.store_blob(blob_hash, &blob).await?;
+        info!("Archived blob {blob_hash} of L1 block {l1_block_number}");
+        Ok(())
+    }
+
+    /// Tries to archive the blobs in the failed blobs file again and keeps only the ones
+    /// that still fail in it.
+    async fn retry_failed_blobs(&self) -> HostResult<()> {
+        let path = self.archive.dir.join(FAILED_BLOBS_FILE);
+        let failed_blobs = match tokio::fs::read_to_string(&path).await {
+            Ok(failed_blobs) => failed_blobs,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut remaining = BTreeSet::new();
+        for line in failed_blobs.lines().filter(|line| !line.trim().is_empty()) {
+            let parsed = line
+                .split_once(' ')
+                .and_then(|(block, hash)| Some((block.parse().ok()?, hash.parse().ok()?)));
+            let Some((l1_block_number, blob_hash)) = parsed else {
+                warn!("Invalid line in {path:?}: {line}");
+                remaining.insert(line.to_owned());
+                continue;
+            };
+            if let Err(e) = self.archive_blob(l1_block_number, blob_hash).await {
+                warn!("Could not archive blob {blob_hash} of L1 block {l1_block_number}: {e}");
+                remaining.insert(line.to_owned());
+            }
+        }
+        if remaining.is_empty() {
+            tokio::fs::remove_file(&path).await?;
+        } else {
+            let remaining: String = remaining.into_iter().map(|line| line + "\n").collect();
+            tokio::fs::write(&path, remaining).await?;
+        }
+        Ok(())
+    }
+
+    async fn record_failed_blob(&self, l1_block_number: u64, blob_hash: B256) -> HostResult<()> {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.archive.dir.join(FAILED_BLOBS_FILE))
+            .await?;
+        file.write_all(format!("{l1_block_number} {blob_hash}\n").as_bytes())
+            .await?;
+        Ok(())
+    }
+
+    async fn read_last_block(&self) -> Option<u64> {
+        tokio::fs::read_to_string(self.archive.dir.join(LAST_BLOCK_FILE))
+            .await
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::hex;
+    use alloy_rpc_types::Block;
+    use axum::{
+        routing::{get, post},
+        Json, Router,
+    };
+    use c_kzg::BYTES_PER_BLOB;
+    use raiko_core::provider::blob::calc_blob_versioned_hash;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const HEAD: u64 = 1000;
+
+    fn rpc_result(req: &Value) -> Value {
+        let result = match req["method"].as_str().unwrap_or_default() {
+            "eth_chainId" => json!("0x1"),
+            "eth_blockNumber" => json!(format!("{HEAD:#x}")),
+            "eth_getLogs" => json!([]),
+            "eth_getBlockByNumber" => {
+                let mut block = Block::default();
+                block.header.timestamp = 1_200;
+                serde_json::to_value(block).unwrap()
+            }
+            method => panic!("Unexpected call to {method}"),
+        };
+        json!({ "jsonrpc": "2.0", "id": req["id"], "result": result })
+    }
+
+    /// Starts a minimal L1 node and beacon node, the beacon node has the blob `blob` in
+    /// every slot.
+    async fn start_node(blob: Vec<u8>) -> String {
+        let rpc = |Json(req): Json<Value>| async move {
+            Json(match req.as_array() {
+                Some(batch) => batch.iter().map(rpc_result).collect(),
+                None => rpc_result(&req),
+            })
+        };
+        let beacon = move || {
+            let blob = format!("0x{}", hex::encode(&blob));
+            async move {
+                Json(json!({
+                    "data": [{ "index": "0", "blob": blob, "kzg_commitment": "", "kzg_proof": "" }]
+                }))
+            }
+        };
+        let router = Router::new()
+            .route("/", post(rpc))
+            .route("/eth/v1/beacon/blob_sidecars/:slot", get(beacon));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    async fn archiver(name: &str, blob: Vec<u8>) -> BlobArchiver {
+        let node = start_node(blob).await;
+        let l1_chain_spec = ChainSpec {
+            rpc: node.clone(),
+            beacon_rpc: Some(node),
+            genesis_time: 1,
+            seconds_per_slot: 12,
+            ..Default::default()
+        };
+        let taiko_chain_spec = ChainSpec {
+            l1_contract: Some(Address::with_last_byte(1)),
+            ..Default::default()
+        };
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        BlobArchiver::new(l1_chain_spec, taiko_chain_spec, dir).unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_archive_final_blocks() {
+        let archiver = archiver("raiko_blob_archiver_final_test", vec![]).await;
+
+        // Without an archived block the archiver starts at the last final block
+        let next_block = archiver.archive_new_blocks(None).await.unwrap();
+        assert_eq!(next_block, HEAD - FINALITY_DEPTH + 1);
+        assert_eq!(
+            archiver.read_last_block().await,
+            Some(HEAD - FINALITY_DEPTH)
+        );
+
+        // Blocks that are not final yet are not archived
+        let next_block = archiver.archive_new_blocks(Some(next_block)).await.unwrap();
+        assert_eq!(next_block, HEAD - FINALITY_DEPTH + 1);
+
+        std::fs::remove_dir_all(&archiver.archive.dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_retry_failed_blobs() {
+        let blob = vec![0u8; BYTES_PER_BLOB];
+        let blob_hash = calc_blob_versioned_hash(&blob).unwrap();
+        let archiver = archiver("raiko_blob_archiver_retry_test", blob.clone()).await;
+        let missing_hash = B256::with_last_byte(1);
+        archiver.record_failed_blob(100, blob_hash).await.unwrap();
+        archiver
+            .record_failed_blob(101, missing_hash)
+            .await
+            .unwrap();
+
+        // The blob the beacon node has is archived, the other one is tried again later
+        archiver.retry_failed_blobs().await.unwrap();
+        assert_eq!(
+            std::fs::read(archiver.archive.blob_path(blob_hash)).unwrap(),
+            blob
+        );
+        let failed_blobs_path = archiver.archive.dir.join(FAILED_BLOBS_FILE);
+        assert_eq!(
+            std::fs::read_to_string(&failed_blobs_path).unwrap(),
+            format!("101 {missing_hash}\n")
+        );
+
+        // Nothing is left to retry once all blobs are archived
+        std::fs::remove_file(&failed_blobs_path).unwrap();
+        archiver.record_failed_blob(100, blob_hash).await.unwrap();
+        archiver.retry_failed_blobs().await.unwrap();
+        assert!(!failed_blobs_path.exists());
+
+        std::fs::remove_dir_all(&archiver.archive.dir).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 use axum::response::IntoResponse;
-use raiko_core::interfaces::ProofType;
-use raiko_lib::prover::ProverError;
+use raiko_core::interfaces::{ProofType, RaikoError};
+use raiko_lib::{input_file::InputFileError, prover::ProverError};
 use utoipa::ToSchema;
 
 /// The standardized error returned by the Raiko host.
@@ -47,6 +47,14 @@ pub enum HostError {
     #[schema(value_type = Value)]
     Core(#[from] raiko_core::interfaces::RaikoError),
 
+    /// For cached inputs that can't be used by this version of the host.
+    #[error("The cached input {path} can't be used: {source}")]
+    #[schema(value_type = Value)]
+    IncompatibleInput {
+        path: String,
+        source: InputFileError,
+    },
+
     /// For requesting a proof of a type that is not supported.
     #[error("Feature not supported: {0}")]
     #[schema(value_type = Value)]
@@ -60,6 +68,15 @@ pub enum HostError {
 
 impl IntoResponse for HostError {
     fn into_response(self) -> axum::response::Response {
+        if let HostError::Core(RaikoError::BlockMismatch(report)) = &self {
+            return axum::Json(serde_json::json!({
+                "status": "error",
+                "error": "block_mismatch",
+                "message": self.to_string(),
+                "data": report,
+            }))
+            .into_response();
+        }
         let (error, message) = match self {
             HostError::InvalidRequestConfig(e) => ("invalid_request_config".to_string(), e),
             HostError::InvalidAddress(e) => ("invalid_address".to_string(), e),
@@ -70,6 +87,10 @@ impl IntoResponse for HostError {
             HostError::JoinHandle(e) => ("join_handle_error".to_string(), e.to_string()),
             HostError::Guest(e) => ("guest_error".to_string(), e.to_string()),
             HostError::Core(e) => ("core_error".to_string(), e.to_string()),
+            HostError::IncompatibleInput { path, source } => (
+                "incompatible_input".to_string(),
+                format!("{path}: {source}"),
+            ),
             HostError::FeatureNotSupportedError(t) => {
                 ("feature_not_supported_error".to_string(), t.to_string())
             }
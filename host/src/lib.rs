@@ -12,21 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod blob_archiver;
 pub mod interfaces;
 pub mod metrics;
 pub mod server;
+pub mod submitter;
 
-use std::{alloc, path::PathBuf};
+use std::{alloc, collections::HashMap, path::PathBuf};
 
 use anyhow::Context;
 use cap::Cap;
 use clap::Parser;
-use raiko_core::{interfaces::ProofRequestOpt, merge};
-use raiko_lib::consts::SupportedChainSpecs;
+use raiko_core::{
+    interfaces::ProofRequestOpt,
+    merge,
+    provider::blob::{default_blob_sources, BlobSource},
+};
+use raiko_lib::consts::{ChainSpec, SupportedChainSpecs};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::interfaces::HostResult;
+use crate::{
+    blob_archiver::BlobArchiver,
+    interfaces::{HostError, HostResult},
+    submitter::{GasPriceStrategy, ProofSubmitter},
+};
 
 #[global_allocator]
 static ALLOCATOR: Cap<alloc::System> = Cap::new(alloc::System, usize::MAX);
@@ -51,6 +61,10 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_submit_gas_price_percent() -> u64 {
+    100
+}
+
 #[derive(Default, Clone, Serialize, Deserialize, Debug, Parser)]
 #[command(
     name = "raiko", 
@@ -104,6 +118,31 @@ pub struct Cli {
     #[arg(long, require_equals = true)]
     /// Set jwt secret for auth
     jwt_secret: Option<String>,
+
+    #[arg(long, require_equals = true)]
+    /// Archive the blobs of all blocks proposed on `network` in a local directory, so blocks
+    /// can still be proven after the beacon node pruned their blobs.
+    pub blob_archive_path: Option<PathBuf>,
+
+    #[arg(skip)]
+    /// The sources the blobs of each L1 network are fetched from, in fallback order. Networks
+    /// without sources only use the `beacon_rpc` of their chain spec.
+    pub blob_sources: HashMap<String, Vec<BlobSource>>,
+
+    #[arg(long)]
+    /// Submit every completed proof to the L1 contract of its network in a `proveBlock`
+    /// transaction, signed with the private key in `SENDER_PRIV_KEY`.
+    pub submit_proofs: bool,
+
+    #[arg(long, require_equals = true, default_value = "100")]
+    #[serde(default = "default_submit_gas_price_percent")]
+    /// The gas price of submitted proofs in percent of the current L1 gas price
+    pub submit_gas_price_percent: u64,
+
+    #[arg(long, require_equals = true)]
+    /// The maximum gas price in wei to submit proofs at, proofs are delayed and retried while
+    /// the L1 gas price is higher
+    pub submit_max_gas_price: Option<u128>,
 }
 
 impl Cli {
@@ -118,12 +157,34 @@ impl Cli {
         *self = serde_json::from_value(config)?;
         Ok(())
     }
+
+    /// Returns the sources the blobs of the L1 chain are fetched from, in fallback order. The
+    /// blob archive is tried first for `l1_network`, whose blobs it stores.
+    pub fn blob_sources(&self, l1_chain_spec: &ChainSpec) -> Vec<BlobSource> {
+        let mut blob_sources = Vec::new();
+        if let (Some(blob_archive_path), Some(l1_network)) =
+            (&self.blob_archive_path, &self.proof_request_opt.l1_network)
+        {
+            if *l1_network == l1_chain_spec.name {
+                blob_sources.push(BlobSource::LocalArchive(
+                    blob_archive_path.to_string_lossy().into_owned(),
+                ));
+            }
+        }
+        match self.blob_sources.get(&l1_chain_spec.name) {
+            Some(sources) if !sources.is_empty() => blob_sources.extend(sources.iter().cloned()),
+            _ => blob_sources.extend(default_blob_sources(l1_chain_spec)),
+        }
+        blob_sources
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ProverState {
     pub opts: Cli,
     pub chain_specs: SupportedChainSpecs,
+    /// Submits completed proofs to L1 when enabled.
+    pub submitter: Option<ProofSubmitter>,
 }
 
 impl ProverState {
@@ -146,7 +207,48 @@ impl ProverState {
             }
         }
 
-        Ok(Self { opts, chain_specs })
+        let submitter = if opts.submit_proofs {
+            Some(ProofSubmitter::new(
+                GasPriceStrategy {
+                    percent: opts.submit_gas_price_percent,
+                    max_gas_price: opts.submit_max_gas_price,
+                },
+                opts.cache_path.clone(),
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            opts,
+            chain_specs,
+            submitter,
+        })
+    }
+
+    /// Creates the blob archiver if a blob archive path is configured.
+    pub fn blob_archiver(&self) -> HostResult<Option<BlobArchiver>> {
+        let Some(blob_archive_path) = &self.opts.blob_archive_path else {
+            return Ok(None);
+        };
+        let (Some(network), Some(l1_network)) = (
+            &self.opts.proof_request_opt.network,
+            &self.opts.proof_request_opt.l1_network,
+        ) else {
+            return Err(HostError::InvalidRequestConfig(
+                "The blob archiver needs both network and l1_network".to_owned(),
+            ));
+        };
+        let get_chain_spec = |network: &str| {
+            self.chain_specs.get_chain_spec(network).ok_or_else(|| {
+                HostError::InvalidRequestConfig(format!("Unsupported network: {network}"))
+            })
+        };
+        Ok(Some(BlobArchiver::new(
+            get_chain_spec(l1_network)?,
+            get_chain_spec(network)?,
+            blob_archive_path.clone(),
+        )?))
     }
 }
 
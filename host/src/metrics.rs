@@ -6,6 +6,7 @@ use prometheus::{
     IntCounterVec, IntGauge,
 };
 use raiko_core::interfaces::ProofType;
+use raiko_lib::builder::SkippedTx;
 
 lazy_static! {
     pub static ref HOST_REQ_COUNT: IntCounterVec = register_int_counter_vec!(
@@ -56,6 +57,12 @@ lazy_static! {
         &["block_id", "success"]
     )
     .unwrap();
+    pub static ref SKIPPED_TX_COUNT: IntCounterVec = register_int_counter_vec!(
+        "skipped_transaction_count",
+        "the number of transactions or tx lists dropped from proven blocks",
+        &["reason", "block_id"]
+    )
+    .unwrap();
     pub static ref CONCURRENT_REQUESTS: IntGauge = register_int_gauge!(
         "concurrent_requests",
         "number of requests currently being processed"
@@ -124,6 +131,18 @@ pub fn inc_guest_error(guest: &ProofType, block_id: u64) {
     GUEST_PROOF_ERROR_COUNT.with(&labels).inc();
 }
 
+/// Increment the count of dropped transactions for the reason of every skipped transaction.
+pub fn inc_skipped_txs(skipped: &[SkippedTx], block_id: u64) {
+    let block_id = block_id.to_string();
+    for tx in skipped {
+        let labels = labels! {
+            "reason" => tx.reason.label(),
+            "block_id" => block_id.as_str(),
+        };
+        SKIPPED_TX_COUNT.with(&labels).inc();
+    }
+}
+
 /// Convert a duration to a float with 3 decimal places (seconds,milliseconds).
 fn duration_to_f64(d: Duration) -> f64 {
     (d.as_secs_f64() * 1_000.0).round() / 1_000.0
@@ -1,5 +1,9 @@
 use axum::{response::IntoResponse, Router};
-use raiko_lib::input::GuestOutput;
+use raiko_core::calldata::ProveBlockCall;
+use raiko_lib::{
+    builder::{trace::TxTrace, SkippedTx},
+    input::GuestOutput,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tower::ServiceBuilder;
@@ -58,6 +62,26 @@ pub struct ProofResponse {
     proof: Option<String>,
     /// The quote.
     quote: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<Value>)]
+    /// The `proveBlock(blockId, input)` call to submit the proof with, if requested.
+    prove_block: Option<ProveBlockCall>,
+    #[serde(default)]
+    #[schema(value_type = Option<Value>)]
+    /// The traces of the transactions selected with `trace`, if requested.
+    traces: Option<Vec<TxTrace>>,
+    #[serde(default)]
+    #[schema(value_type = Option<Value>)]
+    /// The transactions of the tx list that were not included in the block, returned by the
+    /// native prover.
+    skipped: Option<Vec<SkippedTx>>,
+}
+
+impl ProofResponse {
+    /// The transactions that were not included in the block, if the prover reported them.
+    pub fn skipped(&self) -> &[SkippedTx] {
+        self.skipped.as_deref().unwrap_or_default()
+    }
 }
 
 impl IntoResponse for ProofResponse {
@@ -1,43 +1,79 @@
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
 
 use axum::{debug_handler, extract::State, routing::post, Json, Router};
 use raiko_core::{
     interfaces::{ProofRequest, RaikoError},
+    preflight::PreflightReport,
     provider::rpc::RpcBlockDataProvider,
     Raiko,
 };
 use raiko_lib::{
+    builder::trace::TxTrace,
+    consts::ChainSpec,
     input::{get_input_path, GuestInput},
+    input_file::{self, Compression},
     Measurement,
 };
+use serde::Deserialize;
 use serde_json::Value;
-use tracing::{debug, info};
-use utoipa::OpenApi;
+use tracing::{debug, info, warn};
+use utoipa::{OpenApi, ToSchema};
 
 use crate::{
     interfaces::{HostError, HostResult},
     memory,
     metrics::{
         dec_current_req, inc_current_req, inc_guest_error, inc_guest_req_count, inc_guest_success,
-        inc_host_error, inc_host_req_count, observe_guest_time, observe_prepare_input_time,
-        observe_total_time,
+        inc_host_error, inc_host_req_count, inc_skipped_txs, observe_guest_time,
+        observe_prepare_input_time, observe_total_time,
     },
     server::api::v1::ProofResponse,
-    ProverState,
+    submitter::SubmitTask,
+    Cli, ProverState,
 };
 
+/// Returns the cached input of the block, migrated to the current input format. Inputs that
+/// this version of the host can't read are an error instead of being fetched again, so they
+/// are not silently overwritten.
 fn get_cached_input(
     cache_path: &Option<PathBuf>,
     block_number: u64,
     network: &str,
-) -> Option<GuestInput> {
-    let dir = cache_path.as_ref()?;
+    chain_id: u64,
+) -> HostResult<Option<GuestInput>> {
+    let Some(dir) = cache_path.as_ref() else {
+        return Ok(None);
+    };
 
     let path = get_input_path(dir, block_number, network);
 
-    let file = File::open(path).ok()?;
+    let Ok(bytes) = fs::read(&path) else {
+        return Ok(None);
+    };
+
+    let incompatible = |source| HostError::IncompatibleInput {
+        path: path.display().to_string(),
+        source,
+    };
+    if let Some(header) = input_file::read_header(&bytes).map_err(incompatible)? {
+        header
+            .check_block(chain_id, block_number)
+            .map_err(incompatible)?;
+    }
+    let (version, input) = input_file::decode(&bytes).map_err(incompatible)?;
+    if version != input_file::CURRENT_VERSION {
+        info!("migrating input {path:?} from version {version}");
+        fs::write(&path, encode_input(&input)?)?;
+    }
+
+    Ok(Some(input))
+}
 
-    bincode::deserialize_from(file).ok()
+fn encode_input(input: &GuestInput) -> HostResult<Vec<u8>> {
+    input_file::encode(input, Compression::default()).map_err(|e| HostError::Anyhow(e.into()))
 }
 
 fn set_cached_input(
@@ -56,17 +92,98 @@ fn set_cached_input(
         return Ok(());
     }
 
+    info!("caching input for {path:?}");
+
+    fs::write(&path, encode_input(input)?).map_err(Into::into)
+}
+
+fn get_report_path(dir: &Path, block_number: u64, network: &str) -> PathBuf {
+    dir.join(format!("report-{network}-{block_number}.json"))
+}
+
+fn get_traces_path(dir: &Path, block_number: u64, network: &str) -> PathBuf {
+    dir.join(format!("traces-{network}-{block_number}.json"))
+}
+
+fn set_cached_traces(
+    cache_path: &Option<PathBuf>,
+    block_number: u64,
+    network: &str,
+    traces: &[TxTrace],
+) -> HostResult<()> {
+    let Some(dir) = cache_path.as_ref() else {
+        return Ok(());
+    };
+
+    let path = get_traces_path(dir, block_number, network);
+
     let file = File::create(&path).map_err(<std::io::Error as Into<HostError>>::into)?;
 
-    info!("caching input for {path:?}");
+    info!("caching traces for {path:?}");
+
+    serde_json::to_writer_pretty(file, traces).map_err(Into::into)
+}
+
+fn set_cached_report(
+    cache_path: &Option<PathBuf>,
+    block_number: u64,
+    network: &str,
+    report: &PreflightReport,
+) -> HostResult<()> {
+    let Some(dir) = cache_path.as_ref() else {
+        return Ok(());
+    };
 
-    bincode::serialize_into(file, input).map_err(|e| HostError::Anyhow(e.into()))
+    let path = get_report_path(dir, block_number, network);
+
+    let file = File::create(&path).map_err(<std::io::Error as Into<HostError>>::into)?;
+
+    info!("caching preflight report for {path:?}");
+
+    serde_json::to_writer_pretty(file, report).map_err(Into::into)
+}
+
+/// Returns the cached input for the request if there is one, or generates it otherwise.
+async fn get_input(
+    opts: &Cli,
+    raiko: &Raiko,
+    proof_request: &ProofRequest,
+    taiko_chain_spec: &ChainSpec,
+) -> HostResult<GuestInput> {
+    // Check for a cached input for the given request config.
+    if let Some(cached_input) = get_cached_input(
+        &opts.cache_path,
+        proof_request.block_number,
+        &proof_request.network.to_string(),
+        taiko_chain_spec.chain_id,
+    )? {
+        debug!("Using cached input");
+        return Ok(cached_input);
+    }
+
+    memory::reset_stats();
+    let measurement = Measurement::start("Generating input...", false);
+    let provider =
+        RpcBlockDataProvider::new(&taiko_chain_spec.rpc, proof_request.block_number - 1)?;
+    let (input, report) = raiko.generate_input(provider).await?;
+    // Store the report right away so it's also available when building the block fails.
+    set_cached_report(
+        &opts.cache_path,
+        proof_request.block_number,
+        &proof_request.network.to_string(),
+        &report,
+    )?;
+    let input_time = measurement.stop_with("=> Input generated");
+    observe_prepare_input_time(proof_request.block_number, input_time, true);
+    memory::print_stats("Input generation peak memory used: ");
+    Ok(input)
 }
 
 async fn handle_proof(
     ProverState {
         opts,
         chain_specs: support_chain_specs,
+        submitter,
     }: ProverState,
     req: Value,
 ) -> HostResult<ProofResponse> {
@@ -74,6 +191,9 @@ async fn handle_proof(
     // options with the request from the client.
     let mut config = opts.proof_request_opt.clone();
     config.merge(&req)?;
+    let with_calldata = config.calldata.unwrap_or_default();
+    let diagnose = config.diagnose.unwrap_or_default();
+    let trace_config = config.trace.clone();
 
     // Construct the actual proof request from the available configs.
     let proof_request = ProofRequest::try_from(config)?;
@@ -85,13 +205,6 @@ async fn handle_proof(
         proof_request.block_number, proof_request.network
     );
 
-    // Check for a cached input for the given request config.
-    let cached_input = get_cached_input(
-        &opts.cache_path,
-        proof_request.block_number,
-        &proof_request.network.to_string(),
-    );
-
     let l1_chain_spec = support_chain_specs
         .get_chain_spec(&proof_request.l1_network.to_string())
         .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
@@ -103,29 +216,33 @@ async fn handle_proof(
     // Execute the proof generation.
     let total_time = Measurement::start("", false);
 
+    let blob_sources = opts.blob_sources(&l1_chain_spec);
     let raiko = Raiko::new(
         l1_chain_spec.clone(),
         taiko_chain_spec.clone(),
         proof_request.clone(),
-    );
-    let input = if let Some(cached_input) = cached_input {
-        debug!("Using cached input");
-        cached_input
-    } else {
-        memory::reset_stats();
-        let measurement = Measurement::start("Generating input...", false);
-        let provider = RpcBlockDataProvider::new(
-            &taiko_chain_spec.rpc.clone(),
-            proof_request.block_number - 1,
+    )
+    .with_blob_sources(blob_sources);
+    let input = get_input(&opts, &raiko, &proof_request, &taiko_chain_spec).await?;
+    // Trace before building the block is checked, so the traces are also available for
+    // bad blocks.
+    let traces = trace_config
+        .map(|config| raiko.trace(&input, &config))
+        .transpose()?;
+    if let Some(traces) = &traces {
+        set_cached_traces(
+            &opts.cache_path,
+            proof_request.block_number,
+            &proof_request.network.to_string(),
+            traces,
         )?;
-        let input = raiko.generate_input(provider).await?;
-        let input_time = measurement.stop_with("=> Input generated");
-        observe_prepare_input_time(proof_request.block_number, input_time, true);
-        memory::print_stats("Input generation peak memory used: ");
-        input
-    };
+    }
     memory::reset_stats();
-    let output = raiko.get_output(&input)?;
+    let output = if diagnose {
+        raiko.get_output_with_diagnostics(&input).await?
+    } else {
+        raiko.get_output(&input)?
+    };
     memory::print_stats("Guest program peak memory used: ");
 
     memory::reset_stats();
@@ -165,7 +282,47 @@ async fn handle_proof(
         &input,
     )?;
 
-    ProofResponse::try_from(proof)
+    let prove_block = match (with_calldata || submitter.is_some())
+        .then(|| raiko.prove_block_call(&input, &output, &proof, None))
+        .transpose()
+    {
+        Ok(prove_block) => prove_block,
+        Err(e) if with_calldata => return Err(e.into()),
+        Err(e) => {
+            // Don't fail the proof request just because the proof can't be submitted
+            warn!(
+                "Not submitting the proof of block {}: {e}",
+                proof_request.block_number
+            );
+            None
+        }
+    };
+    if let (Some(submitter), Some(prove_block)) = (&submitter, &prove_block) {
+        // Invalid proofs would only waste gas
+        let verified = raiko.verify(input.clone(), &output, &proof).await;
+        match (verified, taiko_chain_spec.l1_contract) {
+            (Err(e), _) => warn!(
+                "Not submitting the proof of block {}, verification failed: {e}",
+                proof_request.block_number
+            ),
+            (Ok(()), Some(l1_contract)) => submitter.submit(SubmitTask {
+                network: proof_request.network.clone(),
+                block_number: proof_request.block_number,
+                l1_rpc: l1_chain_spec.rpc.clone(),
+                l1_chain_id: l1_chain_spec.chain_id,
+                l1_contract,
+                prove_block: prove_block.clone(),
+            })?,
+            (Ok(()), None) => {
+                warn!("Not submitting the proof, no L1 contract address in the chain spec")
+            }
+        }
+    }
+    let mut response = ProofResponse::try_from(proof)?;
+    inc_skipped_txs(response.skipped(), proof_request.block_number);
+    response.prove_block = with_calldata.then_some(prove_block).flatten();
+    response.traces = traces;
+    Ok(response)
 }
 
 #[utoipa::path(post, path = "/proof",
@@ -195,8 +352,508 @@ async fn proof_handler(
     })
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+/// The block to get the preflight report for.
+struct ReportRequest {
+    /// The network of the block.
+    network: String,
+    /// The block number.
+    block_number: u64,
+}
+
+#[utoipa::path(post, path = "/proof/report",
+    tag = "Proving",
+    request_body = ReportRequest,
+    responses (
+        (status = 200, description = "Successfully loaded the preflight report", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Get the preflight report of a block.
+///
+/// Returns the report of the last input generation for the block, describing which RPC
+/// endpoints, block hashes and blob source were used to build the input. Reports are only
+/// stored when the host runs with a cache path.
+async fn report_handler(
+    State(ProverState { opts, .. }): State<ProverState>,
+    Json(req): Json<ReportRequest>,
+) -> HostResult<Json<Value>> {
+    let Some(dir) = opts.cache_path.as_ref() else {
+        return Err(HostError::InvalidRequestConfig(
+            "Preflight reports are only stored with a cache path".to_owned(),
+        ));
+    };
+    let path = get_report_path(dir, req.block_number, &req.network);
+    let report: PreflightReport = serde_json::from_reader(File::open(path)?)?;
+    Ok(Json(serde_json::json!({ "status": "ok", "data": report })))
+}
+
+#[utoipa::path(post, path = "/proof/estimate",
+    tag = "Proving",
+    request_body = ProofRequestOpt,
+    responses (
+        (status = 200, description = "Successfully estimated the proving resources", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Estimate the resources needed to prove a block.
+///
+/// Executes the block in every zkVM enabled on this host without proving it, and returns
+/// per zkVM the total cycles, the number of segments, the precompile calls, the peak guest
+/// memory and the expected proving time. The proof type of the request is ignored.
+async fn estimate_handler(
+    State(ProverState {
+        opts, chain_specs, ..
+    }): State<ProverState>,
+    Json(req): Json<Value>,
+) -> HostResult<Json<Value>> {
+    let mut config = opts.proof_request_opt.clone();
+    config.merge(&req)?;
+    let proof_request = ProofRequest::try_from(config)?;
+
+    info!(
+        "# Estimating proving resources for block {} on {}",
+        proof_request.block_number, proof_request.network
+    );
+
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.l1_network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
+    let taiko_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
+
+    let blob_sources = opts.blob_sources(&l1_chain_spec);
+    let raiko = Raiko::new(
+        l1_chain_spec,
+        taiko_chain_spec.clone(),
+        proof_request.clone(),
+    )
+    .with_blob_sources(blob_sources);
+    let input = get_input(&opts, &raiko, &proof_request, &taiko_chain_spec).await?;
+
+    // The native block building runs the same code as the guests, so its peak memory is
+    // used for the zkVMs that don't measure the guest memory themselves.
+    memory::reset_stats();
+    raiko.get_output(&input)?;
+    let native_peak_memory = memory::get_max_allocated() as u64;
+
+    let mut estimates = raiko.estimate(input.clone()).await?;
+    for estimate in estimates.values_mut() {
+        estimate.peak_memory.get_or_insert(native_peak_memory);
+    }
+
+    set_cached_input(
+        &opts.cache_path,
+        proof_request.block_number,
+        &proof_request.network.to_string(),
+        &input,
+    )?;
+
+    Ok(Json(
+        serde_json::json!({ "status": "ok", "data": estimates }),
+    ))
+}
+
+#[utoipa::path(post, path = "/proof/execution",
+    tag = "Proving",
+    request_body = ProofRequestOpt,
+    responses (
+        (status = 200, description = "Successfully executed the block", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Execute a block without proving it.
+///
+/// Returns the transactions included in the block with their receipts and gas used, the
+/// transactions of the tx list that were skipped with the reason, and the accounts changed
+/// by the block. The proof type of the request is ignored.
+async fn execution_handler(
+    State(ProverState {
+        opts, chain_specs, ..
+    }): State<ProverState>,
+    Json(req): Json<Value>,
+) -> HostResult<Json<Value>> {
+    let mut config = opts.proof_request_opt.clone();
+    config.merge(&req)?;
+    let proof_request = ProofRequest::try_from(config)?;
+
+    info!(
+        "# Executing block {} on {}",
+        proof_request.block_number, proof_request.network
+    );
+
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.l1_network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
+    let taiko_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
+
+    let blob_sources = opts.blob_sources(&l1_chain_spec);
+    let raiko = Raiko::new(
+        l1_chain_spec,
+        taiko_chain_spec.clone(),
+        proof_request.clone(),
+    )
+    .with_blob_sources(blob_sources);
+    let input = get_input(&opts, &raiko, &proof_request, &taiko_chain_spec).await?;
+    let execution = raiko.get_execution(&input)?;
+
+    set_cached_input(
+        &opts.cache_path,
+        proof_request.block_number,
+        &proof_request.network.to_string(),
+        &input,
+    )?;
+
+    Ok(Json(
+        serde_json::json!({ "status": "ok", "data": execution }),
+    ))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+/// The tier to contest a block at, in addition to the proof request.
+struct ContestRequest {
+    /// The tier ID of the proof, as defined in the Taiko protocol.
+    tier: u16,
+}
+
+#[utoipa::path(post, path = "/proof/contest",
+    tag = "Proving",
+    request_body = ProofRequestOpt,
+    responses (
+        (status = 200, description = "Successfully created the contest proof", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Contest or re-prove a block that is already proven on L1.
+///
+/// Reads the transition currently proven for the block from the TaikoL1 contract, checks if
+/// our re-execution of the block agrees with it and proves our transition with the requested
+/// proof type at the requested `tier`. Returns the ready-to-submit
+/// `proveBlock(blockId, input)` calldata together with the proof.
+async fn contest_handler(
+    State(ProverState {
+        opts, chain_specs, ..
+    }): State<ProverState>,
+    Json(req): Json<Value>,
+) -> HostResult<Json<Value>> {
+    let ContestRequest { tier } = ContestRequest::deserialize(&req)?;
+    let mut config = opts.proof_request_opt.clone();
+    config.merge(&req)?;
+    let proof_request = ProofRequest::try_from(config)?;
+
+    info!(
+        "# Contesting block {} on {} at tier {tier}",
+        proof_request.block_number, proof_request.network
+    );
+
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.l1_network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
+    let taiko_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
+
+    let blob_sources = opts.blob_sources(&l1_chain_spec);
+    let raiko = Raiko::new(
+        l1_chain_spec,
+        taiko_chain_spec.clone(),
+        proof_request.clone(),
+    )
+    .with_blob_sources(blob_sources);
+    let input = get_input(&opts, &raiko, &proof_request, &taiko_chain_spec).await?;
+    let output = raiko.get_output(&input)?;
+    let response = raiko.contest(input.clone(), &output, tier).await?;
+
+    set_cached_input(
+        &opts.cache_path,
+        proof_request.block_number,
+        &proof_request.network.to_string(),
+        &input,
+    )?;
+
+    Ok(Json(
+        serde_json::json!({ "status": "ok", "data": response }),
+    ))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+/// The size of the batch, in addition to the proof request.
+struct BatchRequest {
+    /// The number of consecutive blocks to prove together, starting at `block_number`.
+    num_blocks: u64,
+}
+
+#[utoipa::path(post, path = "/proof/batch",
+    tag = "Proving",
+    request_body = ProofRequestOpt,
+    responses (
+        (status = 200, description = "Successfully created the batch proof", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Prove a batch of consecutive blocks in a single proof.
+///
+/// Takes a proof request with an additional `num_blocks` and proves the blocks
+/// `block_number..block_number + num_blocks` together. The blocks are executed on top of each
+/// other from a single state witness and the proof commits to the hash chain of the instance
+/// hashes of all blocks. Returns the output with the headers of all blocks and the proof.
+async fn batch_handler(
+    State(ProverState {
+        opts, chain_specs, ..
+    }): State<ProverState>,
+    Json(req): Json<Value>,
+) -> HostResult<Json<Value>> {
+    let BatchRequest { num_blocks } = BatchRequest::deserialize(&req)?;
+    let mut config = opts.proof_request_opt.clone();
+    config.merge(&req)?;
+    let proof_request = ProofRequest::try_from(config)?;
+
+    info!(
+        "# Generating batch proof for {num_blocks} blocks from block {} on {}",
+        proof_request.block_number, proof_request.network
+    );
+
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.l1_network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
+    let taiko_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
+
+    let blob_sources = opts.blob_sources(&l1_chain_spec);
+    let raiko = Raiko::new(
+        l1_chain_spec,
+        taiko_chain_spec.clone(),
+        proof_request.clone(),
+    )
+    .with_blob_sources(blob_sources);
+    let measurement = Measurement::start("Generating batch input...", false);
+    let provider =
+        RpcBlockDataProvider::new(&taiko_chain_spec.rpc, proof_request.block_number - 1)?;
+    let (batch, _report) = raiko.generate_batch_input(provider, num_blocks).await?;
+    measurement.stop_with("=> Batch input generated");
+
+    let output = raiko.get_batch_output(&batch)?;
+    let measurement = Measurement::start("Generating batch proof...", false);
+    let proof = raiko.prove_batch(batch, &output).await?;
+    measurement.stop_with("=> Batch proof generated");
+
+    Ok(Json(serde_json::json!({
+        "status": "ok",
+        "data": { "output": output, "proof": proof },
+    })))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+/// The blocks to aggregate, in addition to the proof request.
+struct AggregateRequest {
+    /// The blocks to prove and aggregate, in order. The block number of the request is ignored.
+    block_numbers: Vec<u64>,
+}
+
+#[utoipa::path(post, path = "/proof/aggregate",
+    tag = "Proving",
+    request_body = ProofRequestOpt,
+    responses (
+        (status = 200, description = "Successfully created the aggregated proof", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Prove multiple blocks and aggregate their proofs into a single proof.
+///
+/// Takes a proof request with additional `block_numbers` and proves every block with the
+/// requested proof type, reusing cached proofs, before aggregating the proofs. The aggregated
+/// proof commits to the hash chain of the instance hashes of the blocks. Only supported by
+/// RISC0.
+async fn aggregate_handler(
+    State(ProverState {
+        opts, chain_specs, ..
+    }): State<ProverState>,
+    Json(req): Json<Value>,
+) -> HostResult<Json<Value>> {
+    let AggregateRequest { block_numbers } = AggregateRequest::deserialize(&req)?;
+    let mut config = opts.proof_request_opt.clone();
+    config.merge(&req)?;
+
+    let mut proofs = Vec::with_capacity(block_numbers.len());
+    let mut raiko = None;
+    for block_number in block_numbers {
+        config.block_number = Some(block_number);
+        let proof_request = ProofRequest::try_from(config.clone())?;
+
+        info!(
+            "# Generating proof for block {block_number} on {} to aggregate",
+            proof_request.network
+        );
+
+        let l1_chain_spec = chain_specs
+            .get_chain_spec(&proof_request.l1_network.to_string())
+            .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
+        let taiko_chain_spec = chain_specs
+            .get_chain_spec(&proof_request.network.to_string())
+            .ok_or_else(|| {
+                HostError::InvalidRequestConfig("Unsupported raiko network".to_string())
+            })?;
+
+        let blob_sources = opts.blob_sources(&l1_chain_spec);
+        let block_raiko = Raiko::new(
+            l1_chain_spec,
+            taiko_chain_spec.clone(),
+            proof_request.clone(),
+        )
+        .with_blob_sources(blob_sources);
+        let input = get_input(&opts, &block_raiko, &proof_request, &taiko_chain_spec).await?;
+        let output = block_raiko.get_output(&input)?;
+        let proof = block_raiko.prove(input.clone(), &output).await?;
+
+        set_cached_input(
+            &opts.cache_path,
+            proof_request.block_number,
+            &proof_request.network.to_string(),
+            &input,
+        )?;
+
+        proofs.push((output, proof));
+        raiko = Some(block_raiko);
+    }
+
+    let Some(raiko) = raiko else {
+        return Err(HostError::InvalidRequestConfig(
+            "No blocks to aggregate".to_owned(),
+        ));
+    };
+    let measurement = Measurement::start("Aggregating proofs...", false);
+    let proof = raiko.aggregate(proofs).await?;
+    measurement.stop_with("=> Proofs aggregated");
+
+    Ok(Json(serde_json::json!({ "status": "ok", "data": proof })))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+/// The proof to verify, in addition to the proof request.
+struct VerifyRequest {
+    /// The proof as returned by the prover of the proof type.
+    #[schema(value_type = Value)]
+    proof: Value,
+}
+
+#[utoipa::path(post, path = "/proof/verify",
+    tag = "Proving",
+    request_body = ProofRequestOpt,
+    responses (
+        (status = 200, description = "Successfully verified the proof", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Verify a proof of a block.
+///
+/// Checks the `proof` as returned by `/proof` against the block of the proof request, with the
+/// verifier of the requested proof type: the instance signature for SGX, the receipt and
+/// journal for RISC0 and the proof and public values for SP1. Returns an error if the proof
+/// is invalid.
+async fn verify_handler(
+    State(ProverState {
+        opts, chain_specs, ..
+    }): State<ProverState>,
+    Json(req): Json<Value>,
+) -> HostResult<Json<Value>> {
+    let VerifyRequest { proof } = VerifyRequest::deserialize(&req)?;
+    let mut config = opts.proof_request_opt.clone();
+    config.merge(&req)?;
+    let proof_request = ProofRequest::try_from(config)?;
+
+    info!(
+        "# Verifying {} proof for block {} on {}",
+        proof_request.proof_type, proof_request.block_number, proof_request.network
+    );
+
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.l1_network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_string()))?;
+    let taiko_chain_spec = chain_specs
+        .get_chain_spec(&proof_request.network.to_string())
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
+
+    let blob_sources = opts.blob_sources(&l1_chain_spec);
+    let raiko = Raiko::new(
+        l1_chain_spec,
+        taiko_chain_spec.clone(),
+        proof_request.clone(),
+    )
+    .with_blob_sources(blob_sources);
+    let input = get_input(&opts, &raiko, &proof_request, &taiko_chain_spec).await?;
+    let output = raiko.get_output(&input)?;
+    raiko.verify(input.clone(), &output, &proof).await?;
+
+    set_cached_input(
+        &opts.cache_path,
+        proof_request.block_number,
+        &proof_request.network.to_string(),
+        &input,
+    )?;
+
+    Ok(Json(
+        serde_json::json!({ "status": "ok", "data": { "verified": true } }),
+    ))
+}
+
+#[utoipa::path(post, path = "/proof/submission",
+    tag = "Proving",
+    request_body = ReportRequest,
+    responses (
+        (status = 200, description = "Successfully loaded the submission status", body = Status)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Get the status of the submission of the proof of a block to L1.
+///
+/// Only available when the host runs with `--submit-proofs`. The status is `pending` until
+/// the transaction is sent, then `submitted` until it is confirmed. Proofs of blocks that are
+/// already proven or of transitions the contract rejects are not sent.
+async fn submission_handler(
+    State(ProverState { submitter, .. }): State<ProverState>,
+    Json(req): Json<ReportRequest>,
+) -> HostResult<Json<Value>> {
+    let Some(submitter) = submitter else {
+        return Err(HostError::InvalidRequestConfig(
+            "Proof submission is not enabled".to_owned(),
+        ));
+    };
+    let status = submitter
+        .status(&req.network, req.block_number)
+        .ok_or_else(|| {
+            HostError::InvalidRequestConfig(format!(
+                "No proof submitted for block {} on {}",
+                req.block_number, req.network
+            ))
+        })?;
+    Ok(Json(serde_json::json!({ "status": "ok", "data": status })))
+}
+
 #[derive(OpenApi)]
-#[openapi(paths(proof_handler))]
+#[openapi(
+    paths(
+        proof_handler,
+        report_handler,
+        estimate_handler,
+        execution_handler,
+        contest_handler,
+        batch_handler,
+        aggregate_handler,
+        verify_handler,
+        submission_handler
+    ),
+    components(schemas(
+        ReportRequest,
+        ContestRequest,
+        BatchRequest,
+        AggregateRequest,
+        VerifyRequest
+    ))
+)]
 struct Docs;
 
 pub fn create_docs() -> utoipa::openapi::OpenApi {
@@ -204,5 +861,14 @@ pub fn create_docs() -> utoipa::openapi::OpenApi {
 }
 
 pub fn create_router() -> Router<ProverState> {
-    Router::new().route("/", post(proof_handler))
+    Router::new()
+        .route("/", post(proof_handler))
+        .route("/report", post(report_handler))
+        .route("/estimate", post(estimate_handler))
+        .route("/execution", post(execution_handler))
+        .route("/contest", post(contest_handler))
+        .route("/batch", post(batch_handler))
+        .route("/aggregate", post(aggregate_handler))
+        .route("/verify", post(verify_handler))
+        .route("/submission", post(submission_handler))
 }
@@ -0,0 +1,633 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy_primitives::{hex, keccak256, Address, Bytes, B256};
+use alloy_provider::{network::EthereumSigner, Provider, ProviderBuilder, RootProvider};
+use alloy_rpc_client::RpcClient;
+use alloy_rpc_types::TransactionReceipt;
+use alloy_signer::Signer;
+use alloy_signer_wallet::LocalWallet;
+use alloy_sol_types::sol;
+use alloy_transport_http::Http;
+use raiko_core::calldata::ProveBlockCall;
+use raiko_primitives::alloy_eips::{BlockId, BlockNumberOrTag};
+use reqwest_alloy::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::interfaces::{HostError, HostResult};
+
+sol! {
+    #[sol(rpc)]
+    #[allow(dead_code)]
+    contract TaikoL1 {
+        function proveBlock(uint64 blockId, bytes calldata input) external;
+    }
+}
+
+/// The environment variable holding the private key of the submitter.
+const SENDER_PRIV_KEY: &str = "SENDER_PRIV_KEY";
+
+/// The gas limit of a `proveBlock` transaction.
+const PROVE_BLOCK_GAS_LIMIT: u128 = 3_000_000;
+
+/// The number of blocks a `proveBlock` transaction needs to be confirmed by.
+const REQUIRED_CONFIRMATIONS: u64 = 2;
+
+/// How long to wait for a `proveBlock` transaction to be confirmed.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// How often a transaction that is not confirmed in time is replaced at the same nonce.
+const MAX_REPLACEMENTS: u32 = 3;
+
+/// The gas price of a replacement transaction in percent of the one it replaces. Nodes only
+/// accept replacements that pay at least 10% more.
+const REPLACEMENT_GAS_PRICE_PERCENT: u128 = 125;
+
+/// How long to wait before sending a proof again that was delayed by a high gas price. The
+/// delay doubles with every attempt, up to [MAX_RETRY_DELAY].
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// The maximum delay between two attempts to send a proof.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// TaikoL1 errors for blocks that already have a proof we can't improve on.
+const ALREADY_PROVEN_ERRORS: [&str; 2] = ["L1_ALREADY_PROVED()", "L1_ALREADY_CONTESTED()"];
+
+/// TaikoL1 errors for proofs of a transition the contract doesn't accept.
+const INVALID_TRANSITION_ERRORS: [&str; 3] = [
+    "L1_INVALID_TRANSITION()",
+    "L1_BLOCK_MISMATCH()",
+    "L1_INVALID_TIER()",
+];
+
+/// The state of the submission of a proof to L1.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    /// Waiting for the submitter to pick up the proof.
+    Pending,
+    /// The gas price is above the maximum, the proof is sent again after a delay.
+    Delayed { reason: String, attempts: u32 },
+    /// The transaction was sent and waits for confirmation.
+    Submitted { tx_hash: B256, nonce: u64 },
+    /// The transaction was confirmed.
+    Confirmed {
+        tx_hash: B256,
+        block_number: Option<u64>,
+    },
+    /// The block was already proven, so the proof was not sent.
+    AlreadyProven { reason: String },
+    /// The contract rejects the transition of the proof.
+    InvalidTransition { reason: String },
+    /// The transaction reverted or could not be sent.
+    Failed { reason: String },
+}
+
+/// How the gas price of submitted transactions is chosen.
+#[derive(Clone, Copy, Debug)]
+pub struct GasPriceStrategy {
+    /// The gas price in percent of the current network gas price.
+    pub percent: u64,
+    /// The maximum gas price in wei, proofs are not sent while the gas price is above it.
+    pub max_gas_price: Option<u128>,
+}
+
+impl GasPriceStrategy {
+    /// The gas price to use given the current network gas price, or an error if it exceeds
+    /// the maximum.
+    pub fn gas_price(&self, network_gas_price: u128) -> Result<u128, String> {
+        let gas_price = network_gas_price.saturating_mul(self.percent as u128) / 100;
+        match self.max_gas_price {
+            Some(max_gas_price) if gas_price > max_gas_price => Err(format!(
+                "Gas price {gas_price} is above the maximum of {max_gas_price}"
+            )),
+            _ => Ok(gas_price),
+        }
+    }
+
+    /// The gas price to replace a transaction sent with `gas_price`, or an error if it
+    /// exceeds the maximum.
+    pub fn bump(&self, gas_price: u128) -> Result<u128, String> {
+        let bumped = gas_price.saturating_mul(REPLACEMENT_GAS_PRICE_PERCENT) / 100;
+        match self.max_gas_price {
+            Some(max_gas_price) if bumped > max_gas_price => Err(format!(
+                "Replacement gas price {bumped} is above the maximum of {max_gas_price}"
+            )),
+            _ => Ok(bumped),
+        }
+    }
+}
+
+/// A proof to submit to the L1 contract of a network.
+#[derive(Clone, Debug)]
+pub struct SubmitTask {
+    pub network: String,
+    pub block_number: u64,
+    pub l1_rpc: String,
+    pub l1_chain_id: u64,
+    pub l1_contract: Address,
+    pub prove_block: ProveBlockCall,
+}
+
+type SubmissionKey = (String, u64);
+
+/// Sends `proveBlock` transactions for completed proofs and records the result per block.
+///
+/// With a cache path the results are also stored next to the cached inputs of the blocks, so
+/// they are still available after a restart.
+#[derive(Clone, Debug)]
+pub struct ProofSubmitter {
+    tasks: UnboundedSender<SubmitTask>,
+    receiver: Arc<Mutex<Option<UnboundedReceiver<SubmitTask>>>>,
+    results: Arc<Mutex<HashMap<SubmissionKey, SubmissionStatus>>>,
+    cache_path: Option<PathBuf>,
+    wallet: LocalWallet,
+    gas_price: GasPriceStrategy,
+}
+
+impl ProofSubmitter {
+    /// Creates a submitter signing with the private key in `SENDER_PRIV_KEY`.
+    pub fn new(gas_price: GasPriceStrategy, cache_path: Option<PathBuf>) -> HostResult<Self> {
+        let key = std::env::var(SENDER_PRIV_KEY).map_err(|_| {
+            HostError::InvalidRequestConfig(format!(
+                "{SENDER_PRIV_KEY} is required to submit proofs"
+            ))
+        })?;
+        let wallet = key.parse().map_err(|e| {
+            HostError::InvalidRequestConfig(format!("Invalid {SENDER_PRIV_KEY}: {e}"))
+        })?;
+        Ok(Self::with_wallet(wallet, gas_price, cache_path))
+    }
+
+    pub fn with_wallet(
+        wallet: LocalWallet,
+        gas_price: GasPriceStrategy,
+        cache_path: Option<PathBuf>,
+    ) -> Self {
+        let (tasks, receiver) = unbounded_channel();
+        Self {
+            tasks,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+            results: Default::default(),
+            cache_path,
+            wallet,
+            gas_price,
+        }
+    }
+
+    /// Queues a proof for submission.
+    pub fn submit(&self, task: SubmitTask) -> HostResult<()> {
+        self.record(&task, SubmissionStatus::Pending);
+        self.tasks
+            .send(task)
+            .map_err(|_| HostError::Anyhow(anyhow::anyhow!("The proof submitter is not running")))
+    }
+
+    /// The status of the submission of the proof of a block.
+    pub fn status(&self, network: &str, block_number: u64) -> Option<SubmissionStatus> {
+        let status = self
+            .results
+            .lock()
+            .unwrap()
+            .get(&(network.to_owned(), block_number))
+            .cloned();
+        status.or_else(|| {
+            let path = get_submission_path(self.cache_path.as_ref()?, block_number, network);
+            serde_json::from_slice(&fs::read(path).ok()?).ok()
+        })
+    }
+
+    /// Submits the queued proofs one by one, forever.
+    pub async fn run(self) {
+        let Some(mut receiver) = self.receiver.lock().unwrap().take() else {
+            warn!("The proof submitter is already running");
+            return;
+        };
+        // The next nonce per L1 chain, synced from the node when unknown
+        let mut nonces = HashMap::new();
+        // The number of times the proof of a block was delayed
+        let mut attempts: HashMap<SubmissionKey, u32> = HashMap::new();
+        while let Some(task) = receiver.recv().await {
+            let nonce = nonces.remove(&task.l1_chain_id);
+            let (mut status, next_nonce) = self.submit_task(&task, nonce).await;
+            if let Some(next_nonce) = next_nonce {
+                nonces.insert(task.l1_chain_id, next_nonce);
+            }
+            let key = (task.network.clone(), task.block_number);
+            if let SubmissionStatus::Delayed {
+                attempts: task_attempts,
+                ..
+            } = &mut status
+            {
+                let count = attempts.entry(key).or_default();
+                *count += 1;
+                *task_attempts = *count;
+                self.requeue(task.clone(), retry_delay(*count));
+            } else {
+                attempts.remove(&key);
+            }
+            info!(
+                "Submission of block {} on {}: {status:?}",
+                task.block_number, task.network
+            );
+            self.record(&task, status);
+        }
+    }
+
+    /// Queues the task again after `delay`.
+    fn requeue(&self, task: SubmitTask, delay: Duration) {
+        let tasks = self.tasks.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            // The submitter only stops when the host shuts down
+            let _ = tasks.send(task);
+        });
+    }
+
+    fn record(&self, task: &SubmitTask, status: SubmissionStatus) {
+        if let Some(dir) = &self.cache_path {
+            let path = get_submission_path(dir, task.block_number, &task.network);
+            let written = serde_json::to_vec_pretty(&status)
+                .map_err(Into::into)
+                .and_then(|json| fs::write(&path, json).map_err(HostError::from));
+            if let Err(e) = written {
+                warn!("Failed to store the submission result in {path:?}: {e}");
+            }
+        }
+        self.results
+            .lock()
+            .unwrap()
+            .insert((task.network.clone(), task.block_number), status);
+    }
+
+    /// Sends the `proveBlock` transaction of the task and waits for it to be confirmed.
+    /// Returns the result and the next nonce to use, or `None` if it has to be synced again.
+    async fn submit_task(
+        &self,
+        task: &SubmitTask,
+        nonce: Option<u64>,
+    ) -> (SubmissionStatus, Option<u64>) {
+        let failed = |reason: String| SubmissionStatus::Failed { reason };
+        let Ok(url) = Url::parse(&task.l1_rpc) else {
+            return (failed("Invalid L1 RPC URL".to_owned()), nonce);
+        };
+        let mut wallet = self.wallet.clone();
+        wallet.set_chain_id(Some(task.l1_chain_id));
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .signer(EthereumSigner::from(wallet))
+            .on_provider(RootProvider::new(RpcClient::new(Http::new(url), false)));
+        let address = self.wallet.address();
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => {
+                let tag = BlockId::Number(BlockNumberOrTag::Pending);
+                match provider.get_transaction_count(address, tag).await {
+                    Ok(nonce) => nonce,
+                    Err(e) => return (failed(format!("Failed to get the nonce: {e}")), None),
+                }
+            }
+        };
+        let mut gas_price = match provider.get_gas_price().await {
+            Ok(network_gas_price) => match self.gas_price.gas_price(network_gas_price) {
+                Ok(gas_price) => gas_price,
+                Err(reason) => {
+                    let status = SubmissionStatus::Delayed {
+                        reason,
+                        attempts: 0,
+                    };
+                    return (status, Some(nonce));
+                }
+            },
+            Err(e) => {
+                return (
+                    failed(format!("Failed to get the gas price: {e}")),
+                    Some(nonce),
+                )
+            }
+        };
+
+        let contract = TaikoL1::new(task.l1_contract, &provider);
+        let call_builder = |gas_price| {
+            contract
+                .proveBlock(task.prove_block.block_id, task.prove_block.input.clone())
+                .from(address)
+                .nonce(nonce)
+                .gas_price(gas_price)
+                .gas(PROVE_BLOCK_GAS_LIMIT)
+        };
+
+        // Simulate the call first, reverts don't cost any gas then
+        if let Err(e) = call_builder(gas_price).call().await {
+            return (classify_revert(&e.to_string()), Some(nonce));
+        }
+
+        // Transactions that are not confirmed in time are replaced at the same nonce with a
+        // higher gas price, so a stuck transaction doesn't block all later proofs
+        let mut sent = Vec::new();
+        loop {
+            let pending = match call_builder(gas_price).send().await {
+                Ok(pending) => pending,
+                Err(e) => {
+                    // A replaced transaction may have been confirmed in the meantime
+                    if let Some(status) = confirmed(&provider, &sent).await {
+                        return (status, Some(nonce + 1));
+                    }
+                    // The nonce may be out of sync with the node, e.g. after a manual
+                    // transaction
+                    return (failed(format!("Failed to send the transaction: {e}")), None);
+                }
+            };
+            let tx_hash = *pending.tx_hash();
+            sent.push(tx_hash);
+            self.record(task, SubmissionStatus::Submitted { tx_hash, nonce });
+            info!(
+                "Submitted the proof of block {} on {} in {tx_hash} with gas price {gas_price}",
+                task.block_number, task.network
+            );
+
+            let receipt = pending
+                .with_required_confirmations(REQUIRED_CONFIRMATIONS)
+                .with_timeout(Some(CONFIRMATION_TIMEOUT))
+                .get_receipt()
+                .await;
+            let error = match receipt {
+                Ok(receipt) => return (receipt_status(tx_hash, &receipt), Some(nonce + 1)),
+                Err(e) => e,
+            };
+            if let Some(status) = confirmed(&provider, &sent).await {
+                return (status, Some(nonce + 1));
+            }
+            let reason = format!("Transaction {tx_hash} was not confirmed: {error}");
+            if sent.len() as u32 > MAX_REPLACEMENTS {
+                // Whether the nonce is used up is only known to the node now
+                return (failed(reason), None);
+            }
+            gas_price = match self.gas_price.bump(gas_price) {
+                Ok(gas_price) => gas_price,
+                Err(e) => return (failed(format!("{reason}, not replaced: {e}")), None),
+            };
+            warn!("{reason}, replacing it");
+        }
+    }
+}
+
+/// The status of the first of the sent transactions that was included in a block, if any.
+async fn confirmed<P: Provider<Http<Client>>>(
+    provider: &P,
+    sent: &[B256],
+) -> Option<SubmissionStatus> {
+    for tx_hash in sent {
+        if let Ok(Some(receipt)) = provider.get_transaction_receipt(*tx_hash).await {
+            return Some(receipt_status(*tx_hash, &receipt));
+        }
+    }
+    None
+}
+
+/// The submission status of a transaction with the given receipt.
+fn receipt_status(tx_hash: B256, receipt: &TransactionReceipt) -> SubmissionStatus {
+    if receipt.status() {
+        SubmissionStatus::Confirmed {
+            tx_hash,
+            block_number: receipt.block_number,
+        }
+    } else {
+        SubmissionStatus::Failed {
+            reason: format!("Transaction {tx_hash} reverted"),
+        }
+    }
+}
+
+fn get_submission_path(dir: &Path, block_number: u64, network: &str) -> PathBuf {
+    dir.join(format!("submission-{network}-{block_number}.json"))
+}
+
+/// The delay before the given attempt to send a delayed proof again.
+fn retry_delay(attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    RETRY_DELAY.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+/// Maps the error of a reverted `proveBlock` simulation to the submission status.
+fn classify_revert(error: &str) -> SubmissionStatus {
+    // Nodes report custom errors by their selector, some also by their name
+    let matches = |errors: &[&str]| {
+        errors.iter().any(|signature| {
+            let selector = hex::encode(&keccak256(signature)[..4]);
+            let name = signature.trim_end_matches("()");
+            error.contains(&selector) || error.contains(name)
+        })
+    };
+    let reason = error.to_owned();
+    if matches(&ALREADY_PROVEN_ERRORS) {
+        SubmissionStatus::AlreadyProven { reason }
+    } else if matches(&INVALID_TRANSITION_ERRORS) {
+        SubmissionStatus::InvalidTransition { reason }
+    } else {
+        SubmissionStatus::Failed { reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn selector(signature: &str) -> String {
+        format!("0x{}", hex::encode(&keccak256(signature)[..4]))
+    }
+
+    /// Starts a minimal JSON-RPC node that reverts every call with `revert_data`.
+    async fn start_node(revert_data: String) -> String {
+        let handler = move |Json(req): Json<Value>| {
+            let revert_data = revert_data.clone();
+            async move {
+                let id = req["id"].clone();
+                let result = match req["method"].as_str().unwrap_or_default() {
+                    "eth_chainId" => json!("0x1"),
+                    "eth_getTransactionCount" => json!("0x7"),
+                    "eth_gasPrice" => json!("0x3b9aca00"),
+                    "eth_call" => {
+                        return Json(json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": {
+                                "code": 3,
+                                "message": format!("execution reverted: {revert_data}"),
+                                "data": revert_data,
+                            },
+                        }))
+                    }
+                    method => panic!("Unexpected call to {method}"),
+                };
+                Json(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+            }
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/", post(handler)))
+                .await
+                .unwrap()
+        });
+        format!("http://{addr}")
+    }
+
+    fn task(l1_rpc: String) -> SubmitTask {
+        SubmitTask {
+            network: "taiko_a7".to_owned(),
+            block_number: 10,
+            l1_rpc,
+            l1_chain_id: 1,
+            l1_contract: Address::with_last_byte(1),
+            prove_block: ProveBlockCall {
+                block_id: 10,
+                tier: 200,
+                input: Bytes::from(vec![1u8; 32]),
+                calldata: Bytes::default(),
+            },
+        }
+    }
+
+    fn submitter() -> ProofSubmitter {
+        let wallet = LocalWallet::random();
+        ProofSubmitter::with_wallet(
+            wallet,
+            GasPriceStrategy {
+                percent: 100,
+                max_gas_price: None,
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn test_gas_price_strategy() {
+        let strategy = GasPriceStrategy {
+            percent: 120,
+            max_gas_price: Some(1_000),
+        };
+        assert_eq!(strategy.gas_price(500), Ok(600));
+        assert!(strategy.gas_price(900).is_err());
+        assert_eq!(strategy.bump(600), Ok(750));
+        assert!(strategy.bump(900).is_err());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), RETRY_DELAY);
+        assert_eq!(retry_delay(2), RETRY_DELAY * 2);
+        assert_eq!(retry_delay(3), RETRY_DELAY * 4);
+        assert_eq!(retry_delay(100), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_delay_on_high_gas_price() {
+        // The node's gas price of 1 gwei is above the maximum
+        let submitter = ProofSubmitter::with_wallet(
+            LocalWallet::random(),
+            GasPriceStrategy {
+                percent: 100,
+                max_gas_price: Some(1_000),
+            },
+            None,
+        );
+        let node = start_node(selector("L1_ALREADY_PROVED()")).await;
+        let (status, nonce) = submitter.submit_task(&task(node), Some(3)).await;
+        assert!(matches!(status, SubmissionStatus::Delayed { .. }));
+        assert_eq!(nonce, Some(3));
+    }
+
+    #[test]
+    fn test_classify_revert() {
+        let already_proven = selector("L1_ALREADY_PROVED()");
+        assert!(matches!(
+            classify_revert(&format!("execution reverted, data: \"{already_proven}\"")),
+            SubmissionStatus::AlreadyProven { .. }
+        ));
+        assert!(matches!(
+            classify_revert("execution reverted: L1_INVALID_TRANSITION"),
+            SubmissionStatus::InvalidTransition { .. }
+        ));
+        assert!(matches!(
+            classify_revert("insufficient funds"),
+            SubmissionStatus::Failed { .. }
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_submit_reverted_proofs() {
+        let submitter = submitter();
+
+        let node = start_node(selector("L1_ALREADY_PROVED()")).await;
+        let (status, nonce) = submitter.submit_task(&task(node), None).await;
+        assert!(matches!(status, SubmissionStatus::AlreadyProven { .. }));
+        // The nonce is synced from the node and not used up by the reverted proof
+        assert_eq!(nonce, Some(7));
+
+        let node = start_node(selector("L1_BLOCK_MISMATCH()")).await;
+        let (status, nonce) = submitter.submit_task(&task(node), Some(8)).await;
+        assert!(matches!(status, SubmissionStatus::InvalidTransition { .. }));
+        assert_eq!(nonce, Some(8));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_submission_results() {
+        let submitter = submitter();
+        let node = start_node(selector("L1_ALREADY_CONTESTED()")).await;
+        submitter.submit(task(node)).unwrap();
+        assert_eq!(
+            submitter.status("taiko_a7", 10),
+            Some(SubmissionStatus::Pending)
+        );
+        tokio::spawn(submitter.clone().run());
+        for _ in 0..50 {
+            if submitter.status("taiko_a7", 10) != Some(SubmissionStatus::Pending) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(matches!(
+            submitter.status("taiko_a7", 10),
+            Some(SubmissionStatus::AlreadyProven { .. })
+        ));
+        assert_eq!(submitter.status("taiko_a7", 11), None);
+    }
+
+    #[test]
+    fn test_persisted_submission_results() {
+        let dir = std::env::temp_dir().join("raiko_submission_test");
+        fs::create_dir_all(&dir).unwrap();
+        let gas_price = GasPriceStrategy {
+            percent: 100,
+            max_gas_price: None,
+        };
+        let status = SubmissionStatus::Confirmed {
+            tx_hash: B256::with_last_byte(1),
+            block_number: Some(100),
+        };
+        let submitter =
+            ProofSubmitter::with_wallet(LocalWallet::random(), gas_price, Some(dir.clone()));
+        submitter.record(&task(String::new()), status.clone());
+
+        // A submitter started later with the same cache path still knows the result
+        let restarted =
+            ProofSubmitter::with_wallet(LocalWallet::random(), gas_price, Some(dir.clone()));
+        assert_eq!(restarted.status("taiko_a7", 10), Some(status));
+        assert_eq!(restarted.status("taiko_a7", 11), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -15,7 +15,9 @@
 use core::{fmt::Debug, mem::take, str::from_utf8};
 use std::collections::HashSet;
 
-use alloy_consensus::{constants::BEACON_ROOTS_ADDRESS, TxEnvelope};
+use alloy_consensus::{
+    constants::BEACON_ROOTS_ADDRESS, Header as AlloyConsensusHeader, TxEnvelope,
+};
 use alloy_primitives::{TxKind, U256};
 use anyhow::{anyhow, bail, ensure, Context, Error, Result};
 #[cfg(feature = "std")]
@@ -27,35 +29,38 @@ use raiko_primitives::{
 use revm::{
     interpreter::Host,
     primitives::{
-        Account, Address, EVMError, HandlerCfg, ResultAndState, SpecId, TransactTo, TxEnv,
-        MAX_BLOB_GAS_PER_BLOCK,
+        Account, Address, BlockEnv, EVMError, ExecutionResult, HandlerCfg, ResultAndState, SpecId,
+        TransactTo, TxEnv, MAX_BLOB_GAS_PER_BLOCK,
     },
     taiko, Database, DatabaseCommit, Evm, JournaledState,
 };
 cfg_if::cfg_if! {
     if #[cfg(feature = "tracer")] {
-        use std::{fs::{OpenOptions, File}, io::{BufWriter, Write}, sync::{Arc, Mutex}};
-        use revm::{inspector_handle_register, inspectors::TracerEip3155};
+        use revm::inspector_handle_register;
+        use super::trace::TxTracer;
     }
 }
 
 use super::{OptimisticDatabase, TxExecStrategy};
 use crate::{
-    builder::BlockBuilder,
+    builder::{BlockBuilder, SkipReason, SkippedTx},
     clear_line,
     consts::GWEI_TO_WEI,
-    guest_mem_forget, inplace_print, print_duration,
+    guest_mem_forget, inplace_print,
+    input::GuestInput,
+    print_duration,
     time::{AddAssign, Duration, Instant},
     utils::{check_anchor_tx, generate_transactions},
     Measurement,
 };
 
-/// Minimum supported protocol version: SHANGHAI
-const MIN_SPEC_ID: SpecId = SpecId::SHANGHAI;
+/// Minimum supported protocol version: MERGE (earlier blocks need block and ommer rewards)
+const MIN_SPEC_ID: SpecId = SpecId::MERGE;
 
-pub struct TkoTxExecStrategy {}
+/// Executes the transactions of an Ethereum block, which all have to be valid.
+pub struct EthTxExecStrategy {}
 
-impl TxExecStrategy for TkoTxExecStrategy {
+impl TxExecStrategy for EthTxExecStrategy {
     fn execute_transactions<D>(mut block_builder: BlockBuilder<D>) -> Result<BlockBuilder<D>>
     where
         D: Database + DatabaseCommit + OptimisticDatabase,
@@ -65,142 +70,215 @@ impl TxExecStrategy for TkoTxExecStrategy {
         let mut tx_misc_duration = Duration::default();
 
         let is_optimistic = block_builder.db().unwrap().is_optimistic();
-
+        let spec_id = start_execution(&mut block_builder)?;
         let header = block_builder
             .header
             .as_mut()
             .expect("Header is not initialized");
-        // Compute the spec id
-        let spec_id = block_builder
-            .chain_spec
-            .active_fork(header.number, header.timestamp)
-            .unwrap();
-        if !SpecId::enabled(spec_id, MIN_SPEC_ID) {
-            bail!("Invalid protocol version: expected >= {MIN_SPEC_ID:?}, got {spec_id:?}")
+        let chain_id = block_builder.input.chain_spec.chain_id();
+
+        let mut transactions = block_builder.input.transactions.clone();
+
+        // Setup the EVM environment
+        let evm = Evm::builder().with_db(block_builder.db.take().unwrap());
+        #[cfg(feature = "tracer")]
+        let evm = evm.with_external_context(TxTracer::new(block_builder.trace_config.clone()));
+        let evm = evm
+            .with_handler_cfg(HandlerCfg::new_with_taiko(spec_id, false))
+            .modify_cfg_env(|cfg_env| {
+                // set the EVM configuration
+                cfg_env.chain_id = chain_id;
+            })
+            .modify_block_env(|blk_env| fill_block_env(blk_env, header, &block_builder.input));
+        // Only inspect the execution when tracing was requested
+        #[cfg(feature = "tracer")]
+        let evm = if block_builder.trace_config.is_some() {
+            evm.append_handler_register(inspector_handle_register)
+        } else {
+            evm
+        };
+        let mut evm = evm.build();
+
+        if spec_id >= SpecId::CANCUN {
+            apply_beacon_root_contract_call(&mut evm, header)?;
+        }
+
+        let mut executed = ExecutedTxs::default();
+        let num_transactions = transactions.len();
+        for (tx_no, tx) in take(&mut transactions).into_iter().enumerate() {
+            inplace_print(&format!("\rprocessing tx {tx_no}/{num_transactions}..."));
+
+            #[cfg(feature = "tracer")]
+            evm.context.external.start_tx(executed.tx_count);
+
+            // setup the EVM environment
+            let tx_env = &mut evm.env_mut().tx;
+            fill_eth_tx_env(tx_env, &tx)?;
+
+            // if the sigature was not valid, the caller address will have been set to zero
+            if tx_env.caller == Address::ZERO {
+                bail!("Error at transaction {tx_no}: invalid signature");
+            }
+
+            // verify transaction gas
+            let block_available_gas = block_builder.input.gas_limit - executed.cumulative_gas_used;
+            if block_available_gas < tx_env.gas_limit {
+                if is_optimistic {
+                    continue;
+                }
+                bail!("Error at transaction {tx_no}: gas exceeds block limit");
+            }
+
+            executed.add_blob_gas(tx_no, &tx)?;
+
+            // process the transaction
+            let start = Instant::now();
+            let ResultAndState { result, state } = match evm.transact() {
+                Ok(result) => result,
+                Err(err) => {
+                    // Clear the state for the next tx
+                    evm.context.evm.journaled_state = JournaledState::new(spec_id, HashSet::new());
+
+                    if is_optimistic {
+                        continue;
+                    }
+                    bail!(
+                        "Error at transaction {tx_no}: tx failed to execute successfully: {err:?}"
+                    );
+                }
+            };
+            #[cfg(feature = "std")]
+            debug!("  Ok: {result:?}");
+
+            tx_transact_duration.add_assign(start.elapsed());
+
+            let start = Instant::now();
+
+            // update the state
+            evm.context.evm.db.commit(state);
+
+            #[cfg(feature = "tracer")]
+            {
+                let gas_limit = evm.tx().gas_limit;
+                if let Some(trace) = evm.context.external.finish_tx(
+                    executed.tx_count,
+                    *tx.tx_hash(),
+                    gas_limit,
+                    result.gas_used(),
+                ) {
+                    block_builder.traces.push(trace);
+                }
+            }
+
+            let (receipt, gas_used) = executed.add(&tx, &result)?;
+            block_builder.tx_hashes.push(*tx.tx_hash());
+            block_builder.receipts.push(receipt);
+            block_builder.gas_used.push(gas_used);
+
+            tx_misc_duration.add_assign(start.elapsed());
         }
+        clear_line();
+        print_duration("Tx transact time: ", tx_transact_duration);
+        print_duration("Tx misc time: ", tx_misc_duration);
+
+        finish_execution(
+            &mut evm.context.evm.db,
+            &block_builder.input,
+            header,
+            spec_id,
+            executed,
+        )?;
+
+        // Return block builder with updated database
+        Ok(block_builder.with_db(evm.context.evm.inner.db))
+    }
+}
 
+/// Executes the transactions of a Taiko block: the anchor transaction first, then the
+/// transactions of the tx list, skipping the invalid ones.
+pub struct TkoTxExecStrategy {}
+
+impl TxExecStrategy for TkoTxExecStrategy {
+    fn execute_transactions<D>(mut block_builder: BlockBuilder<D>) -> Result<BlockBuilder<D>>
+    where
+        D: Database + DatabaseCommit + OptimisticDatabase,
+        <D as Database>::Error: Debug,
+    {
+        let mut tx_transact_duration = Duration::default();
+        let mut tx_misc_duration = Duration::default();
+
+        let is_optimistic = block_builder.db().unwrap().is_optimistic();
+        let spec_id = start_execution(&mut block_builder)?;
+        let header = block_builder
+            .header
+            .as_mut()
+            .expect("Header is not initialized");
         let chain_spec = &block_builder.input.chain_spec;
         let chain_id = chain_spec.chain_id();
-        let is_taiko = chain_spec.is_taiko();
-        println!("spec_id: {spec_id:?}");
 
-        // generate the transactions from the tx list
-        // For taiko blocks, insert the anchor tx as the first transaction
-        let anchor_tx = if chain_spec.is_taiko() {
-            Some(serde_json::from_str(&block_builder.input.taiko.anchor_tx.clone()).unwrap())
-        } else {
-            None
-        };
-        let mut transactions = generate_transactions(
+        // generate the transactions from the tx list, with the anchor tx as the first
+        // transaction
+        let anchor_tx = serde_json::from_str(&block_builder.input.taiko.anchor_tx.clone()).unwrap();
+        let (mut transactions, tx_list_error) = generate_transactions(
             chain_spec,
-            block_builder.input.taiko.block_proposed.meta.blobUsed,
+            &block_builder.input.taiko.block_proposed,
             &block_builder.input.taiko.tx_data,
             anchor_tx,
         );
+        if let Some(reason) = tx_list_error {
+            block_builder.skipped.push(SkippedTx {
+                tx_no: None,
+                tx_hash: None,
+                reason,
+            });
+        }
 
         // Setup the EVM environment
         let evm = Evm::builder().with_db(block_builder.db.take().unwrap());
         #[cfg(feature = "tracer")]
-        let evm = evm.with_external_context(TracerEip3155::new(Box::new(std::io::stdout())));
+        let evm = evm.with_external_context(TxTracer::new(block_builder.trace_config.clone()));
         let evm = evm
-            .with_handler_cfg(HandlerCfg::new_with_taiko(spec_id, is_taiko))
+            .with_handler_cfg(HandlerCfg::new_with_taiko(spec_id, true))
             .modify_cfg_env(|cfg_env| {
                 // set the EVM configuration
                 cfg_env.chain_id = chain_id;
             })
-            .modify_block_env(|blk_env| {
-                // set the EVM block environment
-                blk_env.number = U256::from(header.number);
-                blk_env.coinbase = block_builder.input.beneficiary;
-                blk_env.timestamp = header.timestamp.try_into().unwrap();
-                blk_env.difficulty = U256::ZERO;
-                blk_env.prevrandao = Some(header.mix_hash);
-                blk_env.basefee = header.base_fee_per_gas.unwrap().try_into().unwrap();
-                blk_env.gas_limit = block_builder.input.gas_limit.try_into().unwrap();
-                if let Some(excess_blob_gas) = header.excess_blob_gas {
-                    blk_env.set_blob_excess_gas_and_price(excess_blob_gas.try_into().unwrap());
-                }
-            });
-        let evm = if is_taiko {
-            evm.append_handler_register(taiko::handler_register::taiko_handle_register)
+            .modify_block_env(|blk_env| fill_block_env(blk_env, header, &block_builder.input))
+            .append_handler_register(taiko::handler_register::taiko_handle_register);
+        // Only inspect the execution when tracing was requested
+        #[cfg(feature = "tracer")]
+        let evm = if block_builder.trace_config.is_some() {
+            evm.append_handler_register(inspector_handle_register)
         } else {
             evm
         };
-        #[cfg(feature = "tracer")]
-        let evm = evm.append_handler_register(inspector_handle_register);
         let mut evm = evm.build();
 
-        // Set the beacon block root in the EVM
         if spec_id >= SpecId::CANCUN {
-            let parent_beacon_block_root = header.parent_beacon_block_root.unwrap();
-
-            // From EIP-4788 Beacon block root in the EVM (Cancun):
-            // "Call BEACON_ROOTS_ADDRESS as SYSTEM_ADDRESS with the 32-byte input of
-            //  header.parent_beacon_block_root, a gas limit of 30_000_000, and 0 value."
-            evm.env_mut().tx = TxEnv {
-                transact_to: TransactTo::Call(BEACON_ROOTS_ADDRESS),
-                caller: SYSTEM_ADDRESS,
-                data: parent_beacon_block_root.into(),
-                gas_limit: 30_000_000,
-                value: U256::ZERO,
-                ..Default::default()
-            };
-
-            let tmp = evm.env_mut().block.clone();
-
-            // disable block gas limit validation and base fee checks
-            evm.block_mut().gas_limit = U256::from(evm.tx().gas_limit);
-            evm.block_mut().basefee = U256::ZERO;
-
-            let ResultAndState { mut state, .. } =
-                evm.transact().expect("beacon roots contract call failed");
-            evm.env_mut().block = tmp;
-
-            // commit only the changes to the beacon roots contract
-            state.remove(&SYSTEM_ADDRESS);
-            state.remove(&evm.block().coinbase);
-            evm.context.evm.db.commit(state);
+            apply_beacon_root_contract_call(&mut evm, header)?;
         }
 
-        // bloom filter over all transaction logs
-        let mut logs_bloom = Bloom::default();
-        // keep track of the gas used over all transactions
-        let mut cumulative_gas_used = 0u64;
-        let mut blob_gas_used = 0_u64;
-
-        // process all the transactions
-        let mut tx_trie = MptNode::default();
-        let mut receipt_trie = MptNode::default();
-        // track the actual tx number to use in the tx/receipt trees as the key
-        let mut actual_tx_no = 0usize;
+        let mut executed = ExecutedTxs::default();
         let num_transactions = transactions.len();
         for (tx_no, tx) in take(&mut transactions).into_iter().enumerate() {
             inplace_print(&format!("\rprocessing tx {tx_no}/{num_transactions}..."));
 
             #[cfg(feature = "tracer")]
-            let trace = set_trace_writer(
-                &mut evm.context.external,
-                chain_id,
-                block_builder.input.block_number,
-                actual_tx_no,
-            );
+            evm.context.external.start_tx(executed.tx_count);
 
             // anchor transaction always the first transaction
-            let is_anchor = is_taiko && tx_no == 0;
+            let is_anchor = tx_no == 0;
 
             // setup the EVM environment
             let tx_env = &mut evm.env_mut().tx;
             fill_eth_tx_env(tx_env, &tx)?;
-            // Set and check some taiko specific values
-            if chain_spec.is_taiko() {
-                // set if the tx is the anchor tx
-                tx_env.taiko.is_anchor = is_anchor;
-                // set the treasury address
-                tx_env.taiko.treasury = chain_spec.l2_contract.unwrap_or_default();
-
-                // Data blobs are not allowed on L2
-                ensure!(tx_env.blob_hashes.len() == 0);
-            }
+            // set if the tx is the anchor tx
+            tx_env.taiko.is_anchor = is_anchor;
+            // set the treasury address
+            tx_env.taiko.treasury = chain_spec.l2_contract.unwrap_or_default();
+
+            // Data blobs are not allowed on L2
+            ensure!(tx_env.blob_hashes.len() == 0);
 
             // if the sigature was not valid, the caller address will have been set to zero
             if tx_env.caller == Address::ZERO {
@@ -209,10 +287,12 @@ impl TxExecStrategy for TkoTxExecStrategy {
                 }
                 #[cfg(feature = "std")]
                 debug!("Error recovering address for transaction {tx_no}");
-                if !is_taiko {
-                    bail!("invalid signature");
-                }
                 // If the signature is not valid, skip the transaction
+                block_builder.skipped.push(SkippedTx {
+                    tx_no: Some(tx_no),
+                    tx_hash: Some(*tx.tx_hash()),
+                    reason: SkipReason::InvalidSignature,
+                });
                 continue;
             }
 
@@ -223,7 +303,7 @@ impl TxExecStrategy for TkoTxExecStrategy {
             }
 
             // verify transaction gas
-            let block_available_gas = block_builder.input.gas_limit - cumulative_gas_used;
+            let block_available_gas = block_builder.input.gas_limit - executed.cumulative_gas_used;
             if block_available_gas < tx_env.gas_limit {
                 if is_optimistic {
                     continue;
@@ -233,21 +313,18 @@ impl TxExecStrategy for TkoTxExecStrategy {
                 }
                 #[cfg(feature = "std")]
                 debug!("Error at transaction {tx_no}: gas exceeds block limit");
-                if !is_taiko {
-                    bail!("gas exceeds block limit");
-                }
+                block_builder.skipped.push(SkippedTx {
+                    tx_no: Some(tx_no),
+                    tx_hash: Some(*tx.tx_hash()),
+                    reason: SkipReason::GasLimitExceeded {
+                        gas_limit: tx_env.gas_limit,
+                        available_gas: block_available_gas,
+                    },
+                });
                 continue;
             }
 
-            // verify blob gas
-            if let TxEnvelope::Eip4844(blob_tx) = &tx {
-                let tx = blob_tx.tx().tx();
-                blob_gas_used = blob_gas_used.checked_add(tx.blob_gas()).unwrap();
-                ensure!(
-                    blob_gas_used <= MAX_BLOB_GAS_PER_BLOCK,
-                    "Error at transaction {tx_no}: total blob gas spent exceeds the limit",
-                );
-            }
+            executed.add_blob_gas(tx_no, &tx)?;
 
             // process the transaction
             let start = Instant::now();
@@ -260,9 +337,6 @@ impl TxExecStrategy for TkoTxExecStrategy {
                     if is_optimistic {
                         continue;
                     }
-                    if !is_taiko {
-                        bail!("tx failed to execute successfully: {err:?}");
-                    }
                     if is_anchor {
                         bail!("Anchor tx failed to execute successfully: {err:?}");
                     }
@@ -273,6 +347,13 @@ impl TxExecStrategy for TkoTxExecStrategy {
                             #[cfg(feature = "std")]
                             debug!("Invalid tx at {tx_no}: {invalid_transaction:?}");
                             // skip the tx
+                            block_builder.skipped.push(SkippedTx {
+                                tx_no: Some(tx_no),
+                                tx_hash: Some(*tx.tx_hash()),
+                                reason: SkipReason::InvalidTransaction(format!(
+                                    "{invalid_transaction:?}"
+                                )),
+                            });
                             continue;
                         }
                         _ => {
@@ -285,10 +366,6 @@ impl TxExecStrategy for TkoTxExecStrategy {
             #[cfg(feature = "std")]
             debug!("  Ok: {result:?}");
 
-            #[cfg(feature = "tracer")]
-            // Flush the trace writer
-            trace.lock().unwrap().flush().expect("Error flushing trace");
-
             tx_transact_duration.add_assign(start.elapsed());
 
             let start = Instant::now();
@@ -301,33 +378,27 @@ impl TxExecStrategy for TkoTxExecStrategy {
                 );
             }
 
-            // keep track of all the gas used in the block
-            let gas_used = result.gas_used();
-            cumulative_gas_used = cumulative_gas_used.checked_add(gas_used).unwrap();
-
-            // create the receipt from the EVM result
-            let receipt = Receipt::new(
-                tx.tx_type() as u8,
-                result.is_success(),
-                cumulative_gas_used.try_into().unwrap(),
-                result.logs().iter().map(|log| log.clone().into()).collect(),
-            );
-
             // update the state
             evm.context.evm.db.commit(state);
 
-            // accumulate logs to the block bloom filter
-            logs_bloom.accrue_bloom(&receipt.payload.logs_bloom);
-
-            // Add receipt and tx to tries
-            let trie_key = alloy_rlp::encode(actual_tx_no);
-            // Add to tx trie
-            tx_trie.insert_rlp_encoded(&trie_key, tx.to_rlp_2718())?;
-            // Add to receipt trie
-            receipt_trie.insert_rlp(&trie_key, receipt)?;
+            #[cfg(feature = "tracer")]
+            {
+                let gas_limit = evm.tx().gas_limit;
+                if let Some(trace) = evm.context.external.finish_tx(
+                    executed.tx_count,
+                    *tx.tx_hash(),
+                    gas_limit,
+                    result.gas_used(),
+                ) {
+                    block_builder.traces.push(trace);
+                }
+            }
 
             // If we got here it means the tx is not invalid
-            actual_tx_no += 1;
+            let (receipt, gas_used) = executed.add(&tx, &result)?;
+            block_builder.tx_hashes.push(*tx.tx_hash());
+            block_builder.receipts.push(receipt);
+            block_builder.gas_used.push(gas_used);
 
             tx_misc_duration.add_assign(start.elapsed());
         }
@@ -335,47 +406,220 @@ impl TxExecStrategy for TkoTxExecStrategy {
         print_duration("Tx transact time: ", tx_transact_duration);
         print_duration("Tx misc time: ", tx_misc_duration);
 
-        let mut db = &mut evm.context.evm.db;
-
-        // process withdrawals unconditionally after any transactions
-        let measurement = Measurement::start("Processing withdrawals...", true);
-        let mut withdrawals_trie = MptNode::default();
-        for (i, withdrawal) in block_builder.input.withdrawals.iter().enumerate() {
-            // the withdrawal amount is given in Gwei
-            let amount_wei = GWEI_TO_WEI
-                .checked_mul(withdrawal.amount.try_into().unwrap())
-                .unwrap();
-
-            // Credit withdrawal amount
-            increase_account_balance(&mut db, withdrawal.address, amount_wei)?;
-            // Add withdrawal to trie
-            withdrawals_trie
-                .insert_rlp(&i.to_rlp(), withdrawal)
-                .with_context(|| "failed to insert withdrawal")?;
-        }
-        measurement.stop();
-
-        // Update result header with computed values
-        let measurement = Measurement::start("Generating block header...", true);
-        header.transactions_root = tx_trie.hash();
-        header.receipts_root = receipt_trie.hash();
-        header.logs_bloom = logs_bloom;
-        header.gas_used = cumulative_gas_used.into();
-        if spec_id >= SpecId::SHANGHAI {
-            header.withdrawals_root = Some(withdrawals_trie.hash());
-        };
-        if spec_id >= SpecId::CANCUN {
-            header.blob_gas_used = Some(blob_gas_used.into());
-        }
-        measurement.stop();
+        finish_execution(
+            &mut evm.context.evm.db,
+            &block_builder.input,
+            header,
+            spec_id,
+            executed,
+        )?;
 
-        // Leak memory, save cycles
-        guest_mem_forget([tx_trie, receipt_trie, withdrawals_trie]);
         // Return block builder with updated database
         Ok(block_builder.with_db(evm.context.evm.inner.db))
     }
 }
 
+/// Clears the results of a previous execution and returns the spec id of the block.
+fn start_execution<D>(block_builder: &mut BlockBuilder<D>) -> Result<SpecId> {
+    // Only keep the results of the last execution
+    block_builder.tx_hashes.clear();
+    block_builder.receipts.clear();
+    block_builder.gas_used.clear();
+    block_builder.skipped.clear();
+    #[cfg(feature = "tracer")]
+    block_builder.traces.clear();
+
+    let header = block_builder
+        .header
+        .as_ref()
+        .expect("Header is not initialized");
+    // Compute the spec id
+    let spec_id = block_builder
+        .chain_spec
+        .active_fork(header.number, header.timestamp)
+        .unwrap();
+    if !SpecId::enabled(spec_id, MIN_SPEC_ID) {
+        bail!("Invalid protocol version: expected >= {MIN_SPEC_ID:?}, got {spec_id:?}")
+    }
+    println!("spec_id: {spec_id:?}");
+    Ok(spec_id)
+}
+
+/// Sets the EVM block environment of the block.
+fn fill_block_env(blk_env: &mut BlockEnv, header: &AlloyConsensusHeader, input: &GuestInput) {
+    blk_env.number = U256::from(header.number);
+    blk_env.coinbase = input.beneficiary;
+    blk_env.timestamp = header.timestamp.try_into().unwrap();
+    blk_env.difficulty = U256::ZERO;
+    blk_env.prevrandao = Some(header.mix_hash);
+    blk_env.basefee = header.base_fee_per_gas.unwrap().try_into().unwrap();
+    blk_env.gas_limit = input.gas_limit.try_into().unwrap();
+    if let Some(excess_blob_gas) = header.excess_blob_gas {
+        blk_env.set_blob_excess_gas_and_price(excess_blob_gas.try_into().unwrap());
+    }
+}
+
+/// Sets the beacon block root in the EVM (Cancun).
+fn apply_beacon_root_contract_call<EXT, D>(
+    evm: &mut Evm<'_, EXT, D>,
+    header: &AlloyConsensusHeader,
+) -> Result<()>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: Debug,
+{
+    let parent_beacon_block_root = header
+        .parent_beacon_block_root
+        .context("Missing parent beacon block root")?;
+
+    // From EIP-4788 Beacon block root in the EVM (Cancun):
+    // "Call BEACON_ROOTS_ADDRESS as SYSTEM_ADDRESS with the 32-byte input of
+    //  header.parent_beacon_block_root, a gas limit of 30_000_000, and 0 value."
+    evm.env_mut().tx = TxEnv {
+        transact_to: TransactTo::Call(BEACON_ROOTS_ADDRESS),
+        caller: SYSTEM_ADDRESS,
+        data: parent_beacon_block_root.into(),
+        gas_limit: 30_000_000,
+        value: U256::ZERO,
+        ..Default::default()
+    };
+
+    let tmp = evm.env_mut().block.clone();
+
+    // disable block gas limit validation and base fee checks
+    evm.block_mut().gas_limit = U256::from(evm.tx().gas_limit);
+    evm.block_mut().basefee = U256::ZERO;
+
+    let ResultAndState { mut state, .. } =
+        evm.transact().expect("beacon roots contract call failed");
+    evm.env_mut().block = tmp;
+
+    // commit only the changes to the beacon roots contract
+    state.remove(&SYSTEM_ADDRESS);
+    state.remove(&evm.block().coinbase);
+    evm.context.evm.db.commit(state);
+    Ok(())
+}
+
+/// The transactions included in the block so far, from which the header fields of the
+/// transactions are computed.
+#[derive(Default)]
+struct ExecutedTxs {
+    /// bloom filter over all transaction logs
+    logs_bloom: Bloom,
+    /// the gas used over all transactions
+    cumulative_gas_used: u64,
+    blob_gas_used: u64,
+    tx_trie: MptNode,
+    receipt_trie: MptNode,
+    /// The number of included transactions, the key of the next one in the tx/receipt trees.
+    tx_count: usize,
+}
+
+impl ExecutedTxs {
+    /// Adds the blob gas of the transaction, which has to stay within the block limit.
+    fn add_blob_gas(&mut self, tx_no: usize, tx: &TxEnvelope) -> Result<()> {
+        if let TxEnvelope::Eip4844(blob_tx) = tx {
+            let tx = blob_tx.tx().tx();
+            self.blob_gas_used = self.blob_gas_used.checked_add(tx.blob_gas()).unwrap();
+            ensure!(
+                self.blob_gas_used <= MAX_BLOB_GAS_PER_BLOCK,
+                "Error at transaction {tx_no}: total blob gas spent exceeds the limit",
+            );
+        }
+        Ok(())
+    }
+
+    /// Adds an executed transaction to the tx and receipt tries, and returns its receipt and
+    /// the gas it used.
+    fn add(&mut self, tx: &TxEnvelope, result: &ExecutionResult) -> Result<(Receipt, u64)> {
+        // keep track of all the gas used in the block
+        let gas_used = result.gas_used();
+        self.cumulative_gas_used = self.cumulative_gas_used.checked_add(gas_used).unwrap();
+
+        // create the receipt from the EVM result
+        let receipt = Receipt::new(
+            tx.tx_type() as u8,
+            result.is_success(),
+            self.cumulative_gas_used.try_into().unwrap(),
+            result.logs().iter().map(|log| log.clone().into()).collect(),
+        );
+
+        // accumulate logs to the block bloom filter
+        self.logs_bloom.accrue_bloom(&receipt.payload.logs_bloom);
+
+        // Add receipt and tx to tries
+        let trie_key = alloy_rlp::encode(self.tx_count);
+        // Add to tx trie
+        self.tx_trie
+            .insert_rlp_encoded(&trie_key, tx.to_rlp_2718())?;
+        // Add to receipt trie
+        self.receipt_trie.insert_rlp(&trie_key, &receipt)?;
+        self.tx_count += 1;
+
+        Ok((receipt, gas_used))
+    }
+}
+
+/// Processes the withdrawals after the transactions and fills in the header fields computed
+/// from the execution.
+fn finish_execution<D>(
+    db: &mut D,
+    input: &GuestInput,
+    header: &mut AlloyConsensusHeader,
+    spec_id: SpecId,
+    executed: ExecutedTxs,
+) -> Result<()>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: Debug,
+{
+    // process withdrawals unconditionally after any transactions
+    ensure!(
+        spec_id >= SpecId::SHANGHAI || input.withdrawals.is_empty(),
+        "Withdrawals are not allowed before Shanghai"
+    );
+    let measurement = Measurement::start("Processing withdrawals...", true);
+    let mut withdrawals_trie = MptNode::default();
+    for (i, withdrawal) in input.withdrawals.iter().enumerate() {
+        // the withdrawal amount is given in Gwei
+        let amount_wei = GWEI_TO_WEI
+            .checked_mul(withdrawal.amount.try_into().unwrap())
+            .unwrap();
+
+        // Credit withdrawal amount
+        increase_account_balance(db, withdrawal.address, amount_wei)?;
+        // Add withdrawal to trie
+        withdrawals_trie
+            .insert_rlp(&i.to_rlp(), withdrawal)
+            .with_context(|| "failed to insert withdrawal")?;
+    }
+    measurement.stop();
+
+    // Update result header with computed values
+    let measurement = Measurement::start("Generating block header...", true);
+    header.transactions_root = executed.tx_trie.hash();
+    header.receipts_root = executed.receipt_trie.hash();
+    header.logs_bloom = executed.logs_bloom;
+    header.gas_used = executed.cumulative_gas_used.into();
+    if spec_id >= SpecId::SHANGHAI {
+        header.withdrawals_root = Some(withdrawals_trie.hash());
+    };
+    if spec_id >= SpecId::CANCUN {
+        header.blob_gas_used = Some(executed.blob_gas_used.into());
+    } else {
+        ensure!(
+            executed.blob_gas_used == 0,
+            "Blob transactions are not allowed before Cancun"
+        );
+    }
+    measurement.stop();
+
+    // Leak memory, save cycles
+    guest_mem_forget([executed.tx_trie, executed.receipt_trie, withdrawals_trie]);
+    Ok(())
+}
+
 pub fn fill_eth_tx_env(tx_env: &mut TxEnv, tx: &TxEnvelope) -> Result<(), Error> {
     // Clear values that may not be set
     tx_env.access_list.clear();
@@ -478,49 +722,3 @@ where
 
     Ok(())
 }
-
-#[cfg(feature = "tracer")]
-fn set_trace_writer(
-    tracer: &mut TracerEip3155,
-    chain_id: u64,
-    block_number: u64,
-    tx_no: usize,
-) -> Arc<Mutex<BufWriter<File>>> {
-    struct FlushWriter {
-        writer: Arc<Mutex<BufWriter<std::fs::File>>>,
-    }
-    impl FlushWriter {
-        fn new(writer: Arc<Mutex<BufWriter<std::fs::File>>>) -> Self {
-            Self { writer }
-        }
-    }
-    impl Write for FlushWriter {
-        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-            self.writer.lock().unwrap().write(buf)
-        }
-
-        fn flush(&mut self) -> std::io::Result<()> {
-            self.writer.lock().unwrap().flush()
-        }
-    }
-
-    // Create the traces directory if it doesn't exist
-    std::fs::create_dir_all("traces").expect("Failed to create traces directory");
-
-    // Construct the file writer to write the trace to
-    let file_name = format!("traces/{}_{}_{}.json", chain_id, block_number, tx_no);
-    let write = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(file_name);
-    let trace = Arc::new(Mutex::new(BufWriter::new(
-        write.expect("Failed to open file"),
-    )));
-    let writer = FlushWriter::new(Arc::clone(&trace));
-
-    // Set the writer inside the handler in revm
-    tracer.set_writer(Box::new(writer));
-
-    trace
-}
@@ -19,12 +19,14 @@ use anyhow::Result;
 use raiko_primitives::{
     keccak::keccak,
     mpt::{MptNode, StateAccount},
+    Address,
 };
-use revm::{Database, DatabaseCommit};
+use revm::{primitives::HashMap, Database, DatabaseCommit};
 
 use crate::{
     builder::BlockBuilder,
     guest_mem_forget,
+    input::StorageEntry,
     mem_db::{AccountState, MemDb},
 };
 
@@ -44,65 +46,103 @@ impl BlockFinalizeStrategy<MemDb> for MemDbBlockFinalizeStrategy {
 
         // apply state updates
         let mut state_trie = mem::take(&mut block_builder.input.parent_state_trie);
-        for (address, account) in &db.accounts {
-            // if the account has not been touched, it can be ignored
-            if account.state == AccountState::None {
-                continue;
-            }
-
-            // compute the index of the current account in the state trie
-            let state_trie_index = keccak(address);
+        apply_state_updates(
+            &db,
+            &mut state_trie,
+            &mut block_builder.input.parent_storage,
+        )?;
 
-            // remove deleted accounts from the state trie
-            if account.state == AccountState::Deleted {
-                state_trie.delete(&state_trie_index)?;
-                continue;
-            }
+        // update result header with the new state root
+        let mut header = block_builder.header.take().expect("Header not initialized");
+        header.state_root = state_trie.hash();
 
-            // otherwise, compute the updated storage root for that account
-            let state_storage = &account.storage;
-            let storage_root = {
-                // getting a mutable reference is more efficient than calling remove
-                // every account must have an entry, even newly created accounts
-                let (storage_trie, _) = block_builder
-                    .input
-                    .parent_storage
-                    .get_mut(address)
-                    .expect("Address not found in storage");
-                // for cleared accounts always start from the empty trie
-                if account.state == AccountState::StorageCleared {
-                    storage_trie.clear();
-                }
+        // Leak memory, save cycles
+        guest_mem_forget(block_builder);
 
-                // apply all new storage entries for the current account (address)
-                for (key, value) in state_storage {
-                    let storage_trie_index = keccak(key.to_be_bytes::<32>());
-                    if value.is_zero() {
-                        storage_trie.delete(&storage_trie_index)?;
-                    } else {
-                        storage_trie.insert_rlp(&storage_trie_index, *value)?;
-                    }
-                }
+        Ok((header, state_trie))
+    }
+}
 
-                storage_trie.hash()
-            };
+impl MemDbBlockFinalizeStrategy {
+    /// Like [BlockFinalizeStrategy::finalize], but also returns the database and the updated
+    /// storage tries so the final state can be used to build the next block on top of it.
+    pub fn finalize_with_state(
+        mut block_builder: BlockBuilder<MemDb>,
+    ) -> Result<(
+        AlloyConsensusHeader,
+        MemDb,
+        MptNode,
+        HashMap<Address, StorageEntry>,
+    )> {
+        let db: MemDb = block_builder.db.take().expect("DB not initialized");
 
-            let state_account = StateAccount {
-                nonce: account.info.nonce,
-                balance: account.info.balance,
-                storage_root,
-                code_hash: account.info.code_hash,
-            };
-            state_trie.insert_rlp(&state_trie_index, state_account)?;
-        }
+        let mut state_trie = mem::take(&mut block_builder.input.parent_state_trie);
+        let mut storage = mem::take(&mut block_builder.input.parent_storage);
+        apply_state_updates(&db, &mut state_trie, &mut storage)?;
 
-        // update result header with the new state root
         let mut header = block_builder.header.take().expect("Header not initialized");
         header.state_root = state_trie.hash();
 
-        // Leak memory, save cycles
         guest_mem_forget(block_builder);
 
-        Ok((header, state_trie))
+        Ok((header, db, state_trie, storage))
+    }
+}
+
+/// Applies all changes of the database to the state trie and the storage tries.
+fn apply_state_updates(
+    db: &MemDb,
+    state_trie: &mut MptNode,
+    storage: &mut HashMap<Address, StorageEntry>,
+) -> Result<()> {
+    for (address, account) in &db.accounts {
+        // if the account has not been touched, it can be ignored
+        if account.state == AccountState::None {
+            continue;
+        }
+
+        // compute the index of the current account in the state trie
+        let state_trie_index = keccak(address);
+
+        // remove deleted accounts from the state trie
+        if account.state == AccountState::Deleted {
+            state_trie.delete(&state_trie_index)?;
+            continue;
+        }
+
+        // otherwise, compute the updated storage root for that account
+        let state_storage = &account.storage;
+        let storage_root = {
+            // getting a mutable reference is more efficient than calling remove
+            // every account must have an entry, even newly created accounts
+            let (storage_trie, _) = storage
+                .get_mut(address)
+                .expect("Address not found in storage");
+            // for cleared accounts always start from the empty trie
+            if account.state == AccountState::StorageCleared {
+                storage_trie.clear();
+            }
+
+            // apply all new storage entries for the current account (address)
+            for (key, value) in state_storage {
+                let storage_trie_index = keccak(key.to_be_bytes::<32>());
+                if value.is_zero() {
+                    storage_trie.delete(&storage_trie_index)?;
+                } else {
+                    storage_trie.insert_rlp(&storage_trie_index, *value)?;
+                }
+            }
+
+            storage_trie.hash()
+        };
+
+        let state_account = StateAccount {
+            nonce: account.info.nonce,
+            balance: account.info.balance,
+            storage_root,
+            code_hash: account.info.code_hash,
+        };
+        state_trie.insert_rlp(&state_trie_index, state_account)?;
     }
+    Ok(())
 }
@@ -12,33 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::mem;
+
 use alloy_consensus::Header as AlloyConsensusHeader;
-use anyhow::Result;
-use raiko_primitives::mpt::MptNode;
+use anyhow::{anyhow, bail, Result};
+use raiko_primitives::{mpt::MptNode, receipt::Receipt, B256};
 use revm::{Database, DatabaseCommit};
+use serde::{Deserialize, Serialize};
 
-pub use self::execute::TkoTxExecStrategy;
+pub use self::execute::{EthTxExecStrategy, TkoTxExecStrategy};
+#[cfg(feature = "tracer")]
+use crate::builder::trace::{TraceConfig, TxTrace};
 use crate::{
     builder::{
         finalize::{BlockFinalizeStrategy, MemDbBlockFinalizeStrategy},
         initialize::{DbInitStrategy, MemDbInitStrategy},
-        prepare::{HeaderPrepStrategy, TaikoHeaderPrepStrategy},
+        prepare::{EthHeaderPrepStrategy, HeaderPrepStrategy},
     },
-    consts::ChainSpec,
-    input::GuestInput,
-    mem_db::MemDb,
+    consts::{BlockBuilderKind, ChainSpec},
+    input::{BatchGuestInput, GuestInput},
+    mem_db::{AccountChange, MemDb},
+    utils::HeaderHasher,
 };
 
 pub mod execute;
 mod finalize;
 mod initialize;
 pub mod prepare;
+#[cfg(feature = "tracer")]
+pub mod trace;
 
 /// Optimistic database
 #[allow(async_fn_in_trait)]
-pub trait OptimisticDatabase {
-    /// Handle post execution work
-    async fn fetch_data(&mut self) -> bool;
+pub trait OptimisticDatabase: Database {
+    /// Handle post execution work, returns if the run was valid
+    async fn fetch_data(&mut self) -> Result<bool, <Self as Database>::Error>;
 
     /// If the current database is optimistic
     fn is_optimistic(&self) -> bool;
@@ -51,6 +59,93 @@ pub struct BlockBuilder<D> {
     pub(crate) input: GuestInput,
     pub(crate) db: Option<D>,
     pub(crate) header: Option<AlloyConsensusHeader>,
+    /// The hashes of the transactions included in the block.
+    pub(crate) tx_hashes: Vec<B256>,
+    /// The receipts of the transactions included in the block.
+    pub(crate) receipts: Vec<Receipt>,
+    /// The gas used by each transaction included in the block.
+    pub(crate) gas_used: Vec<u64>,
+    /// The transactions of the tx list that were not included in the block.
+    pub(crate) skipped: Vec<SkippedTx>,
+    /// Selects the transactions to trace.
+    #[cfg(feature = "tracer")]
+    pub(crate) trace_config: Option<TraceConfig>,
+    /// The traces of the selected transactions included in the block.
+    #[cfg(feature = "tracer")]
+    pub(crate) traces: Vec<TxTrace>,
+}
+
+/// Why a transaction of the tx list, or the whole tx list, was not included in the block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The tx list could not be decompressed or RLP decoded.
+    TxListDecodeFailure(String),
+    /// The tx list is larger than allowed.
+    TxListTooLarge { size: usize, capacity: usize },
+    /// The blob is not encoded with the supported encoding version.
+    BlobVersionMismatch { version: u8 },
+    /// The blob data is not encoded correctly.
+    InvalidBlob(String),
+    /// The sender could not be recovered from the signature.
+    InvalidSignature,
+    /// The gas limit of the transaction exceeds the gas left in the block.
+    GasLimitExceeded { gas_limit: u64, available_gas: u64 },
+    /// The transaction is invalid in the state of the block, e.g. because of its nonce or
+    /// the balance of the sender.
+    InvalidTransaction(String),
+}
+
+impl SkipReason {
+    /// A short name of the reason, e.g. for metric labels.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SkipReason::TxListDecodeFailure(_) => "tx_list_decode_failure",
+            SkipReason::TxListTooLarge { .. } => "tx_list_too_large",
+            SkipReason::BlobVersionMismatch { .. } => "blob_version_mismatch",
+            SkipReason::InvalidBlob(_) => "invalid_blob",
+            SkipReason::InvalidSignature => "invalid_signature",
+            SkipReason::GasLimitExceeded { .. } => "gas_limit_exceeded",
+            SkipReason::InvalidTransaction(_) => "invalid_transaction",
+        }
+    }
+}
+
+/// A transaction of the tx list that was not included in the block. Without `tx_no` and
+/// `tx_hash` the whole tx list was dropped and the block only contains the anchor
+/// transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedTx {
+    /// The position of the transaction in the tx list, with the anchor transaction at 0.
+    pub tx_no: Option<usize>,
+    pub tx_hash: Option<B256>,
+    pub reason: SkipReason,
+}
+
+impl core::fmt::Display for SkippedTx {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match (self.tx_no, self.tx_hash) {
+            (Some(tx_no), Some(tx_hash)) => write!(f, "tx {tx_no} ({tx_hash}): {:?}", self.reason),
+            _ => write!(f, "tx list: {:?}", self.reason),
+        }
+    }
+}
+
+/// A built block together with its post-execution state and receipts, to inspect the
+/// execution of the block.
+#[derive(Clone, Debug)]
+pub struct BlockBuildOutput {
+    pub header: AlloyConsensusHeader,
+    /// The state after executing the block.
+    pub db: MemDb,
+    /// The hashes of the transactions included in the block.
+    pub tx_hashes: Vec<B256>,
+    /// The receipts of the transactions included in the block.
+    pub receipts: Vec<Receipt>,
+    /// The gas used by each transaction included in the block.
+    pub gas_used: Vec<u64>,
+    /// The transactions of the tx list that were not included in the block.
+    pub skipped: Vec<SkippedTx>,
 }
 
 impl<D> BlockBuilder<D>
@@ -65,9 +160,24 @@ where
             db: None,
             header: None,
             input: input.clone(),
+            tx_hashes: Vec::new(),
+            receipts: Vec::new(),
+            gas_used: Vec::new(),
+            skipped: Vec::new(),
+            #[cfg(feature = "tracer")]
+            trace_config: None,
+            #[cfg(feature = "tracer")]
+            traces: Vec::new(),
         }
     }
 
+    /// Traces the transactions selected by `config` while executing them.
+    #[cfg(feature = "tracer")]
+    pub fn with_tracing(mut self, config: TraceConfig) -> Self {
+        self.trace_config = Some(config);
+        self
+    }
+
     /// Sets the database instead of initializing it from the input.
     pub fn with_db(mut self, db: D) -> Self {
         self.db = Some(db);
@@ -89,6 +199,30 @@ where
         T::execute_transactions(self)
     }
 
+    /// Initializes the header with the [HeaderPrepStrategy] of the chain of the block.
+    pub fn prepare_chain_header(self) -> Result<Self> {
+        match self.chain_spec.block_builder() {
+            BlockBuilderKind::Ethereum => self.prepare_strategy_header::<EthereumStrategy>(),
+            BlockBuilderKind::Taiko => self.prepare_strategy_header::<TaikoStrategy>(),
+        }
+    }
+
+    /// Executes all input transactions with the [TxExecStrategy] of the chain of the block.
+    pub fn execute_chain_transactions(self) -> Result<Self> {
+        match self.chain_spec.block_builder() {
+            BlockBuilderKind::Ethereum => self.execute_strategy_transactions::<EthereumStrategy>(),
+            BlockBuilderKind::Taiko => self.execute_strategy_transactions::<TaikoStrategy>(),
+        }
+    }
+
+    fn prepare_strategy_header<S: BlockBuilderStrategy>(self) -> Result<Self> {
+        self.prepare_header::<S::HeaderPrepStrategy>()
+    }
+
+    fn execute_strategy_transactions<S: BlockBuilderStrategy>(self) -> Result<Self> {
+        self.execute_transactions::<S::TxExecStrategy>()
+    }
+
     /// Finalizes the block building and returns the header and the state trie.
     pub fn finalize<T: BlockFinalizeStrategy<D>>(self) -> Result<(AlloyConsensusHeader, MptNode)> {
         T::finalize(self)
@@ -103,6 +237,11 @@ where
     pub fn mut_db(&mut self) -> Option<&mut D> {
         self.db.as_mut()
     }
+
+    /// Takes the database out of the builder.
+    pub fn take_db(&mut self) -> Option<D> {
+        self.db.take()
+    }
 }
 
 /// A bundle of strategies for building a block using [BlockBuilder].
@@ -120,19 +259,424 @@ pub trait BlockBuilderStrategy {
             .execute_transactions::<Self::TxExecStrategy>()?
             .finalize::<Self::BlockFinalizeStrategy>()
     }
+
+    /// Builds a block from the given input and also returns the post-execution state, the
+    /// receipts and the skipped transactions of the block.
+    fn build_with_output(input: &GuestInput) -> Result<BlockBuildOutput> {
+        let mut builder = BlockBuilder::<MemDb>::new(input)
+            .initialize_database::<Self::DbInitStrategy>()?
+            .prepare_header::<Self::HeaderPrepStrategy>()?
+            .execute_transactions::<Self::TxExecStrategy>()?;
+        let tx_hashes = mem::take(&mut builder.tx_hashes);
+        let receipts = mem::take(&mut builder.receipts);
+        let gas_used = mem::take(&mut builder.gas_used);
+        let skipped = mem::take(&mut builder.skipped);
+        let (header, db, _, _) = MemDbBlockFinalizeStrategy::finalize_with_state(builder)?;
+        Ok(BlockBuildOutput {
+            header,
+            db,
+            tx_hashes,
+            receipts,
+            gas_used,
+            skipped,
+        })
+    }
+
+    /// Like [BlockBuilderStrategy::build_with_output], but also returns the accounts changed
+    /// by the block.
+    ///
+    /// The state of the parent block is loaded a second time from the input to compare the
+    /// final state against, so only use this when the state changes are needed.
+    fn build_with_state_changes(
+        input: &GuestInput,
+    ) -> Result<(BlockBuildOutput, Vec<AccountChange>)> {
+        let parent_db = BlockBuilder::<MemDb>::new(input)
+            .initialize_database::<Self::DbInitStrategy>()?
+            .take_db()
+            .ok_or_else(|| anyhow!("The database of the parent block was not initialized"))?;
+        let output = Self::build_with_output(input)?;
+        let state_changes = output.db.changes_since(&parent_db);
+        Ok((output, state_changes))
+    }
+
+    /// Executes the transactions of the given input and returns the traces of the ones
+    /// selected by `config`.
+    #[cfg(feature = "tracer")]
+    fn trace(input: &GuestInput, config: &TraceConfig) -> Result<Vec<TxTrace>> {
+        let builder = BlockBuilder::<MemDb>::new(input)
+            .with_tracing(config.clone())
+            .initialize_database::<Self::DbInitStrategy>()?
+            .prepare_header::<Self::HeaderPrepStrategy>()?
+            .execute_transactions::<Self::TxExecStrategy>()?;
+        Ok(builder.traces)
+    }
+
+    /// Builds a batch of consecutive blocks and returns their headers.
+    ///
+    /// The database is initialized once from the state witness of the first block. Every
+    /// following block is executed on top of the final state of the block before it, so the
+    /// state is always finalized with [MemDbBlockFinalizeStrategy].
+    fn build_batch_from(batch: &BatchGuestInput) -> Result<Vec<AlloyConsensusHeader>> {
+        let mut headers: Vec<AlloyConsensusHeader> = Vec::with_capacity(batch.inputs.len());
+        let mut state = None;
+        for input in &batch.inputs {
+            let mut builder = BlockBuilder::<MemDb>::new(input);
+            builder = match state.take() {
+                None => builder.initialize_database::<Self::DbInitStrategy>()?,
+                Some((mut db, state_trie, storage)) => {
+                    let parent = headers.last().expect("A previous block was built");
+                    let parent_hash = parent.hash();
+                    if input.parent_header.hash() != parent_hash {
+                        bail!(
+                            "Invalid batch: block {} does not build on block {}",
+                            input.block_number,
+                            parent.number
+                        );
+                    }
+                    db.insert_block_hash(parent.number, parent_hash);
+                    builder.input.parent_state_trie = state_trie;
+                    builder.input.parent_storage = storage;
+                    builder.with_db(db)
+                }
+            };
+            let (header, db, state_trie, storage) =
+                MemDbBlockFinalizeStrategy::finalize_with_state(
+                    builder
+                        .prepare_header::<Self::HeaderPrepStrategy>()?
+                        .execute_transactions::<Self::TxExecStrategy>()?,
+                )?;
+            headers.push(header);
+            state = Some((db, state_trie, storage));
+        }
+        Ok(headers)
+    }
 }
 
 /// The [BlockBuilderStrategy] for building a Taiko block.
 pub struct TaikoStrategy {}
 impl BlockBuilderStrategy for TaikoStrategy {
     type DbInitStrategy = MemDbInitStrategy;
-    type HeaderPrepStrategy = TaikoHeaderPrepStrategy;
+    type HeaderPrepStrategy = EthHeaderPrepStrategy;
     type TxExecStrategy = TkoTxExecStrategy;
     type BlockFinalizeStrategy = MemDbBlockFinalizeStrategy;
 }
+
+/// The [BlockBuilderStrategy] for building an Ethereum block.
+pub struct EthereumStrategy {}
+impl BlockBuilderStrategy for EthereumStrategy {
+    type DbInitStrategy = MemDbInitStrategy;
+    type HeaderPrepStrategy = EthHeaderPrepStrategy;
+    type TxExecStrategy = EthTxExecStrategy;
+    type BlockFinalizeStrategy = MemDbBlockFinalizeStrategy;
+}
+
+/// Calls the function of the [BlockBuilderStrategy] selected by the chain spec.
+macro_rules! with_chain_strategy {
+    ($chain_spec:expr, $f:ident($($arg:expr),*)) => {
+        match $chain_spec.block_builder() {
+            BlockBuilderKind::Ethereum => EthereumStrategy::$f($($arg),*),
+            BlockBuilderKind::Taiko => TaikoStrategy::$f($($arg),*),
+        }
+    };
+}
+
+/// Builds blocks with the [BlockBuilderStrategy] selected by the chain spec of the input, see
+/// [ChainSpec::block_builder]. A chain with its own header or execution rules adds its
+/// [BlockBuilderKind] and the strategy it selects.
+pub struct ChainStrategy {}
+impl ChainStrategy {
+    /// See [BlockBuilderStrategy::build_from].
+    pub fn build_from(input: &GuestInput) -> Result<(AlloyConsensusHeader, MptNode)> {
+        with_chain_strategy!(input.chain_spec, build_from(input))
+    }
+
+    /// See [BlockBuilderStrategy::build_with_output].
+    pub fn build_with_output(input: &GuestInput) -> Result<BlockBuildOutput> {
+        with_chain_strategy!(input.chain_spec, build_with_output(input))
+    }
+
+    /// See [BlockBuilderStrategy::build_with_state_changes].
+    pub fn build_with_state_changes(
+        input: &GuestInput,
+    ) -> Result<(BlockBuildOutput, Vec<AccountChange>)> {
+        with_chain_strategy!(input.chain_spec, build_with_state_changes(input))
+    }
+
+    /// See [BlockBuilderStrategy::trace].
+    #[cfg(feature = "tracer")]
+    pub fn trace(input: &GuestInput, config: &TraceConfig) -> Result<Vec<TxTrace>> {
+        with_chain_strategy!(input.chain_spec, trace(input, config))
+    }
+
+    /// See [BlockBuilderStrategy::build_batch_from]. All blocks of a batch are of the same
+    /// chain.
+    pub fn build_batch_from(batch: &BatchGuestInput) -> Result<Vec<AlloyConsensusHeader>> {
+        let Some(first) = batch.inputs.first() else {
+            return Ok(Vec::new());
+        };
+        if batch
+            .inputs
+            .iter()
+            .any(|input| input.chain_spec != first.chain_spec)
+        {
+            bail!("Invalid batch: the blocks are not of the same chain");
+        }
+        with_chain_strategy!(first.chain_spec, build_batch_from(batch))
+    }
+}
+
 pub trait TxExecStrategy {
     fn execute_transactions<D>(block_builder: BlockBuilder<D>) -> Result<BlockBuilder<D>>
     where
         D: Database + DatabaseCommit + OptimisticDatabase,
         <D as Database>::Error: core::fmt::Debug;
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::constants::BEACON_ROOTS_ADDRESS;
+    use alloy_rpc_types::Withdrawal;
+    use raiko_primitives::{
+        alloy_eips::eip4788::SYSTEM_ADDRESS, keccak::keccak, mpt::StateAccount, Address, Bytes,
+        U256,
+    };
+    use revm::{primitives::SpecId, Database};
+
+    use super::*;
+    use crate::consts::{Eip1559Constants, GWEI_TO_WEI};
+
+    /// An Ethereum block on top of `parent` without transactions that withdraws `amount` Gwei
+    /// to `address`.
+    fn withdrawal_block(
+        parent: &AlloyConsensusHeader,
+        address: Address,
+        amount: u64,
+    ) -> GuestInput {
+        GuestInput {
+            chain_spec: ChainSpec::new_single(
+                "test".to_owned(),
+                1,
+                SpecId::SHANGHAI,
+                Eip1559Constants::default(),
+                false,
+            ),
+            block_number: parent.number + 1,
+            parent_header: parent.clone(),
+            gas_limit: 30_000_000,
+            timestamp: parent.timestamp + 12,
+            withdrawals: vec![Withdrawal {
+                index: 0,
+                validator_index: 0,
+                address,
+                amount,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_batch_from_carries_state() {
+        let address = Address::with_last_byte(1);
+        let parent = AlloyConsensusHeader {
+            number: 1,
+            state_root: MptNode::default().hash(),
+            ..Default::default()
+        };
+        let mut first = withdrawal_block(&parent, address, 1);
+        first
+            .parent_storage
+            .insert(address, (MptNode::default(), Vec::new()));
+        let (first_header, _) = ChainStrategy::build_from(&first).unwrap();
+        // Only the first block of a batch has a state witness
+        let second = withdrawal_block(&first_header, address, 2);
+
+        let batch = BatchGuestInput {
+            inputs: vec![first.clone(), second.clone()],
+        };
+        let headers = ChainStrategy::build_batch_from(&batch).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[0], first_header);
+        assert_eq!(headers[1].parent_hash, first_header.hash());
+
+        // The second block credits its withdrawal on top of the balance of the first block
+        let mut state_trie = MptNode::default();
+        state_trie
+            .insert_rlp(
+                &keccak(address),
+                StateAccount {
+                    balance: U256::from(3) * GWEI_TO_WEI,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(headers[1].state_root, state_trie.hash());
+
+        // On its own the second block has no state to build on
+        assert!(ChainStrategy::build_from(&second).is_err());
+
+        // Every block has to build on the block before it
+        let unlinked = withdrawal_block(&parent, address, 2);
+        let batch = BatchGuestInput {
+            inputs: vec![first, unlinked],
+        };
+        assert!(ChainStrategy::build_batch_from(&batch).is_err());
+    }
+
+    /// A Cancun block without transactions on top of a chain of headers up to block 18. The
+    /// beacon roots system call runs a contract that stores the hash of the `i`-th of
+    /// `read_hashes` in slot `i`. The input has the state witness of the contract and the
+    /// ancestor headers down to the oldest hash read, trimmed like the preflight does.
+    /// Returns the input and the chain.
+    fn blockhash_block(read_hashes: &[u8]) -> (GuestInput, Vec<AlloyConsensusHeader>) {
+        let mut code = Vec::new();
+        for (slot, number) in read_hashes.iter().enumerate() {
+            // PUSH1 number, BLOCKHASH, PUSH1 slot, SSTORE
+            code.extend_from_slice(&[0x60, *number, 0x40, 0x60, slot as u8, 0x55]);
+        }
+        let code = Bytes::from(code);
+        let mut state_trie = MptNode::default();
+        state_trie
+            .insert_rlp(
+                &keccak(BEACON_ROOTS_ADDRESS),
+                StateAccount {
+                    code_hash: keccak(&code).into(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let mut chain: Vec<AlloyConsensusHeader> = Vec::new();
+        for number in 0..=18 {
+            chain.push(AlloyConsensusHeader {
+                number,
+                parent_hash: chain.last().map(|header| header.hash()).unwrap_or_default(),
+                state_root: state_trie.hash(),
+                ..Default::default()
+            });
+        }
+
+        let mut input = withdrawal_block(&chain[18], Address::ZERO, 0);
+        input.chain_spec = ChainSpec::new_single(
+            "test".to_owned(),
+            1,
+            SpecId::CANCUN,
+            Eip1559Constants::default(),
+            false,
+        );
+        input.withdrawals.clear();
+        input.parent_beacon_block_root = Some(B256::ZERO);
+        input.parent_state_trie = state_trie;
+        input.contracts = vec![code];
+        input.parent_storage.insert(
+            BEACON_ROOTS_ADDRESS,
+            (
+                MptNode::default(),
+                (0..read_hashes.len()).map(U256::from).collect(),
+            ),
+        );
+        // Read by the system call
+        for address in [SYSTEM_ADDRESS, input.beneficiary] {
+            input
+                .parent_storage
+                .insert(address, (MptNode::default(), Vec::new()));
+        }
+        let oldest = read_hashes
+            .iter()
+            .map(|number| *number as usize)
+            .min()
+            .unwrap_or(18)
+            .min(18);
+        input.ancestor_headers = chain[oldest..18].iter().rev().cloned().collect();
+        (input, chain)
+    }
+
+    #[test]
+    fn trimmed_ancestor_headers_authenticate_block_hashes() {
+        let read_hashes = [3, 17, 18];
+        let (input, chain) = blockhash_block(&read_hashes);
+        assert_eq!(input.ancestor_headers.len(), 15);
+
+        // Every hash read is linked to the parent block, and no older hash is available
+        let mut builder = BlockBuilder::<MemDb>::new(&input)
+            .initialize_database::<MemDbInitStrategy>()
+            .unwrap();
+        let db = builder.mut_db().unwrap();
+        for number in read_hashes {
+            assert_eq!(
+                db.block_hash(U256::from(number)).unwrap(),
+                chain[number as usize].hash()
+            );
+        }
+        assert!(db.block_hash(U256::from(2)).is_err());
+
+        // The block reads the authenticated hashes
+        let output = ChainStrategy::build_with_output(&input).unwrap();
+        let storage = &output.db.accounts[&BEACON_ROOTS_ADDRESS].storage;
+        for (slot, number) in read_hashes.iter().enumerate() {
+            assert_eq!(
+                storage[&U256::from(slot)],
+                U256::from_be_bytes(chain[*number as usize].hash().0)
+            );
+        }
+
+        // A header that isn't part of the chain is rejected
+        let mut forged = input.clone();
+        forged.ancestor_headers[5].gas_limit += 1;
+        assert!(ChainStrategy::build_from(&forged).is_err());
+    }
+
+    #[test]
+    fn trimmed_ancestor_headers_authenticate_block_hashes_in_batch() {
+        // Both blocks read the old hashes, the second block also reads the hash of the first
+        // block, which is built in the batch
+        let (first, chain) = blockhash_block(&[9, 17, 18, 19]);
+        assert_eq!(first.ancestor_headers.len(), 9);
+
+        // Only the first block of a batch has a state witness
+        let (first_header, _) = ChainStrategy::build_from(&first).unwrap();
+        let second = GuestInput {
+            block_number: first.block_number + 1,
+            parent_header: first_header.clone(),
+            timestamp: first.timestamp + 12,
+            chain_spec: first.chain_spec.clone(),
+            gas_limit: first.gas_limit,
+            parent_beacon_block_root: first.parent_beacon_block_root,
+            ..Default::default()
+        };
+        let code = first.contracts[0].clone();
+        let batch = BatchGuestInput {
+            inputs: vec![first, second],
+        };
+        let headers = ChainStrategy::build_batch_from(&batch).unwrap();
+        assert_eq!(headers[0], first_header);
+
+        // The second block stored the authenticated hashes
+        let hashes = [
+            chain[9].hash(),
+            chain[17].hash(),
+            chain[18].hash(),
+            first_header.hash(),
+        ];
+        let mut storage_trie = MptNode::default();
+        for (slot, hash) in hashes.iter().enumerate() {
+            storage_trie
+                .insert_rlp(
+                    &keccak(U256::from(slot).to_be_bytes::<32>()),
+                    U256::from_be_bytes(hash.0),
+                )
+                .unwrap();
+        }
+        let mut state_trie = MptNode::default();
+        state_trie
+            .insert_rlp(
+                &keccak(BEACON_ROOTS_ADDRESS),
+                StateAccount {
+                    storage_root: storage_trie.hash(),
+                    code_hash: keccak(&code).into(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(headers[1].state_root, state_trie.hash());
+    }
+}
@@ -15,8 +15,9 @@
 use core::fmt::Debug;
 
 use alloy_consensus::Header as AlloyConsensusHeader;
-use anyhow::{bail, Context, Result};
-use revm::{Database, DatabaseCommit};
+use anyhow::{bail, ensure, Context, Result};
+use raiko_primitives::eip4844::calculate_excess_blob_gas;
+use revm::{primitives::SpecId, Database, DatabaseCommit};
 
 use crate::{builder::BlockBuilder, consts::MAX_EXTRA_DATA_BYTES, utils::HeaderHasher};
 
@@ -27,9 +28,10 @@ pub trait HeaderPrepStrategy {
         <D as Database>::Error: core::fmt::Debug;
 }
 
-pub struct TaikoHeaderPrepStrategy {}
+/// Prepares the header of an Ethereum block. Taiko blocks follow the same header rules.
+pub struct EthHeaderPrepStrategy {}
 
-impl HeaderPrepStrategy for TaikoHeaderPrepStrategy {
+impl HeaderPrepStrategy for EthHeaderPrepStrategy {
     fn prepare_header<D>(mut block_builder: BlockBuilder<D>) -> Result<BlockBuilder<D>>
     where
         D: Database + DatabaseCommit,
@@ -50,13 +52,34 @@ impl HeaderPrepStrategy for TaikoHeaderPrepStrategy {
             bail!("Invalid extra data: expected <= {MAX_EXTRA_DATA_BYTES}, got {extra_data_bytes}")
         }
         // Derive header
-        let number: u64 = block_builder.input.parent_header.number;
+        let number: u64 = block_builder
+            .input
+            .parent_header
+            .number
+            .checked_add(1)
+            .with_context(|| "Invalid block number: too large")?;
+        let spec_id = block_builder.chain_spec.active_fork(number, timestamp)?;
+        // Derive the blob gas fields (Cancun)
+        let excess_blob_gas = if spec_id >= SpecId::CANCUN {
+            ensure!(
+                block_builder.input.parent_beacon_block_root.is_some(),
+                "Missing parent beacon block root"
+            );
+            let excess_blob_gas = calc_excess_blob_gas(&block_builder.input.parent_header)?;
+            if let Some(expected) = block_builder.input.excess_blob_gas {
+                ensure!(
+                    expected == excess_blob_gas,
+                    "Invalid excess blob gas: expected {excess_blob_gas}, got {expected}"
+                );
+            }
+            Some(excess_blob_gas)
+        } else {
+            None
+        };
         block_builder.header = Some(AlloyConsensusHeader {
             // Initialize fields that we can compute from the parent
             parent_hash: block_builder.input.parent_header.hash(),
-            number: number
-                .checked_add(1)
-                .with_context(|| "Invalid block number: too large")?,
+            number,
             base_fee_per_gas: Some(block_builder.input.base_fee_per_gas.into()),
             // Initialize metadata from input
             beneficiary: block_builder.input.beneficiary,
@@ -65,7 +88,7 @@ impl HeaderPrepStrategy for TaikoHeaderPrepStrategy {
             mix_hash: block_builder.input.mix_hash,
             extra_data: block_builder.input.extra_data.clone(),
             blob_gas_used: block_builder.input.blob_gas_used.map(|b| b.into()),
-            excess_blob_gas: block_builder.input.excess_blob_gas.map(|b| b.into()),
+            excess_blob_gas: excess_blob_gas.map(|b| b.into()),
             parent_beacon_block_root: block_builder.input.parent_beacon_block_root,
             // do not fill the remaining fields
             ..Default::default()
@@ -73,3 +96,22 @@ impl HeaderPrepStrategy for TaikoHeaderPrepStrategy {
         Ok(block_builder)
     }
 }
+
+/// Calculates the excess blob gas of a block from its parent header.
+/// A parent without blob gas fields (the last block before Cancun) counts as zero.
+fn calc_excess_blob_gas(parent_header: &AlloyConsensusHeader) -> Result<u64> {
+    let parent_excess_blob_gas = parent_header
+        .excess_blob_gas
+        .unwrap_or_default()
+        .try_into()
+        .with_context(|| "Invalid parent excess blob gas")?;
+    let parent_blob_gas_used = parent_header
+        .blob_gas_used
+        .unwrap_or_default()
+        .try_into()
+        .with_context(|| "Invalid parent blob gas used")?;
+    Ok(calculate_excess_blob_gas(
+        parent_excess_blob_gas,
+        parent_blob_gas_used,
+    ))
+}
@@ -0,0 +1,448 @@
+use core::str::FromStr;
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use revm::{
+    inspectors::TracerEip3155,
+    interpreter::{
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, Interpreter,
+        InterpreterResult,
+    },
+    primitives::{CreateScheme, Log},
+    Database, EvmContext, Inspector,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The format of the collected traces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStyle {
+    /// The EIP-3155 JSON object of every executed opcode.
+    #[default]
+    Eip3155,
+    /// The tree of calls made by the transaction, like the `callTracer` of geth.
+    CallTracer,
+}
+
+/// Selects the transactions to trace while executing a block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraceConfig {
+    pub style: TraceStyle,
+    /// The indices of the transactions in the block to trace, all transactions if empty.
+    pub tx_indices: Vec<usize>,
+}
+
+impl TraceConfig {
+    /// Returns whether the transaction at `tx_index` in the block is traced.
+    pub fn traces(&self, tx_index: usize) -> bool {
+        self.tx_indices.is_empty() || self.tx_indices.contains(&tx_index)
+    }
+}
+
+impl FromStr for TraceConfig {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+/// The trace of a transaction included in the block.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TxTrace {
+    /// The index of the transaction in the block.
+    pub tx_index: usize,
+    pub tx_hash: B256,
+    /// A list of EIP-3155 steps or the root [CallFrame], depending on the [TraceStyle].
+    pub trace: Value,
+}
+
+/// A call made during the execution of a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub from: Address,
+    /// The called account, or the created contract if the creation succeeded.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas: u64,
+    pub gas_used: u64,
+    pub input: Bytes,
+    pub output: Bytes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+/// Collects the calls of a transaction into a tree of [CallFrame]s.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    fn enter(&mut self, frame: CallFrame) {
+        self.stack.push(frame);
+    }
+
+    fn exit(
+        &mut self,
+        gas_remaining: u64,
+        output: Bytes,
+        error: Option<String>,
+        created: Option<Address>,
+    ) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        frame.gas_used = frame.gas.saturating_sub(gas_remaining);
+        frame.output = output;
+        frame.error = error;
+        if created.is_some() {
+            frame.to = created;
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+
+    fn exit_with(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let error = (!result.result.is_ok()).then(|| format!("{:?}", result.result));
+        self.exit(
+            result.gas.remaining(),
+            result.output.clone(),
+            error,
+            created,
+        );
+    }
+
+    /// Returns the root call of the traced transaction.
+    pub fn take(&mut self) -> Option<CallFrame> {
+        self.stack.clear();
+        self.root.take()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let typ = match inputs.context.scheme {
+            CallScheme::Call => "CALL",
+            CallScheme::CallCode => "CALLCODE",
+            CallScheme::DelegateCall => "DELEGATECALL",
+            CallScheme::StaticCall => "STATICCALL",
+        };
+        self.enter(CallFrame {
+            typ: typ.to_owned(),
+            from: inputs.context.caller,
+            to: Some(inputs.context.address),
+            value: inputs.transfer.value,
+            gas: inputs.gas_limit,
+            input: inputs.input.clone(),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.exit_with(&outcome.result, None);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let typ = match inputs.scheme {
+            CreateScheme::Create => "CREATE",
+            CreateScheme::Create2 { .. } => "CREATE2",
+        };
+        self.enter(CallFrame {
+            typ: typ.to_owned(),
+            from: inputs.caller,
+            value: inputs.value,
+            gas: inputs.gas_limit,
+            input: inputs.init_code.clone(),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.exit_with(&outcome.result, outcome.address);
+        outcome
+    }
+}
+
+/// A writer collecting the output of [TracerEip3155] in memory.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum ActiveTracer {
+    Eip3155 {
+        tracer: TracerEip3155,
+        buffer: SharedBuffer,
+    },
+    Call(CallTracer),
+}
+
+/// The inspector used while executing the transactions of a block, tracing only the
+/// transactions selected by the [TraceConfig].
+#[derive(Default)]
+pub struct TxTracer {
+    config: Option<TraceConfig>,
+    active: Option<ActiveTracer>,
+}
+
+impl TxTracer {
+    pub fn new(config: Option<TraceConfig>) -> Self {
+        Self {
+            config,
+            active: None,
+        }
+    }
+
+    /// Starts tracing the next transaction if it is selected. `tx_index` is the index the
+    /// transaction gets in the block when it is included.
+    pub fn start_tx(&mut self, tx_index: usize) {
+        self.active = self
+            .config
+            .as_ref()
+            .filter(|config| config.traces(tx_index))
+            .map(|config| match config.style {
+                TraceStyle::Eip3155 => {
+                    let buffer = SharedBuffer::default();
+                    ActiveTracer::Eip3155 {
+                        tracer: TracerEip3155::new(Box::new(buffer.clone())),
+                        buffer,
+                    }
+                }
+                TraceStyle::CallTracer => ActiveTracer::Call(CallTracer::default()),
+            });
+    }
+
+    /// Returns the trace of the transaction started last, once it is included in the block.
+    pub fn finish_tx(
+        &mut self,
+        tx_index: usize,
+        tx_hash: B256,
+        gas_limit: u64,
+        gas_used: u64,
+    ) -> Option<TxTrace> {
+        let trace = match self.active.take()? {
+            ActiveTracer::Eip3155 { buffer, .. } => {
+                let output = buffer.0.lock().unwrap();
+                let steps = String::from_utf8_lossy(&output)
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect();
+                Value::Array(steps)
+            }
+            ActiveTracer::Call(mut tracer) => {
+                let mut root = tracer.take()?;
+                // Report the gas of the transaction, like geth, instead of the gas left
+                // after the intrinsic gas
+                root.gas = gas_limit;
+                root.gas_used = gas_used;
+                serde_json::to_value(root).ok()?
+            }
+        };
+        Some(TxTrace {
+            tx_index,
+            tx_hash,
+            trace,
+        })
+    }
+}
+
+impl<DB: Database> Inspector<DB> for TxTracer {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.initialize_interp(interp, context),
+            Some(ActiveTracer::Call(tracer)) => tracer.initialize_interp(interp, context),
+            None => {}
+        }
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.step(interp, context),
+            Some(ActiveTracer::Call(tracer)) => tracer.step(interp, context),
+            None => {}
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.step_end(interp, context),
+            Some(ActiveTracer::Call(tracer)) => tracer.step_end(interp, context),
+            None => {}
+        }
+    }
+
+    fn log(&mut self, context: &mut EvmContext<DB>, log: &Log) {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.log(context, log),
+            Some(ActiveTracer::Call(tracer)) => tracer.log(context, log),
+            None => {}
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.call(context, inputs),
+            Some(ActiveTracer::Call(tracer)) => tracer.call(context, inputs),
+            None => None,
+        }
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.call_end(context, inputs, outcome),
+            Some(ActiveTracer::Call(tracer)) => tracer.call_end(context, inputs, outcome),
+            None => outcome,
+        }
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => tracer.create(context, inputs),
+            Some(ActiveTracer::Call(tracer)) => tracer.create(context, inputs),
+            None => None,
+        }
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => {
+                tracer.create_end(context, inputs, outcome)
+            }
+            Some(ActiveTracer::Call(tracer)) => tracer.create_end(context, inputs, outcome),
+            None => outcome,
+        }
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        match &mut self.active {
+            Some(ActiveTracer::Eip3155 { tracer, .. }) => {
+                Inspector::<DB>::selfdestruct(tracer, contract, target, value)
+            }
+            Some(ActiveTracer::Call(tracer)) => {
+                Inspector::<DB>::selfdestruct(tracer, contract, target, value)
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_config() {
+        let config: TraceConfig =
+            r#"{"style": "call_tracer", "tx_indices": [1, 3]}"#.parse().unwrap();
+        assert_eq!(config.style, TraceStyle::CallTracer);
+        assert!(!config.traces(0));
+        assert!(config.traces(1));
+        assert!(config.traces(3));
+
+        // Everything is traced by default
+        let config: TraceConfig = "{}".parse().unwrap();
+        assert_eq!(config.style, TraceStyle::Eip3155);
+        assert!(config.traces(0));
+        assert!(config.traces(42));
+    }
+
+    #[test]
+    fn test_call_tracer() {
+        let mut tracer = CallTracer::default();
+        tracer.enter(CallFrame {
+            typ: "CALL".to_owned(),
+            gas: 100_000,
+            ..Default::default()
+        });
+        tracer.enter(CallFrame {
+            typ: "STATICCALL".to_owned(),
+            gas: 50_000,
+            ..Default::default()
+        });
+        tracer.exit(40_000, Bytes::from_static(&[1]), None, None);
+        tracer.enter(CallFrame {
+            typ: "CREATE".to_owned(),
+            gas: 30_000,
+            ..Default::default()
+        });
+        tracer.exit(
+            0,
+            Bytes::new(),
+            Some("OutOfGas".to_owned()),
+            Some(Address::with_last_byte(1)),
+        );
+        tracer.exit(20_000, Bytes::new(), None, None);
+
+        let root = tracer.take().unwrap();
+        assert_eq!(root.gas_used, 80_000);
+        assert_eq!(root.calls.len(), 2);
+        assert_eq!(root.calls[0].typ, "STATICCALL");
+        assert_eq!(root.calls[0].gas_used, 10_000);
+        assert_eq!(root.calls[0].output, Bytes::from_static(&[1]));
+        assert_eq!(root.calls[1].to, Some(Address::with_last_byte(1)));
+        assert_eq!(root.calls[1].error.as_deref(), Some("OutOfGas"));
+        assert!(tracer.take().is_none());
+    }
+}
@@ -117,6 +117,25 @@ impl ForkCondition {
     }
 }
 
+/// The versions of the Taiko protocol, each with its own `BlockProposed` event and block
+/// metadata layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ProtocolVersion {
+    V1,
+    V2,
+}
+
+/// The block builder strategies the blocks of a chain can be built with, see
+/// [crate::builder::ChainStrategy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockBuilderKind {
+    /// Ethereum blocks, see [crate::builder::EthereumStrategy].
+    Ethereum,
+    /// Taiko blocks starting with the anchor transaction, see [crate::builder::TaikoStrategy].
+    Taiko,
+}
+
 /// [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559) parameters.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Eip1559Constants {
@@ -147,6 +166,29 @@ pub enum VerifierType {
     RISC0,
 }
 
+/// The tier IDs of the Taiko protocol, as defined in `LibTiers.sol`.
+pub mod tiers {
+    pub const TIER_OPTIMISTIC: u16 = 100;
+    pub const TIER_SGX: u16 = 200;
+    pub const TIER_ZKVM_RISC0: u16 = 250;
+    pub const TIER_ZKVM_SP1: u16 = 251;
+    pub const TIER_GUARDIAN_MINORITY: u16 = 900;
+    pub const TIER_GUARDIAN: u16 = 1000;
+}
+
+impl VerifierType {
+    /// The tier proofs of this verifier are submitted with. Blocks without a proof are
+    /// submitted optimistically.
+    pub fn tier(&self) -> u16 {
+        match self {
+            VerifierType::None => tiers::TIER_OPTIMISTIC,
+            VerifierType::SGX => tiers::TIER_SGX,
+            VerifierType::SP1 => tiers::TIER_ZKVM_SP1,
+            VerifierType::RISC0 => tiers::TIER_ZKVM_RISC0,
+        }
+    }
+}
+
 /// Specification of a specific chain.
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct ChainSpec {
@@ -154,6 +196,9 @@ pub struct ChainSpec {
     pub chain_id: ChainId,
     pub max_spec_id: SpecId,
     pub hard_forks: BTreeMap<SpecId, ForkCondition>,
+    /// The Taiko protocol upgrades, blocks before the first upgrade use [ProtocolVersion::V1].
+    #[serde(default)]
+    pub protocol_forks: BTreeMap<ProtocolVersion, ForkCondition>,
     pub eip_1559_constants: Eip1559Constants,
     pub l1_contract: Option<Address>,
     pub l2_contract: Option<Address>,
@@ -163,6 +208,9 @@ pub struct ChainSpec {
     pub genesis_time: u64,
     pub seconds_per_slot: u64,
     pub is_taiko: bool,
+    /// The strategy the blocks are built with, derived from `is_taiko` if not set.
+    #[serde(default)]
+    pub block_builder: Option<BlockBuilderKind>,
 }
 
 impl ChainSpec {
@@ -179,6 +227,7 @@ impl ChainSpec {
             chain_id,
             max_spec_id: spec_id,
             hard_forks: BTreeMap::from([(spec_id, ForkCondition::Block(0))]),
+            protocol_forks: BTreeMap::new(),
             eip_1559_constants,
             l1_contract: None,
             l2_contract: None,
@@ -188,6 +237,7 @@ impl ChainSpec {
             genesis_time: 0u64,
             seconds_per_slot: 1u64,
             is_taiko,
+            block_builder: None,
         }
     }
 
@@ -228,6 +278,28 @@ impl ChainSpec {
         self.is_taiko
     }
 
+    /// Returns the strategy the blocks of the chain are built with.
+    pub fn block_builder(&self) -> BlockBuilderKind {
+        match self.block_builder {
+            Some(kind) => kind,
+            None if self.is_taiko => BlockBuilderKind::Taiko,
+            None => BlockBuilderKind::Ethereum,
+        }
+    }
+
+    /// Returns the Taiko protocol version active for a given block number and timestamp.
+    pub fn active_protocol_version(
+        &self,
+        block_no: BlockNumber,
+        timestamp: u64,
+    ) -> ProtocolVersion {
+        self.protocol_forks
+            .iter()
+            .rev()
+            .find(|(_, condition)| condition.active(block_no, timestamp))
+            .map_or(ProtocolVersion::V1, |(version, _)| *version)
+    }
+
     pub fn network(&self) -> String {
         self.name.clone()
     }
@@ -259,6 +331,48 @@ impl ToString for Network {
 mod tests {
     use super::*;
 
+    #[test]
+    fn active_protocol_version() {
+        let mut spec = ChainSpec::default();
+        assert_eq!(spec.active_protocol_version(100, 0), ProtocolVersion::V1);
+
+        spec.protocol_forks = BTreeMap::from([(ProtocolVersion::V2, ForkCondition::Block(100))]);
+        assert_eq!(spec.active_protocol_version(99, 0), ProtocolVersion::V1);
+        assert_eq!(spec.active_protocol_version(100, 0), ProtocolVersion::V2);
+
+        spec.protocol_forks = BTreeMap::from([(ProtocolVersion::V2, ForkCondition::TBD)]);
+        assert_eq!(
+            spec.active_protocol_version(u64::MAX, 0),
+            ProtocolVersion::V1
+        );
+    }
+
+    #[test]
+    fn ontake_protocol_fork() {
+        let specs = SupportedChainSpecs::default();
+        for (network, ontake_block) in [("taiko_a7", 840_512), ("taiko_mainnet", 538_304)] {
+            let spec = specs.get_chain_spec(network).unwrap();
+            assert_eq!(
+                spec.active_protocol_version(ontake_block - 1, 0),
+                ProtocolVersion::V1
+            );
+            assert_eq!(
+                spec.active_protocol_version(ontake_block, 0),
+                ProtocolVersion::V2
+            );
+        }
+    }
+
+    #[test]
+    fn block_builder() {
+        let mut spec = ChainSpec::default();
+        assert_eq!(spec.block_builder(), BlockBuilderKind::Ethereum);
+        spec.is_taiko = true;
+        assert_eq!(spec.block_builder(), BlockBuilderKind::Taiko);
+        spec.block_builder = Some(BlockBuilderKind::Ethereum);
+        assert_eq!(spec.block_builder(), BlockBuilderKind::Ethereum);
+    }
+
     #[test]
     fn revm_spec_id() {
         let eth_mainnet_spec = SupportedChainSpecs::default()
@@ -286,6 +400,7 @@ mod tests {
                 (SpecId::SHANGHAI, ForkCondition::Block(17034870)),
                 (SpecId::CANCUN, ForkCondition::Timestamp(1710338135)),
             ]),
+            protocol_forks: BTreeMap::new(),
             eip_1559_constants: Eip1559Constants {
                 base_fee_change_denominator: uint!(8_U256),
                 base_fee_max_increase_denominator: uint!(8_U256),
@@ -304,6 +419,7 @@ mod tests {
             genesis_time: 0u64,
             seconds_per_slot: 1u64,
             is_taiko: false,
+            block_builder: Some(BlockBuilderKind::Ethereum),
         };
 
         let json = serde_json::to_string(&spec).unwrap();
@@ -15,9 +15,10 @@ use core::fmt::Debug;
 #[cfg(feature = "std")]
 use std::path::PathBuf;
 
-use alloy_consensus::Header as AlloyConsensusHeader;
+use alloy_consensus::{Header as AlloyConsensusHeader, TxEnvelope};
+use alloy_primitives::Log;
 use alloy_rpc_types::Withdrawal as AlloyWithdrawal;
-use alloy_sol_types::{sol, SolCall};
+use alloy_sol_types::{sol, SolCall, SolEvent};
 use anyhow::{anyhow, Result};
 use raiko_primitives::{mpt::MptNode, Address, Bytes, B256, U256};
 use revm::primitives::HashMap;
@@ -26,7 +27,12 @@ use serde_with::serde_as;
 
 #[cfg(not(feature = "std"))]
 use crate::no_std::*;
-use crate::{consts::ChainSpec, serde_with::RlpBytes, serde_with::RlpHexBytes};
+use crate::{
+    consts::{ChainSpec, ProtocolVersion},
+    protocol_instance::batch_instance_hash,
+    serde_with::RlpBytes,
+    serde_with::RlpHexBytes,
+};
 
 /// Represents the state of an account's storage.
 /// The storage trie together with the used storage slots allow us to reconstruct all the
@@ -67,7 +73,8 @@ pub struct GuestInput {
     pub parent_storage: HashMap<Address, StorageEntry>,
     /// The code of all unique contracts.
     pub contracts: Vec<Bytes>,
-    /// List of at most 256 previous block headers
+    /// The previous block headers down to the oldest block whose hash is read with
+    /// `BLOCKHASH`, at most 256
     #[serde_as(as = "Vec<RlpBytes>")]
     pub ancestor_headers: Vec<AlloyConsensusHeader>,
     /// Base fee per gas
@@ -77,6 +84,10 @@ pub struct GuestInput {
     pub excess_blob_gas: Option<u64>,
     pub parent_beacon_block_root: Option<B256>,
 
+    /// The transactions of an Ethereum block. Taiko blocks generate theirs from the tx list.
+    #[serde_as(as = "Vec<RlpBytes>")]
+    pub transactions: Vec<TxEnvelope>,
+
     /// Taiko specific data
     pub taiko: TaikoGuestInput,
 }
@@ -89,7 +100,7 @@ pub struct TaikoGuestInput {
     pub l1_header: AlloyConsensusHeader,
     pub tx_data: Vec<u8>,
     pub anchor_tx: String,
-    pub block_proposed: BlockProposed,
+    pub block_proposed: BlockProposedFork,
     pub prover_data: TaikoProverData,
     pub tx_blob_hash: Option<B256>,
     pub skip_verify_blob: bool,
@@ -112,6 +123,65 @@ pub enum GuestOutput {
     Failure,
 }
 
+/// Input for proving a range of consecutive blocks in a single proof.
+///
+/// Only the first input carries the state witness, which covers the state accessed by all blocks
+/// in the batch. The state of every following block is the final state of the block before it,
+/// so the witness fields of those inputs are left empty.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BatchGuestInput {
+    pub inputs: Vec<GuestInput>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchGuestOutput {
+    Success {
+        #[serde_as(as = "Vec<RlpHexBytes>")]
+        headers: Vec<AlloyConsensusHeader>,
+        /// Hash chain of the instance hashes of all blocks in the batch.
+        hash: B256,
+    },
+    Failure,
+}
+
+/// Input for aggregating the proofs of multiple blocks into a single proof.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ZkAggregationGuestInput {
+    /// The image id of the guest program that proved the blocks.
+    pub image_id: [u32; 8],
+    /// The outputs of the block proofs, in block order.
+    pub block_outputs: Vec<GuestOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregationGuestOutput {
+    /// The image id of the guest program the aggregated proofs were verified against. The
+    /// aggregation guest takes it from its input, so verifiers have to check that it is the
+    /// image id of the block guest.
+    pub image_id: [u32; 8],
+    /// Hash chain of the instance hashes of all aggregated blocks.
+    pub hash: B256,
+}
+
+impl AggregationGuestOutput {
+    /// Aggregates the outputs of the block proofs. Only successfully built blocks can be
+    /// aggregated.
+    pub fn new(image_id: [u32; 8], block_outputs: &[GuestOutput]) -> Result<Self> {
+        let instance_hashes = block_outputs
+            .iter()
+            .map(|output| match output {
+                GuestOutput::Success { hash, .. } => Ok(*hash),
+                GuestOutput::Failure => Err(anyhow!("cannot aggregate a failed block proof")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            image_id,
+            hash: batch_instance_hash(&instance_hashes),
+        })
+    }
+}
+
 sol! {
     function anchor(
         bytes32 l1Hash,
@@ -123,10 +193,54 @@ sol! {
     {}
 }
 
-#[inline]
-pub fn decode_anchor(bytes: &[u8]) -> Result<anchorCall> {
-    anchorCall::abi_decode(bytes, true).map_err(|e| anyhow!(e))
-    // .context("Invalid anchor call")
+/// The anchor call of the protocol version the block was proposed with.
+#[derive(Debug)]
+pub enum AnchorCallFork {
+    V1(anchorCall),
+    V2(anchorV2Call),
+}
+
+impl AnchorCallFork {
+    pub fn version(&self) -> ProtocolVersion {
+        match self {
+            AnchorCallFork::V1(_) => ProtocolVersion::V1,
+            AnchorCallFork::V2(_) => ProtocolVersion::V2,
+        }
+    }
+
+    /// The number of the L1 block the state of is made available on L2.
+    pub fn l1_block_id(&self) -> u64 {
+        match self {
+            AnchorCallFork::V1(call) => call.l1BlockId,
+            AnchorCallFork::V2(call) => call._anchorBlockId,
+        }
+    }
+
+    pub fn l1_state_root(&self) -> B256 {
+        match self {
+            AnchorCallFork::V1(call) => call.l1StateRoot,
+            AnchorCallFork::V2(call) => call._anchorStateRoot,
+        }
+    }
+
+    pub fn parent_gas_used(&self) -> u32 {
+        match self {
+            AnchorCallFork::V1(call) => call.parentGasUsed,
+            AnchorCallFork::V2(call) => call._parentGasUsed,
+        }
+    }
+}
+
+/// Decodes the anchor call, `anchor` or `anchorV2` depending on its selector.
+pub fn decode_anchor(bytes: &[u8]) -> Result<AnchorCallFork> {
+    if bytes.starts_with(&anchorV2Call::SELECTOR) {
+        return Ok(AnchorCallFork::V2(
+            anchorV2Call::abi_decode(bytes, true).map_err(|e| anyhow!(e))?,
+        ));
+    }
+    Ok(AnchorCallFork::V1(
+        anchorCall::abi_decode(bytes, true).map_err(|e| anyhow!(e))?,
+    ))
 }
 
 sol! {
@@ -193,6 +307,22 @@ sol! {
         bytes data;
     }
 
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct TransitionState {
+        bytes32 key;
+        bytes32 blockHash;
+        bytes32 stateRoot;
+        address prover;
+        uint96 validityBond;
+        address contester;
+        uint96 contestBond;
+        uint64 timestamp;
+        uint16 tier;
+        uint8 __reserved1;
+    }
+
+    function getTransition(uint64 blockId, bytes32 parentHash) returns (TransitionState) {}
+
     #[derive(Debug)]
     function proposeBlock(
         bytes calldata params,
@@ -203,6 +333,162 @@ sol! {
     function proveBlock(uint64 blockId, bytes calldata input) {}
 }
 
+sol! {
+    #[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+    struct BaseFeeConfig {
+        uint8 adjustmentQuotient;
+        uint8 sharingPctg;
+        uint32 gasIssuancePerSecond;
+        uint64 minGasExcess;
+        uint32 maxGasIssuancePerBlock;
+    }
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct BlockMetadataV2 {
+        bytes32 anchorBlockHash; // `l1Hash` in the anchor tx.
+        bytes32 difficulty;
+        bytes32 blobHash;
+        bytes32 extraData;
+        address coinbase;
+        uint64 id;
+        uint32 gasLimit;
+        uint64 timestamp;
+        uint64 anchorBlockId; // `l1BlockId` in the anchor tx.
+        uint16 minTier;
+        bool blobUsed;
+        bytes32 parentMetaHash;
+        address proposer;
+        uint96 livenessBond;
+        uint64 proposedAt;
+        uint64 proposedIn;
+        uint32 blobTxListOffset;
+        uint32 blobTxListLength;
+        uint8 blobIndex;
+        BaseFeeConfig baseFeeConfig;
+    }
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    event BlockProposedV2(uint256 indexed blockId, BlockMetadataV2 meta);
+
+    function anchorV2(
+        uint64 _anchorBlockId,
+        bytes32 _anchorStateRoot,
+        uint32 _parentGasUsed,
+        BaseFeeConfig calldata _baseFeeConfig
+    )
+        external
+    {}
+
+    #[derive(Debug)]
+    function proposeBlockV2(
+        bytes calldata params,
+        bytes calldata txList
+    )
+    {}
+}
+
+/// The `BlockProposed` event of the protocol version the block was proposed with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum BlockProposedFork {
+    V1(BlockProposed),
+    V2(BlockProposedV2),
+}
+
+impl Default for BlockProposedFork {
+    fn default() -> Self {
+        BlockProposedFork::V1(BlockProposed::default())
+    }
+}
+
+impl BlockProposedFork {
+    /// The signature hash of the event emitted by the given protocol version.
+    pub fn signature_hash(version: ProtocolVersion) -> B256 {
+        match version {
+            ProtocolVersion::V1 => BlockProposed::SIGNATURE_HASH,
+            ProtocolVersion::V2 => BlockProposedV2::SIGNATURE_HASH,
+        }
+    }
+
+    /// Decodes the event emitted by the given protocol version.
+    pub fn decode_log(version: ProtocolVersion, log: &Log) -> Result<Self> {
+        Ok(match version {
+            ProtocolVersion::V1 => BlockProposedFork::V1(
+                BlockProposed::decode_log(log, false)
+                    .map_err(|e| anyhow!(e))?
+                    .data,
+            ),
+            ProtocolVersion::V2 => BlockProposedFork::V2(
+                BlockProposedV2::decode_log(log, false)
+                    .map_err(|e| anyhow!(e))?
+                    .data,
+            ),
+        })
+    }
+
+    /// Decodes the tx list from the calldata of the propose transaction.
+    pub fn decode_tx_list(&self, calldata: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            BlockProposedFork::V1(_) => proposeBlockCall::abi_decode(calldata, false)
+                .map_err(|e| anyhow!(e))?
+                .txList
+                .to_vec(),
+            BlockProposedFork::V2(_) => proposeBlockV2Call::abi_decode(calldata, false)
+                .map_err(|e| anyhow!(e))?
+                .txList
+                .to_vec(),
+        })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        match self {
+            BlockProposedFork::V1(_) => ProtocolVersion::V1,
+            BlockProposedFork::V2(_) => ProtocolVersion::V2,
+        }
+    }
+
+    pub fn block_id(&self) -> U256 {
+        match self {
+            BlockProposedFork::V1(event) => event.blockId,
+            BlockProposedFork::V2(event) => event.blockId,
+        }
+    }
+
+    pub fn blob_used(&self) -> bool {
+        match self {
+            BlockProposedFork::V1(event) => event.meta.blobUsed,
+            BlockProposedFork::V2(event) => event.meta.blobUsed,
+        }
+    }
+
+    /// The index of the blob in the propose transaction that contains the tx list.
+    pub fn blob_index(&self) -> usize {
+        match self {
+            // The first blob is always used
+            BlockProposedFork::V1(_) => 0,
+            BlockProposedFork::V2(event) => event.meta.blobIndex as usize,
+        }
+    }
+
+    /// The base fee config the anchor transaction of a V2 block has to be called with.
+    pub fn base_fee_config(&self) -> Option<&BaseFeeConfig> {
+        match self {
+            BlockProposedFork::V1(_) => None,
+            BlockProposedFork::V2(event) => Some(&event.meta.baseFeeConfig),
+        }
+    }
+
+    /// The offset and length of the tx list inside the blob, if only part of the blob is used.
+    pub fn blob_tx_slice_param(&self) -> Option<(usize, usize)> {
+        match self {
+            BlockProposedFork::V1(_) => None,
+            BlockProposedFork::V2(event) => Some((
+                event.meta.blobTxListOffset as usize,
+                event.meta.blobTxListLength as usize,
+            )),
+        }
+    }
+}
+
 #[cfg(feature = "std")]
 use std::path::Path;
 
@@ -221,4 +507,56 @@ mod tests {
         let input = GuestInput::default();
         let _: GuestInput = bincode::deserialize(&bincode::serialize(&input).unwrap()).unwrap();
     }
+
+    #[test]
+    fn aggregation_output() {
+        let success = |hash| GuestOutput::Success {
+            header: AlloyConsensusHeader::default(),
+            hash,
+        };
+        let hashes = [B256::with_last_byte(1), B256::with_last_byte(2)];
+        let output =
+            AggregationGuestOutput::new([1; 8], &[success(hashes[0]), success(hashes[1])]).unwrap();
+        assert_eq!(output.image_id, [1; 8]);
+        assert_eq!(output.hash, batch_instance_hash(&hashes));
+        assert!(
+            AggregationGuestOutput::new([1; 8], &[success(hashes[0]), GuestOutput::Failure])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn decode_anchor_fork() {
+        let v1 = anchorCall {
+            l1Hash: B256::with_last_byte(1),
+            l1StateRoot: B256::with_last_byte(2),
+            l1BlockId: 3,
+            parentGasUsed: 4,
+        };
+        let call = decode_anchor(&v1.abi_encode()).unwrap();
+        assert_eq!(call.version(), ProtocolVersion::V1);
+        assert_eq!(call.l1_block_id(), 3);
+
+        let v2 = anchorV2Call {
+            _anchorBlockId: 5,
+            _anchorStateRoot: B256::with_last_byte(6),
+            _parentGasUsed: 7,
+            _baseFeeConfig: BaseFeeConfig {
+                adjustmentQuotient: 8,
+                sharingPctg: 75,
+                gasIssuancePerSecond: 5_000_000,
+                minGasExcess: 1_340_000_000,
+                maxGasIssuancePerBlock: 600_000_000,
+            },
+        };
+        let call = decode_anchor(&v2.abi_encode()).unwrap();
+        assert_eq!(call.version(), ProtocolVersion::V2);
+        assert_eq!(call.l1_block_id(), 5);
+        assert_eq!(call.l1_state_root(), B256::with_last_byte(6));
+        assert_eq!(call.parent_gas_used(), 7);
+        let AnchorCallFork::V2(decoded) = call else {
+            unreachable!()
+        };
+        assert_eq!(decoded._baseFeeConfig, v2._baseFeeConfig);
+    }
 }
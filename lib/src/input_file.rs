@@ -0,0 +1,662 @@
+//! The versioned format the inputs are stored in.
+//!
+//! An input file starts with a fixed header that describes the input, followed by the
+//! bincode encoded [GuestInput], compressed with zlib or uncompressed:
+//!
+//! | field          | size | description                                   |
+//! |----------------|------|-----------------------------------------------|
+//! | magic          | 4    | [MAGIC]                                       |
+//! | version        | 2    | the schema version of the payload, LE         |
+//! | chain id       | 8    | the chain of the block, LE                    |
+//! | block number   | 8    | LE                                            |
+//! | compression    | 1    | 0: none, 1: zlib                              |
+//! | checksum       | 32   | the SHA-256 of the payload as stored          |
+//! | payload length | 8    | LE                                            |
+//!
+//! Inputs written before the format was introduced are the bare bincode encoded
+//! [GuestInput], they are read as version 0.
+//!
+//! | version | change                                                                  |
+//! |---------|-------------------------------------------------------------------------|
+//! | 0       | the schema before the format, without header                            |
+//! | 1       | [ChainSpec] has `protocol_forks`, [TaikoGuestInput] has a               |
+//! |         | [BlockProposedFork]                                                     |
+//! | 2       | [ChainSpec] has `block_builder`                                         |
+//! | 3       | [GuestInput] has the `transactions` of Ethereum blocks, which were      |
+//! |         | stored as the compressed tx list of [TaikoGuestInput] before            |
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+};
+
+use alloy_consensus::Header as AlloyConsensusHeader;
+use alloy_rpc_types::Withdrawal as AlloyWithdrawal;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+use raiko_primitives::{mpt::MptNode, Address, Bytes, ChainId, B256};
+use revm::primitives::{HashMap, SpecId};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_with::serde_as;
+use sha2::{Digest, Sha256};
+use thiserror::Error as ThisError;
+
+use crate::{
+    consts::{
+        BlockBuilderKind, ChainSpec, Eip1559Constants, ForkCondition, ProtocolVersion, VerifierType,
+    },
+    input::{
+        BlockProposed, BlockProposedFork, GuestInput, StorageEntry, TaikoGuestInput,
+        TaikoProverData,
+    },
+    serde_with::RlpBytes,
+    utils::decode_eth_transactions,
+};
+
+/// The first bytes of every input file.
+pub const MAGIC: [u8; 4] = *b"RKIN";
+
+/// The version of the [GuestInput] schema written by this version of raiko. Bump it on every
+/// change of [GuestInput] or of any type it contains, and add a migration of the previous
+/// version to [migrate].
+pub const CURRENT_VERSION: u16 = 3;
+
+/// The version of the inputs without a header.
+pub const LEGACY_VERSION: u16 = 0;
+
+const HEADER_SIZE: usize = 4 + 2 + 8 + 8 + 1 + 32 + 8;
+
+#[derive(ThisError, Debug)]
+pub enum InputFileError {
+    #[error("the input has schema version {found}, this version of raiko reads versions up to {supported}")]
+    UnsupportedVersion { found: u16, supported: u16 },
+    #[error("the input is for block {found_block} of chain {found_chain}, expected block {expected_block} of chain {expected_chain}")]
+    WrongBlock {
+        expected_chain: u64,
+        expected_block: u64,
+        found_chain: u64,
+        found_block: u64,
+    },
+    #[error("unknown compression {0}")]
+    UnknownCompression(u8),
+    #[error("the checksum of the input doesn't match, the file is corrupted")]
+    ChecksumMismatch,
+    #[error("the input is truncated")]
+    Truncated,
+    #[error("could not decode the input of schema version {version}: {message}")]
+    Decode { version: u16, message: String },
+    #[error("could not encode the input: {0}")]
+    Encode(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type InputFileResult<T> = Result<T, InputFileError>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    #[default]
+    Zlib,
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zlib => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> InputFileResult<Self> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Zlib),
+            _ => Err(InputFileError::UnknownCompression(byte)),
+        }
+    }
+}
+
+/// What an input file contains, read without decoding the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputFileHeader {
+    pub version: u16,
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub compression: Compression,
+    pub checksum: [u8; 32],
+}
+
+impl InputFileHeader {
+    /// Checks that the file is the input of the given block.
+    pub fn check_block(&self, chain_id: u64, block_number: u64) -> InputFileResult<()> {
+        if self.chain_id != chain_id || self.block_number != block_number {
+            return Err(InputFileError::WrongBlock {
+                expected_chain: chain_id,
+                expected_block: block_number,
+                found_chain: self.chain_id,
+                found_block: self.block_number,
+            });
+        }
+        Ok(())
+    }
+
+    fn read(bytes: &[u8]) -> InputFileResult<(Self, &[u8])> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(InputFileError::Truncated);
+        }
+        let (header, rest) = bytes.split_at(HEADER_SIZE);
+        let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+        let payload_len = u64_at(55) as usize;
+        let payload = rest.get(..payload_len).ok_or(InputFileError::Truncated)?;
+        let header = Self {
+            version: u16::from_le_bytes(header[4..6].try_into().unwrap()),
+            chain_id: u64_at(6),
+            block_number: u64_at(14),
+            compression: Compression::from_byte(header[22])?,
+            checksum: header[23..55].try_into().unwrap(),
+        };
+        if header.version > CURRENT_VERSION {
+            return Err(InputFileError::UnsupportedVersion {
+                found: header.version,
+                supported: CURRENT_VERSION,
+            });
+        }
+        Ok((header, payload))
+    }
+}
+
+/// Returns whether the bytes start with an input file header, and not a legacy input.
+pub fn is_input_file(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Encodes the input in the current version of the format.
+pub fn encode(input: &GuestInput, compression: Compression) -> InputFileResult<Vec<u8>> {
+    let encoded = bincode::serialize(input).map_err(|e| InputFileError::Encode(e.to_string()))?;
+    write(
+        CURRENT_VERSION,
+        input.chain_spec.chain_id,
+        input.block_number,
+        encoded,
+        compression,
+    )
+}
+
+/// Writes the header and the payload of the bincode encoded input.
+fn write(
+    version: u16,
+    chain_id: u64,
+    block_number: u64,
+    encoded: Vec<u8>,
+    compression: Compression,
+) -> InputFileResult<Vec<u8>> {
+    let payload = match compression {
+        Compression::None => encoded,
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&encoded)?;
+            encoder.finish()?
+        }
+    };
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&chain_id.to_le_bytes());
+    bytes.extend_from_slice(&block_number.to_le_bytes());
+    bytes.push(compression.to_byte());
+    bytes.extend_from_slice(&Sha256::digest(&payload));
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Reads the header of an input file. Legacy inputs have no header and return `None`.
+pub fn read_header(bytes: &[u8]) -> InputFileResult<Option<InputFileHeader>> {
+    if !is_input_file(bytes) {
+        return Ok(None);
+    }
+    let (header, _) = InputFileHeader::read(bytes)?;
+    Ok(Some(header))
+}
+
+/// Decodes an input file of any supported version, migrating it to the current schema, and
+/// returns the version it was stored in with it.
+pub fn decode(bytes: &[u8]) -> InputFileResult<(u16, GuestInput)> {
+    if !is_input_file(bytes) {
+        return Ok((LEGACY_VERSION, migrate(LEGACY_VERSION, bytes)?));
+    }
+    let (header, payload) = InputFileHeader::read(bytes)?;
+    if Sha256::digest(payload).as_slice() != header.checksum {
+        return Err(InputFileError::ChecksumMismatch);
+    }
+    let input = match header.compression {
+        Compression::None => migrate(header.version, payload)?,
+        Compression::Zlib => {
+            let mut decoded = Vec::new();
+            ZlibDecoder::new(payload).read_to_end(&mut decoded)?;
+            migrate(header.version, &decoded)?
+        }
+    };
+    Ok((header.version, input))
+}
+
+/// Decodes the bincode encoded input of the given schema version into the current schema.
+fn migrate(version: u16, encoded: &[u8]) -> InputFileResult<GuestInput> {
+    let input = match version {
+        LEGACY_VERSION => decode_legacy::<ChainSpecV0, TaikoGuestInputV0>(encoded),
+        1 => decode_legacy::<ChainSpecV1, TaikoGuestInput>(encoded),
+        2 => decode_legacy::<ChainSpec, TaikoGuestInput>(encoded),
+        CURRENT_VERSION => bincode::deserialize(encoded).map_err(anyhow::Error::from),
+        found => {
+            return Err(InputFileError::UnsupportedVersion {
+                found,
+                supported: CURRENT_VERSION,
+            })
+        }
+    };
+    input.map_err(|e| InputFileError::Decode {
+        version,
+        message: e.to_string(),
+    })
+}
+
+/// Decodes the bincode encoded input of an older schema version and migrates it.
+fn decode_legacy<C, T>(encoded: &[u8]) -> anyhow::Result<GuestInput>
+where
+    C: DeserializeOwned + Into<ChainSpec>,
+    T: DeserializeOwned + Into<TaikoGuestInput>,
+{
+    bincode::deserialize::<LegacyGuestInput<C, T>>(encoded)?.try_into()
+}
+
+/// The [GuestInput] of the inputs up to version 2, with the [ChainSpec] and the
+/// [TaikoGuestInput] of the version.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct LegacyGuestInput<C, T> {
+    chain_spec: C,
+    block_number: u64,
+    block_hash_reference: B256,
+    #[serde_as(as = "RlpBytes")]
+    block_header_reference: AlloyConsensusHeader,
+    #[serde_as(as = "RlpBytes")]
+    parent_header: AlloyConsensusHeader,
+    beneficiary: Address,
+    gas_limit: u64,
+    timestamp: u64,
+    extra_data: Bytes,
+    mix_hash: B256,
+    withdrawals: Vec<AlloyWithdrawal>,
+    parent_state_trie: MptNode,
+    parent_storage: HashMap<Address, StorageEntry>,
+    contracts: Vec<Bytes>,
+    #[serde_as(as = "Vec<RlpBytes>")]
+    ancestor_headers: Vec<AlloyConsensusHeader>,
+    base_fee_per_gas: u64,
+    blob_gas_used: Option<u64>,
+    excess_blob_gas: Option<u64>,
+    parent_beacon_block_root: Option<B256>,
+    taiko: T,
+}
+
+impl<C: Into<ChainSpec>, T: Into<TaikoGuestInput>> TryFrom<LegacyGuestInput<C, T>> for GuestInput {
+    type Error = anyhow::Error;
+
+    fn try_from(input: LegacyGuestInput<C, T>) -> anyhow::Result<Self> {
+        let chain_spec: ChainSpec = input.chain_spec.into();
+        let mut taiko: TaikoGuestInput = input.taiko.into();
+        // The transactions of Ethereum blocks were stored as their tx list
+        let transactions = match chain_spec.block_builder() {
+            BlockBuilderKind::Ethereum => {
+                decode_eth_transactions(&core::mem::take(&mut taiko.tx_data))?
+            }
+            BlockBuilderKind::Taiko => Vec::new(),
+        };
+        Ok(GuestInput {
+            chain_spec,
+            block_number: input.block_number,
+            block_hash_reference: input.block_hash_reference,
+            block_header_reference: input.block_header_reference,
+            parent_header: input.parent_header,
+            beneficiary: input.beneficiary,
+            gas_limit: input.gas_limit,
+            timestamp: input.timestamp,
+            extra_data: input.extra_data,
+            mix_hash: input.mix_hash,
+            withdrawals: input.withdrawals,
+            parent_state_trie: input.parent_state_trie,
+            parent_storage: input.parent_storage,
+            contracts: input.contracts,
+            ancestor_headers: input.ancestor_headers,
+            base_fee_per_gas: input.base_fee_per_gas,
+            blob_gas_used: input.blob_gas_used,
+            excess_blob_gas: input.excess_blob_gas,
+            parent_beacon_block_root: input.parent_beacon_block_root,
+            transactions,
+            taiko,
+        })
+    }
+}
+
+/// The [ChainSpec] of the legacy inputs.
+#[derive(Serialize, Deserialize)]
+struct ChainSpecV0 {
+    name: String,
+    chain_id: ChainId,
+    max_spec_id: SpecId,
+    hard_forks: BTreeMap<SpecId, ForkCondition>,
+    eip_1559_constants: Eip1559Constants,
+    l1_contract: Option<Address>,
+    l2_contract: Option<Address>,
+    rpc: String,
+    beacon_rpc: Option<String>,
+    verifier_address: BTreeMap<VerifierType, Option<Address>>,
+    genesis_time: u64,
+    seconds_per_slot: u64,
+    is_taiko: bool,
+}
+
+impl From<ChainSpecV0> for ChainSpec {
+    fn from(spec: ChainSpecV0) -> Self {
+        ChainSpec {
+            name: spec.name,
+            chain_id: spec.chain_id,
+            max_spec_id: spec.max_spec_id,
+            hard_forks: spec.hard_forks,
+            // The legacy inputs are all of blocks proposed before the first protocol upgrade
+            protocol_forks: BTreeMap::new(),
+            eip_1559_constants: spec.eip_1559_constants,
+            l1_contract: spec.l1_contract,
+            l2_contract: spec.l2_contract,
+            rpc: spec.rpc,
+            beacon_rpc: spec.beacon_rpc,
+            verifier_address: spec.verifier_address,
+            genesis_time: spec.genesis_time,
+            seconds_per_slot: spec.seconds_per_slot,
+            is_taiko: spec.is_taiko,
+            block_builder: None,
+        }
+    }
+}
+
+/// The [TaikoGuestInput] of the legacy inputs, which only supported the first protocol
+/// version.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct TaikoGuestInputV0 {
+    #[serde_as(as = "RlpBytes")]
+    l1_header: AlloyConsensusHeader,
+    tx_data: Vec<u8>,
+    anchor_tx: String,
+    block_proposed: BlockProposed,
+    prover_data: TaikoProverData,
+    tx_blob_hash: Option<B256>,
+    skip_verify_blob: bool,
+}
+
+impl From<TaikoGuestInputV0> for TaikoGuestInput {
+    fn from(taiko: TaikoGuestInputV0) -> Self {
+        TaikoGuestInput {
+            l1_header: taiko.l1_header,
+            tx_data: taiko.tx_data,
+            anchor_tx: taiko.anchor_tx,
+            block_proposed: BlockProposedFork::V1(taiko.block_proposed),
+            prover_data: taiko.prover_data,
+            tx_blob_hash: taiko.tx_blob_hash,
+            skip_verify_blob: taiko.skip_verify_blob,
+        }
+    }
+}
+
+/// The [ChainSpec] of the version 1 inputs.
+#[derive(Serialize, Deserialize)]
+struct ChainSpecV1 {
+    name: String,
+    chain_id: ChainId,
+    max_spec_id: SpecId,
+    hard_forks: BTreeMap<SpecId, ForkCondition>,
+    protocol_forks: BTreeMap<ProtocolVersion, ForkCondition>,
+    eip_1559_constants: Eip1559Constants,
+    l1_contract: Option<Address>,
+    l2_contract: Option<Address>,
+    rpc: String,
+    beacon_rpc: Option<String>,
+    verifier_address: BTreeMap<VerifierType, Option<Address>>,
+    genesis_time: u64,
+    seconds_per_slot: u64,
+    is_taiko: bool,
+}
+
+impl From<ChainSpecV1> for ChainSpec {
+    fn from(spec: ChainSpecV1) -> Self {
+        ChainSpec {
+            name: spec.name,
+            chain_id: spec.chain_id,
+            max_spec_id: spec.max_spec_id,
+            hard_forks: spec.hard_forks,
+            protocol_forks: spec.protocol_forks,
+            eip_1559_constants: spec.eip_1559_constants,
+            l1_contract: spec.l1_contract,
+            l2_contract: spec.l2_contract,
+            rpc: spec.rpc,
+            beacon_rpc: spec.beacon_rpc,
+            verifier_address: spec.verifier_address,
+            genesis_time: spec.genesis_time,
+            seconds_per_slot: spec.seconds_per_slot,
+            is_taiko: spec.is_taiko,
+            block_builder: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{SignableTransaction, TxEnvelope, TxLegacy};
+    use alloy_primitives::Signature;
+    use raiko_primitives::U256;
+
+    use super::*;
+    use crate::{consts::SupportedChainSpecs, utils::zlib_compress_data};
+
+    fn test_input() -> GuestInput {
+        let mut input = GuestInput {
+            block_number: 1234,
+            ..Default::default()
+        };
+        input.chain_spec.chain_id = 167009;
+        input
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let input = test_input();
+        for compression in [Compression::None, Compression::Zlib] {
+            let bytes = encode(&input, compression).unwrap();
+            let header = read_header(&bytes).unwrap().unwrap();
+            assert_eq!(header.version, CURRENT_VERSION);
+            assert_eq!(header.compression, compression);
+            header.check_block(167009, 1234).unwrap();
+            assert!(header.check_block(167009, 1235).is_err());
+
+            let (version, decoded) = decode(&bytes).unwrap();
+            assert_eq!(version, CURRENT_VERSION);
+            assert_eq!(decoded.block_number, input.block_number);
+        }
+    }
+
+    /// Replaces the chain spec and the Taiko data of the input with the ones of an older
+    /// schema.
+    fn legacy<C, T>(input: GuestInput, chain_spec: C, taiko: T) -> LegacyGuestInput<C, T> {
+        LegacyGuestInput {
+            chain_spec,
+            block_number: input.block_number,
+            block_hash_reference: input.block_hash_reference,
+            block_header_reference: input.block_header_reference,
+            parent_header: input.parent_header,
+            beneficiary: input.beneficiary,
+            gas_limit: input.gas_limit,
+            timestamp: input.timestamp,
+            extra_data: input.extra_data,
+            mix_hash: input.mix_hash,
+            withdrawals: input.withdrawals,
+            parent_state_trie: input.parent_state_trie,
+            parent_storage: input.parent_storage,
+            contracts: input.contracts,
+            ancestor_headers: input.ancestor_headers,
+            base_fee_per_gas: input.base_fee_per_gas,
+            blob_gas_used: input.blob_gas_used,
+            excess_blob_gas: input.excess_blob_gas,
+            parent_beacon_block_root: input.parent_beacon_block_root,
+            taiko,
+        }
+    }
+
+    /// Encodes the input the way the releases before the input file format stored it.
+    fn encode_v0(input: &GuestInput, block_proposed: BlockProposed) -> Vec<u8> {
+        let spec = input.chain_spec.clone();
+        let spec_v0 = ChainSpecV0 {
+            name: spec.name,
+            chain_id: spec.chain_id,
+            max_spec_id: spec.max_spec_id,
+            hard_forks: spec.hard_forks,
+            eip_1559_constants: spec.eip_1559_constants,
+            l1_contract: spec.l1_contract,
+            l2_contract: spec.l2_contract,
+            rpc: spec.rpc,
+            beacon_rpc: spec.beacon_rpc,
+            verifier_address: spec.verifier_address,
+            genesis_time: spec.genesis_time,
+            seconds_per_slot: spec.seconds_per_slot,
+            is_taiko: spec.is_taiko,
+        };
+        let taiko = input.taiko.clone();
+        let taiko_v0 = TaikoGuestInputV0 {
+            l1_header: taiko.l1_header,
+            tx_data: taiko.tx_data,
+            anchor_tx: taiko.anchor_tx,
+            block_proposed,
+            prover_data: taiko.prover_data,
+            tx_blob_hash: taiko.tx_blob_hash,
+            skip_verify_blob: taiko.skip_verify_blob,
+        };
+        bincode::serialize(&legacy(input.clone(), spec_v0, taiko_v0)).unwrap()
+    }
+
+    /// Encodes the input in the schema of version 1.
+    fn encode_v1(input: &GuestInput) -> Vec<u8> {
+        let spec = input.chain_spec.clone();
+        let spec_v1 = ChainSpecV1 {
+            name: spec.name,
+            chain_id: spec.chain_id,
+            max_spec_id: spec.max_spec_id,
+            hard_forks: spec.hard_forks,
+            protocol_forks: spec.protocol_forks,
+            eip_1559_constants: spec.eip_1559_constants,
+            l1_contract: spec.l1_contract,
+            l2_contract: spec.l2_contract,
+            rpc: spec.rpc,
+            beacon_rpc: spec.beacon_rpc,
+            verifier_address: spec.verifier_address,
+            genesis_time: spec.genesis_time,
+            seconds_per_slot: spec.seconds_per_slot,
+            is_taiko: spec.is_taiko,
+        };
+        bincode::serialize(&legacy(input.clone(), spec_v1, input.taiko.clone())).unwrap()
+    }
+
+    #[test]
+    fn test_legacy_input() {
+        let mut input = test_input();
+        input.chain_spec = SupportedChainSpecs::default()
+            .get_chain_spec("taiko_a7")
+            .unwrap();
+        input.taiko.tx_data = vec![1, 2, 3];
+        let block_proposed = BlockProposed {
+            blockId: U256::from(1234),
+            ..Default::default()
+        };
+        let bytes = encode_v0(&input, block_proposed);
+        assert_eq!(read_header(&bytes).unwrap(), None);
+        let (version, decoded) = decode(&bytes).unwrap();
+        assert_eq!(version, LEGACY_VERSION);
+        assert_eq!(decoded.block_number, input.block_number);
+        assert_eq!(decoded.chain_spec.chain_id, input.chain_spec.chain_id);
+        assert_eq!(decoded.chain_spec.hard_forks, input.chain_spec.hard_forks);
+        assert!(decoded.chain_spec.protocol_forks.is_empty());
+        assert_eq!(decoded.chain_spec.block_builder, None);
+        assert_eq!(decoded.taiko.tx_data, input.taiko.tx_data);
+        assert_eq!(decoded.taiko.block_proposed.version(), ProtocolVersion::V1);
+        assert_eq!(decoded.taiko.block_proposed.block_id(), U256::from(1234));
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let mut input = test_input();
+        input.chain_spec.is_taiko = true;
+        input.chain_spec.protocol_forks =
+            BTreeMap::from([(ProtocolVersion::V2, ForkCondition::Block(1000))]);
+        let bytes = write(1, 167009, 1234, encode_v1(&input), Compression::Zlib).unwrap();
+        let (version, decoded) = decode(&bytes).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(decoded.block_number, input.block_number);
+        assert_eq!(decoded.chain_spec, input.chain_spec);
+        assert_eq!(decoded.chain_spec.block_builder, None);
+    }
+
+    #[test]
+    fn test_migrate_eth_transactions() {
+        // Up to version 2 the transactions of Ethereum blocks were stored as their tx list
+        let tx = TxLegacy::default().into_signed(
+            Signature::from_rs_and_parity(U256::from(1), U256::from(1), false).unwrap(),
+        );
+        let transactions = vec![TxEnvelope::from(tx)];
+        let mut input = test_input();
+        input.taiko.tx_data = zlib_compress_data(&alloy_rlp::encode(&transactions)).unwrap();
+        let encode_v2 = |input: &GuestInput| {
+            let legacy = legacy(input.clone(), input.chain_spec.clone(), input.taiko.clone());
+            let encoded = bincode::serialize(&legacy).unwrap();
+            write(2, 167009, 1234, encoded, Compression::None).unwrap()
+        };
+        let (version, decoded) = decode(&encode_v2(&input)).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(decoded.transactions, transactions);
+        assert!(decoded.taiko.tx_data.is_empty());
+
+        // The tx list of a Taiko block stays the tx list
+        input.chain_spec.is_taiko = true;
+        let (_, decoded) = decode(&encode_v2(&input)).unwrap();
+        assert!(decoded.transactions.is_empty());
+        assert_eq!(decoded.taiko.tx_data, input.taiko.tx_data);
+
+        input.chain_spec.is_taiko = false;
+        input.taiko.tx_data = vec![1, 2, 3];
+        assert!(matches!(
+            decode(&encode_v2(&input)),
+            Err(InputFileError::Decode { version: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejected_inputs() {
+        let mut bytes = encode(&test_input(), Compression::None).unwrap();
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(
+            decode(&bytes),
+            Err(InputFileError::ChecksumMismatch)
+        ));
+
+        bytes[4..6].copy_from_slice(&(CURRENT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode(&bytes),
+            Err(InputFileError::UnsupportedVersion { .. })
+        ));
+        assert!(read_header(&bytes).is_err());
+
+        assert!(matches!(
+            decode(&bytes[..HEADER_SIZE - 1]),
+            Err(InputFileError::Truncated)
+        ));
+    }
+}
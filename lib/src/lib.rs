@@ -31,10 +31,13 @@ mod no_std {
 pub mod builder;
 pub mod consts;
 pub mod input;
+#[cfg(feature = "std")]
+pub mod input_file;
 pub mod mem_db;
 pub mod protocol_instance;
 pub mod prover;
 pub mod utils;
+pub mod witness;
 
 #[cfg(not(target_os = "zkvm"))]
 mod time {
@@ -105,12 +108,12 @@ impl Measurement {
         }
     }
 
-    pub fn stop(&self) {
-        self.stop_with(&format!("{} Done", self.title));
+    pub fn stop(&self) -> time::Duration {
+        self.stop_with(&format!("{} Done", self.title))
     }
 
-    pub fn stop_with_count(&self, count: &str) {
-        self.stop_with(&format!("{} {count} done", self.title));
+    pub fn stop_with_count(&self, count: &str) -> time::Duration {
+        self.stop_with(&format!("{} {count} done", self.title))
     }
 
     pub fn stop_with(&self, title: &str) -> time::Duration {
@@ -86,6 +86,43 @@ impl DbAccount {
     }
 }
 
+/// The nonce, balance and code of an account at some point of the block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code_hash: B256,
+}
+
+impl From<&AccountInfo> for AccountSnapshot {
+    fn from(info: &AccountInfo) -> Self {
+        Self {
+            nonce: info.nonce,
+            balance: info.balance,
+            code_hash: info.code_hash,
+        }
+    }
+}
+
+/// A storage slot changed by the block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageChange {
+    pub slot: U256,
+    pub before: U256,
+    pub after: U256,
+}
+
+/// An account changed by the block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountChange {
+    pub address: Address,
+    /// The account before the block, `None` if it didn't exist.
+    pub before: Option<AccountSnapshot>,
+    /// The account after the block, `None` if it was deleted.
+    pub after: Option<AccountSnapshot>,
+    pub storage: Vec<StorageChange>,
+}
+
 /// In-memory EVM database.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct MemDb {
@@ -109,6 +146,63 @@ impl MemDb {
         out
     }
 
+    /// Returns the accounts and storage slots that differ from the state in `pre`, sorted by
+    /// address and slot.
+    pub fn changes_since(&self, pre: &MemDb) -> Vec<AccountChange> {
+        let mut changes: Vec<AccountChange> = self
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.state != AccountState::None)
+            .filter_map(|(address, account)| {
+                let pre_account = pre.accounts.get(address);
+                let before = pre_account
+                    .and_then(DbAccount::info)
+                    .map(|info| AccountSnapshot::from(&info));
+                let after = account.info().map(|info| AccountSnapshot::from(&info));
+                let pre_storage = pre_account.map(|account| &account.storage);
+                let get_before = |slot: &U256| {
+                    pre_storage
+                        .and_then(|s| s.get(slot))
+                        .copied()
+                        .unwrap_or_default()
+                };
+                let mut storage: Vec<StorageChange> = account
+                    .storage
+                    .iter()
+                    .filter(|(slot, value)| get_before(slot) != **value)
+                    .map(|(slot, value)| StorageChange {
+                        slot: *slot,
+                        before: get_before(slot),
+                        after: *value,
+                    })
+                    .collect();
+                // Cleared storage isn't in the account anymore
+                storage.extend(
+                    pre_storage
+                        .into_iter()
+                        .flatten()
+                        .filter(|(slot, value)| {
+                            !value.is_zero() && !account.storage.contains_key(*slot)
+                        })
+                        .map(|(slot, value)| StorageChange {
+                            slot: *slot,
+                            before: *value,
+                            after: U256::ZERO,
+                        }),
+                );
+                storage.sort_by_key(|change| change.slot);
+                (before != after || !storage.is_empty()).then_some(AccountChange {
+                    address: *address,
+                    before,
+                    after,
+                    storage,
+                })
+            })
+            .collect();
+        changes.sort_by_key(|change| change.address);
+        changes
+    }
+
     /// Insert account info without overriding its storage.
     /// Panics if a different account info exists.
     pub fn insert_account_info(&mut self, address: Address, info: AccountInfo) {
@@ -283,11 +377,74 @@ impl DatabaseCommit for MemDb {
 }
 
 impl OptimisticDatabase for MemDb {
-    async fn fetch_data(&mut self) -> bool {
-        true
+    async fn fetch_data(&mut self) -> Result<bool, DbError> {
+        Ok(true)
     }
 
     fn is_optimistic(&self) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_since() {
+        let sender = Address::with_last_byte(1);
+        let receiver = Address::with_last_byte(2);
+        let untouched = Address::with_last_byte(3);
+        let mut pre = MemDb::default();
+        for address in [sender, untouched] {
+            pre.insert_account_info(
+                address,
+                AccountInfo {
+                    balance: U256::from(100),
+                    ..Default::default()
+                },
+            );
+        }
+        pre.insert_account_storage(&sender, U256::from(1), U256::from(7));
+        pre.insert_account_storage(&sender, U256::from(2), U256::from(8));
+
+        let mut post = pre.clone();
+        let account = post.accounts.get_mut(&sender).unwrap();
+        account.info.nonce = 1;
+        account.info.balance = U256::from(50);
+        account.storage.insert(U256::from(2), U256::from(9));
+        account.state = AccountState::Touched;
+        let mut account = DbAccount::new(AccountInfo {
+            balance: U256::from(50),
+            ..Default::default()
+        });
+        account.state = AccountState::Touched;
+        post.accounts.insert(receiver, account);
+
+        let changes = post.changes_since(&pre);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].address, sender);
+        assert_eq!(changes[0].before.as_ref().unwrap().nonce, 0);
+        assert_eq!(changes[0].after.as_ref().unwrap().nonce, 1);
+        assert_eq!(
+            changes[0].storage,
+            [StorageChange {
+                slot: U256::from(2),
+                before: U256::from(8),
+                after: U256::from(9),
+            }]
+        );
+        assert_eq!(changes[1].address, receiver);
+        assert_eq!(changes[1].before, None);
+        assert_eq!(changes[1].after.as_ref().unwrap().balance, U256::from(50));
+
+        // Deleting an account clears its storage
+        let account = post.accounts.get_mut(&sender).unwrap();
+        account.storage.clear();
+        account.state = AccountState::Deleted;
+        let changes = post.changes_since(&pre);
+        assert_eq!(changes[0].after, None);
+        assert_eq!(changes[0].storage.len(), 2);
+        assert!(changes[0].storage.iter().all(|change| change.after.is_zero()));
+    }
+}
@@ -1,26 +1,52 @@
 use alloy_consensus::Header as AlloyConsensusHeader;
 use alloy_primitives::{Address, TxHash, B256};
-use alloy_sol_types::SolValue;
+use alloy_sol_types::{SolCall, SolValue};
 use anyhow::{ensure, Result};
 use c_kzg::{Blob, KzgCommitment, KzgSettings};
 use raiko_primitives::keccak::keccak;
 use sha2::{Digest as _, Sha256};
 
-use super::utils::ANCHOR_GAS_LIMIT;
+use super::utils::anchor_gas_limit;
 #[cfg(not(feature = "std"))]
 use crate::no_std::*;
 use crate::{
     consts::{SupportedChainSpecs, VerifierType},
-    input::{BlockMetadata, EthDeposit, GuestInput, Transition},
+    input::{
+        proveBlockCall, BatchGuestInput, BlockMetadata, BlockMetadataV2, BlockProposedFork,
+        EthDeposit, GuestInput, TierProof, Transition,
+    },
     utils::HeaderHasher,
 };
 
 const KZG_TRUST_SETUP_DATA: &[u8] = include_bytes!("../../kzg_settings_raw.bin");
 
+/// The block metadata of the protocol version the block was proposed with.
+#[derive(Debug)]
+pub enum BlockMetadataFork {
+    V1(BlockMetadata),
+    V2(BlockMetadataV2),
+}
+
+impl BlockMetadataFork {
+    pub fn abi_encode(&self) -> Vec<u8> {
+        match self {
+            BlockMetadataFork::V1(meta) => meta.abi_encode(),
+            BlockMetadataFork::V2(meta) => meta.abi_encode(),
+        }
+    }
+
+    pub fn block_id(&self) -> u64 {
+        match self {
+            BlockMetadataFork::V1(meta) => meta.id,
+            BlockMetadataFork::V2(meta) => meta.id,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ProtocolInstance {
     pub transition: Transition,
-    pub block_metadata: BlockMetadata,
+    pub block_metadata: BlockMetadataFork,
     pub prover: Address,
     pub sgx_instance: Address, // only used for SGX
     pub chain_id: u64,
@@ -33,7 +59,7 @@ impl ProtocolInstance {
         header: &AlloyConsensusHeader,
         proof_type: VerifierType,
     ) -> Result<Self> {
-        let blob_used = input.taiko.block_proposed.meta.blobUsed;
+        let blob_used = input.taiko.block_proposed.blob_used();
         let tx_list_hash = if blob_used {
             if input.taiko.skip_verify_blob {
                 println!("kzg check disabled!");
@@ -89,19 +115,36 @@ impl ProtocolInstance {
                 input.chain_spec.is_taiko, verified_chain_spec.is_taiko,
                 "unexpected eip_1559_constants"
             );
+            assert_eq!(
+                input.chain_spec.protocol_forks, verified_chain_spec.protocol_forks,
+                "unexpected protocol_forks"
+            );
+            assert_eq!(
+                input.chain_spec.block_builder(),
+                verified_chain_spec.block_builder(),
+                "unexpected block_builder"
+            );
         }
 
-        let deposits = input
-            .withdrawals
-            .iter()
-            .map(|w| EthDeposit {
-                recipient: w.address,
-                amount: w.amount as u128,
-                id: w.index,
-            })
-            .collect::<Vec<_>>();
+        // The block has to be proposed with the protocol version active at the block
+        if input.chain_spec.is_taiko() {
+            let version = input
+                .chain_spec
+                .active_protocol_version(header.number, header.timestamp);
+            ensure!(
+                input.taiko.block_proposed.version() == version,
+                "unexpected protocol version, expected: {version:?}, got: {:?}",
+                input.taiko.block_proposed.version()
+            );
+        }
 
         let gas_limit: u64 = header.gas_limit.try_into().unwrap();
+        let gas_limit = (gas_limit
+            - if input.chain_spec.is_taiko() {
+                anchor_gas_limit(&input.taiko.block_proposed)
+            } else {
+                0
+            }) as u32;
         let verifier_address = (*input
             .chain_spec
             .verifier_address
@@ -109,34 +152,66 @@ impl ProtocolInstance {
             .unwrap_or(&None))
         .unwrap_or_default();
 
-        let pi = ProtocolInstance {
-            transition: Transition {
-                parentHash: header.parent_hash,
-                blockHash: header.hash(),
-                stateRoot: header.state_root,
-                graffiti: input.taiko.prover_data.graffiti,
-            },
-            block_metadata: BlockMetadata {
-                l1Hash: input.taiko.l1_header.hash(),
-                difficulty: input.taiko.block_proposed.meta.difficulty,
+        let block_metadata = match &input.taiko.block_proposed {
+            BlockProposedFork::V1(block_proposed) => {
+                let deposits = input
+                    .withdrawals
+                    .iter()
+                    .map(|w| EthDeposit {
+                        recipient: w.address,
+                        amount: w.amount as u128,
+                        id: w.index,
+                    })
+                    .collect::<Vec<_>>();
+                BlockMetadataFork::V1(BlockMetadata {
+                    l1Hash: input.taiko.l1_header.hash(),
+                    difficulty: block_proposed.meta.difficulty,
+                    blobHash: tx_list_hash,
+                    extraData: bytes_to_bytes32(&header.extra_data).into(),
+                    depositsHash: keccak(deposits.abi_encode()).into(),
+                    coinbase: header.beneficiary,
+                    id: header.number,
+                    gasLimit: gas_limit,
+                    timestamp: header.timestamp,
+                    l1Height: input.taiko.l1_header.number,
+                    minTier: block_proposed.meta.minTier,
+                    blobUsed: blob_used,
+                    parentMetaHash: block_proposed.meta.parentMetaHash,
+                    sender: block_proposed.meta.sender,
+                })
+            }
+            BlockProposedFork::V2(block_proposed) => BlockMetadataFork::V2(BlockMetadataV2 {
+                anchorBlockHash: input.taiko.l1_header.hash(),
+                difficulty: block_proposed.meta.difficulty,
                 blobHash: tx_list_hash,
                 extraData: bytes_to_bytes32(&header.extra_data).into(),
-                depositsHash: keccak(deposits.abi_encode()).into(),
                 coinbase: header.beneficiary,
                 id: header.number,
-                gasLimit: (gas_limit
-                    - if input.chain_spec.is_taiko() {
-                        ANCHOR_GAS_LIMIT
-                    } else {
-                        0
-                    }) as u32,
+                gasLimit: gas_limit,
                 timestamp: header.timestamp,
-                l1Height: input.taiko.l1_header.number,
-                minTier: input.taiko.block_proposed.meta.minTier,
+                anchorBlockId: input.taiko.l1_header.number,
+                minTier: block_proposed.meta.minTier,
                 blobUsed: blob_used,
-                parentMetaHash: input.taiko.block_proposed.meta.parentMetaHash,
-                sender: input.taiko.block_proposed.meta.sender,
+                parentMetaHash: block_proposed.meta.parentMetaHash,
+                proposer: block_proposed.meta.proposer,
+                livenessBond: block_proposed.meta.livenessBond,
+                proposedAt: block_proposed.meta.proposedAt,
+                proposedIn: block_proposed.meta.proposedIn,
+                blobTxListOffset: block_proposed.meta.blobTxListOffset,
+                blobTxListLength: block_proposed.meta.blobTxListLength,
+                blobIndex: block_proposed.meta.blobIndex,
+                baseFeeConfig: block_proposed.meta.baseFeeConfig.clone(),
+            }),
+        };
+
+        let pi = ProtocolInstance {
+            transition: Transition {
+                parentHash: header.parent_hash,
+                blockHash: header.hash(),
+                stateRoot: header.state_root,
+                graffiti: input.taiko.prover_data.graffiti,
             },
+            block_metadata,
             sgx_instance: Address::default(),
             prover: input.taiko.prover_data.prover,
             chain_id: input.chain_spec.chain_id,
@@ -145,11 +220,15 @@ impl ProtocolInstance {
 
         // Sanity check
         if input.chain_spec.is_taiko() {
+            let expected_meta = match &input.taiko.block_proposed {
+                BlockProposedFork::V1(block_proposed) => block_proposed.meta.abi_encode(),
+                BlockProposedFork::V2(block_proposed) => block_proposed.meta.abi_encode(),
+            };
             ensure!(
-                pi.block_metadata.abi_encode() == input.taiko.block_proposed.meta.abi_encode(),
+                pi.block_metadata.abi_encode() == expected_meta,
                 format!(
                     "block hash mismatch, expected: {:?}, got: {:?}",
-                    input.taiko.block_proposed.meta, pi.block_metadata
+                    input.taiko.block_proposed, pi.block_metadata
                 )
             );
         }
@@ -185,6 +264,85 @@ impl ProtocolInstance {
         }
         keccak(data).into()
     }
+
+    /// Encodes the `input` of `proveBlock(blockId, input)` that submits the transition with
+    /// the given proof: `abi.encode(meta, transition, proof)`.
+    pub fn prove_block_input(&self, tier_proof: TierProof) -> Vec<u8> {
+        let transition = self.transition.clone();
+        match &self.block_metadata {
+            BlockMetadataFork::V1(meta) => {
+                (meta.clone(), transition, tier_proof).abi_encode_params()
+            }
+            BlockMetadataFork::V2(meta) => {
+                (meta.clone(), transition, tier_proof).abi_encode_params()
+            }
+        }
+    }
+
+    /// Encodes the `proveBlock(blockId, input)` call that submits the transition with the
+    /// given proof.
+    pub fn prove_block_calldata(&self, tier_proof: TierProof) -> Vec<u8> {
+        proveBlockCall {
+            blockId: self.block_metadata.block_id(),
+            input: self.prove_block_input(tier_proof).into(),
+        }
+        .abi_encode()
+    }
+}
+
+/// The protocol instances of a batch of consecutive blocks proven together.
+#[derive(Debug)]
+pub struct BatchProtocolInstance {
+    pub instances: Vec<ProtocolInstance>,
+}
+
+impl BatchProtocolInstance {
+    pub fn new(
+        batch: &BatchGuestInput,
+        headers: &[AlloyConsensusHeader],
+        proof_type: VerifierType,
+    ) -> Result<Self> {
+        ensure!(!batch.inputs.is_empty(), "empty batch");
+        ensure!(
+            batch.inputs.len() == headers.len(),
+            "unexpected number of headers, expected: {}, got: {}",
+            batch.inputs.len(),
+            headers.len()
+        );
+        let instances = batch
+            .inputs
+            .iter()
+            .zip(headers)
+            .map(|(input, header)| ProtocolInstance::new(input, header, proof_type))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { instances })
+    }
+
+    pub fn sgx_instance(mut self, instance: Address) -> Self {
+        for pi in &mut self.instances {
+            pi.sgx_instance = instance;
+        }
+        self
+    }
+
+    pub fn instance_hashes(&self) -> Vec<B256> {
+        self.instances.iter().map(|pi| pi.instance_hash()).collect()
+    }
+
+    /// The hash committed to by a batch proof, see [batch_instance_hash].
+    pub fn instance_hash(&self) -> B256 {
+        batch_instance_hash(&self.instance_hashes())
+    }
+}
+
+/// Hash chain over the instance hashes of the blocks in a batch, in block order:
+/// `h_0 = 0`, `h_i = keccak256(h_{i-1} || instanceHash_i)`.
+pub fn batch_instance_hash(instance_hashes: &[B256]) -> B256 {
+    instance_hashes
+        .iter()
+        .fold(B256::ZERO, |acc, instance_hash| {
+            keccak([acc.as_slice(), instance_hash.as_slice()].concat()).into()
+        })
 }
 
 pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
@@ -203,12 +361,195 @@ fn bytes_to_bytes32(input: &[u8]) -> [u8; 32] {
 
 #[cfg(test)]
 mod tests {
-    use alloy_primitives::{address, b256};
-    use alloy_sol_types::SolCall;
+    use alloy_primitives::{address, b256, Log, U256};
+    use alloy_sol_types::{SolCall, SolEvent};
     use raiko_primitives::keccak;
 
     use super::*;
-    use crate::input::{proveBlockCall, TierProof};
+    use crate::{
+        consts::{ChainSpec, ForkCondition, ProtocolVersion},
+        input::{
+            proveBlockCall, BaseFeeConfig, BlockProposed, BlockProposedV2, TaikoGuestInput,
+            TierProof,
+        },
+        utils::{ANCHOR_GAS_LIMIT, ANCHOR_V2_GAS_LIMIT},
+    };
+
+    fn protocol_fixture(block_proposed: BlockProposedFork) -> (GuestInput, AlloyConsensusHeader) {
+        let header = AlloyConsensusHeader {
+            number: 100,
+            gas_limit: 15_000_000,
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        let input = GuestInput {
+            chain_spec: ChainSpec {
+                chain_id: 999_999,
+                is_taiko: true,
+                protocol_forks: [(ProtocolVersion::V2, ForkCondition::Block(100))].into(),
+                ..Default::default()
+            },
+            taiko: TaikoGuestInput {
+                block_proposed,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        (input, header)
+    }
+
+    #[test]
+    fn test_protocol_instance_v1() {
+        let l1_header = AlloyConsensusHeader::default();
+        let meta = BlockMetadata {
+            l1Hash: l1_header.hash(),
+            blobHash: keccak(Vec::<u8>::new()).into(),
+            depositsHash: keccak(Vec::<EthDeposit>::new().abi_encode()).into(),
+            id: 99,
+            gasLimit: (15_000_000 - ANCHOR_GAS_LIMIT) as u32,
+            timestamp: 1_700_000_000,
+            minTier: 100,
+            sender: address!("70997970C51812dc3A010C7d01b50e0d17dc79C8"),
+            ..Default::default()
+        };
+        let event = BlockProposed {
+            blockId: U256::from(99),
+            meta,
+            ..Default::default()
+        };
+        // The event roundtrips through the log encoding of its version
+        let log_data = event.encode_log_data();
+        let log = Log::new(
+            Address::default(),
+            log_data.topics().to_vec(),
+            log_data.data.clone(),
+        )
+        .unwrap();
+        let decoded = BlockProposedFork::decode_log(ProtocolVersion::V1, &log).unwrap();
+        assert!(BlockProposedFork::decode_log(ProtocolVersion::V2, &log).is_err());
+
+        let (input, mut header) = protocol_fixture(decoded);
+        header.number = 99;
+        let pi = ProtocolInstance::new(&input, &header, VerifierType::None).unwrap();
+        assert!(matches!(pi.block_metadata, BlockMetadataFork::V1(_)));
+        assert_eq!(pi.meta_hash(), keccak(event.meta.abi_encode()).into());
+
+        // The proveBlock calldata roundtrips
+        let tier_proof = TierProof {
+            tier: VerifierType::SGX.tier(),
+            data: vec![1u8; 89].into(),
+        };
+        let call = proveBlockCall::abi_decode(&pi.prove_block_calldata(tier_proof), true).unwrap();
+        assert_eq!(call.blockId, 99);
+        let (meta, transition, proof) =
+            <(BlockMetadata, Transition, TierProof)>::abi_decode_params(&call.input, true).unwrap();
+        assert_eq!(meta.abi_encode(), event.meta.abi_encode());
+        assert_eq!(transition.abi_encode(), pi.transition.abi_encode());
+        assert_eq!(proof.tier, 200);
+        assert_eq!(proof.data.to_vec(), vec![1u8; 89]);
+
+        // V1 blocks can't be proven after the V2 upgrade
+        header.number = 100;
+        assert!(ProtocolInstance::new(&input, &header, VerifierType::None).is_err());
+    }
+
+    #[test]
+    fn test_protocol_instance_v2() {
+        let l1_header = AlloyConsensusHeader::default();
+        let meta = BlockMetadataV2 {
+            anchorBlockHash: l1_header.hash(),
+            blobHash: keccak(Vec::<u8>::new()).into(),
+            id: 100,
+            gasLimit: (15_000_000 - ANCHOR_V2_GAS_LIMIT) as u32,
+            timestamp: 1_700_000_000,
+            minTier: 100,
+            proposer: address!("70997970C51812dc3A010C7d01b50e0d17dc79C8"),
+            livenessBond: 125,
+            proposedAt: 1_699_999_990,
+            proposedIn: 5_000,
+            baseFeeConfig: BaseFeeConfig {
+                adjustmentQuotient: 8,
+                sharingPctg: 75,
+                gasIssuancePerSecond: 5_000_000,
+                minGasExcess: 1_340_000_000,
+                maxGasIssuancePerBlock: 600_000_000,
+            },
+            ..Default::default()
+        };
+        let event = BlockProposedV2 {
+            blockId: U256::from(100),
+            meta,
+        };
+        let log_data = event.encode_log_data();
+        let log = Log::new(
+            Address::default(),
+            log_data.topics().to_vec(),
+            log_data.data.clone(),
+        )
+        .unwrap();
+        let decoded = BlockProposedFork::decode_log(ProtocolVersion::V2, &log).unwrap();
+        assert!(BlockProposedFork::decode_log(ProtocolVersion::V1, &log).is_err());
+
+        let (input, header) = protocol_fixture(decoded);
+        let pi = ProtocolInstance::new(&input, &header, VerifierType::None).unwrap();
+        assert!(matches!(pi.block_metadata, BlockMetadataFork::V2(_)));
+        assert_eq!(pi.meta_hash(), keccak(event.meta.abi_encode()).into());
+
+        // The proveBlock input roundtrips with the V2 metadata
+        let tier_proof = TierProof {
+            tier: VerifierType::RISC0.tier(),
+            data: vec![2u8; 64].into(),
+        };
+        let input = pi.prove_block_input(tier_proof);
+        let (meta, transition, proof) =
+            <(BlockMetadataV2, Transition, TierProof)>::abi_decode_params(&input, true).unwrap();
+        assert_eq!(meta.abi_encode(), event.meta.abi_encode());
+        assert_eq!(transition.abi_encode(), pi.transition.abi_encode());
+        assert_eq!(proof.tier, 250);
+        assert_eq!(proof.data.to_vec(), vec![2u8; 64]);
+    }
+
+    #[test]
+    fn test_batch_instance_hash() {
+        let a = B256::with_last_byte(1);
+        let b = B256::with_last_byte(2);
+        let first: B256 = keccak([B256::ZERO.as_slice(), a.as_slice()].concat()).into();
+        let second: B256 = keccak([first.as_slice(), b.as_slice()].concat()).into();
+        assert_eq!(batch_instance_hash(&[]), B256::ZERO);
+        assert_eq!(batch_instance_hash(&[a]), first);
+        assert_eq!(batch_instance_hash(&[a, b]), second);
+        // The order of the blocks is committed to
+        assert_ne!(batch_instance_hash(&[b, a]), second);
+    }
+
+    #[test]
+    fn test_batch_protocol_instance() {
+        let meta = BlockMetadataV2 {
+            anchorBlockHash: AlloyConsensusHeader::default().hash(),
+            blobHash: keccak(Vec::<u8>::new()).into(),
+            id: 100,
+            gasLimit: (15_000_000 - ANCHOR_V2_GAS_LIMIT) as u32,
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        let (input, header) = protocol_fixture(BlockProposedFork::V2(BlockProposedV2 {
+            blockId: U256::from(100),
+            meta,
+        }));
+        let pi = ProtocolInstance::new(&input, &header, VerifierType::None).unwrap();
+        let batch = BatchGuestInput {
+            inputs: vec![input.clone()],
+        };
+        let batch_pi =
+            BatchProtocolInstance::new(&batch, &[header.clone()], VerifierType::None).unwrap();
+        assert_eq!(batch_pi.instance_hashes(), vec![pi.instance_hash()]);
+        assert_eq!(
+            batch_pi.instance_hash(),
+            batch_instance_hash(&[pi.instance_hash()])
+        );
+        // Every block needs a header
+        assert!(BatchProtocolInstance::new(&batch, &[], VerifierType::None).is_err());
+    }
 
     #[test]
     fn bytes_to_bytes32_test() {
@@ -1,9 +1,9 @@
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
-use crate::input::{GuestInput, GuestOutput};
+use crate::input::{BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput};
 
 #[derive(ThisError, Debug)]
 pub enum ProverError {
@@ -35,6 +35,73 @@ pub trait Prover {
         output: &GuestOutput,
         config: &ProverConfig,
     ) -> ProverResult<Proof>;
+
+    /// Executes the guest program without proving it and estimates the resources needed to
+    /// prove it.
+    async fn estimate(_input: GuestInput, _config: &ProverConfig) -> ProverResult<GuestEstimate> {
+        Err(ProverError::GuestError(
+            "Estimation is not supported by this prover".to_owned(),
+        ))
+    }
+
+    /// Proves a batch of consecutive blocks in a single proof, which commits to the hash chain
+    /// of the instance hashes of all blocks in the batch.
+    async fn batch_run(
+        _batch: BatchGuestInput,
+        _output: &BatchGuestOutput,
+        _config: &ProverConfig,
+    ) -> ProverResult<Proof> {
+        Err(ProverError::GuestError(
+            "Batch proving is not supported by this prover".to_owned(),
+        ))
+    }
+
+    /// Checks that a proof created by `run` is valid and proves the expected output for the
+    /// input, so invalid proofs are caught before they are submitted.
+    async fn verify(
+        _input: GuestInput,
+        _output: &GuestOutput,
+        _proof: &Proof,
+        _config: &ProverConfig,
+    ) -> ProverResult<()> {
+        Err(ProverError::GuestError(
+            "Verification is not supported by this prover".to_owned(),
+        ))
+    }
+}
+
+/// The resources needed to prove a guest program, measured by executing it without proving.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GuestEstimate {
+    /// The total number of cycles, including paging and padding.
+    pub total_cycles: u64,
+    /// The number of segments (shards for SP1) the execution is split into.
+    pub segments: u64,
+    /// The log2 of the maximum segment size in cycles.
+    pub segment_po2: u32,
+    /// The number of calls per precompile, `None` for the zkVMs that don't report them.
+    pub precompile_calls: Option<BTreeMap<String, u64>>,
+    /// The peak memory used by the guest program in bytes.
+    pub peak_memory: Option<u64>,
+    /// The expected proving time in seconds.
+    pub proving_time_secs: f64,
+}
+
+/// A linear model of the proving time. The defaults of the provers are rough estimates, so the
+/// model should be calibrated on timed proofs of the proving machine and passed in the
+/// `proving_time_model` prover param.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProvingTimeModel {
+    /// The fixed cost of a proof in seconds, e.g. setup and SNARK wrapping.
+    pub base_secs: f64,
+    /// The proving time per segment in seconds.
+    pub secs_per_segment: f64,
+}
+
+impl ProvingTimeModel {
+    pub fn proving_time_secs(&self, segments: u64) -> f64 {
+        self.base_secs + self.secs_per_segment * segments as f64
+    }
 }
 
 pub fn to_proof(proof: ProverResult<impl Serialize>) -> ProverResult<Proof> {
@@ -42,3 +109,25 @@ pub fn to_proof(proof: ProverResult<impl Serialize>) -> ProverResult<Proof> {
         serde_json::to_value(res).map_err(|err| ProverError::GuestError(err.to_string()))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proving_time_model() {
+        let model = ProvingTimeModel {
+            base_secs: 10.0,
+            secs_per_segment: 2.5,
+        };
+        assert_eq!(model.proving_time_secs(0), 10.0);
+        assert_eq!(model.proving_time_secs(4), 20.0);
+
+        let model: ProvingTimeModel = serde_json::from_value(serde_json::json!({
+            "base_secs": 1.0,
+            "secs_per_segment": 0.5,
+        }))
+        .unwrap();
+        assert_eq!(model.proving_time_secs(2), 2.0);
+    }
+}
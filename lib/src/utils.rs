@@ -16,11 +16,24 @@ use raiko_primitives::{keccak256, B256};
 #[cfg(not(feature = "std"))]
 use crate::no_std::*;
 use crate::{
-    consts::{ChainSpec, Network},
-    input::{decode_anchor, GuestInput},
+    builder::SkipReason,
+    consts::{ChainSpec, Network, ProtocolVersion},
+    input::{decode_anchor, AnchorCallFork, BlockProposedFork, GuestInput},
 };
 
 pub const ANCHOR_GAS_LIMIT: u64 = 250_000;
+/// The gas limit of the `anchorV2` transaction of the blocks proposed with
+/// [crate::consts::ProtocolVersion::V2].
+pub const ANCHOR_V2_GAS_LIMIT: u64 = 1_000_000;
+
+/// The gas limit of the anchor transaction of the protocol version the block was proposed
+/// with.
+pub fn anchor_gas_limit(block_proposed: &BlockProposedFork) -> u64 {
+    match block_proposed {
+        BlockProposedFork::V1(_) => ANCHOR_GAS_LIMIT,
+        BlockProposedFork::V2(_) => ANCHOR_V2_GAS_LIMIT,
+    }
+}
 
 lazy_static! {
     pub static ref GOLDEN_TOUCH_ACCOUNT: Address = {
@@ -38,87 +51,112 @@ lazy_static! {
 }
 
 pub fn decode_transactions(tx_list: &[u8]) -> Vec<TxEnvelope> {
-    Vec::<TxEnvelope>::decode(&mut &tx_list.to_owned()[..]).unwrap_or_else(|e| {
-        // If decoding fails we need to make an empty block
-        println!("decode_transactions not successful: {e:?}, use empty tx_list");
-        vec![]
-    })
+    // If decoding fails we need to make an empty block
+    try_decode_transactions(tx_list).unwrap_or_default()
+}
+
+/// Decodes the transactions of a decompressed tx list.
+pub fn try_decode_transactions(tx_list: &[u8]) -> Result<Vec<TxEnvelope>, SkipReason> {
+    Vec::<TxEnvelope>::decode(&mut &tx_list.to_owned()[..])
+        .map_err(|e| SkipReason::TxListDecodeFailure(format!("{e:?}")))
 }
 
 // leave a simply fn in case of more checks in future
-fn validate_calldata_tx_list(tx_list: &[u8]) -> bool {
-    tx_list.len() <= CALL_DATA_CAPACITY
+fn validate_calldata_tx_list(tx_list: &[u8]) -> Result<(), SkipReason> {
+    if tx_list.len() > CALL_DATA_CAPACITY {
+        return Err(SkipReason::TxListTooLarge {
+            size: tx_list.len(),
+            capacity: CALL_DATA_CAPACITY,
+        });
+    }
+    Ok(())
 }
 
-fn get_tx_list(chain_spec: &ChainSpec, is_blob_data: bool, tx_list: &[u8]) -> Vec<u8> {
-    if chain_spec.is_taiko() {
-        // taiko has some limiations to be aligned with taiko-client
-        if is_blob_data {
-            let compressed_tx_list = decode_blob_data(tx_list);
-            return zlib_decompress_data(&compressed_tx_list).unwrap_or_default();
-        }
+fn decompress_tx_list(tx_list: &[u8]) -> Result<Vec<u8>, SkipReason> {
+    zlib_decompress_data(tx_list).map_err(|e| SkipReason::TxListDecodeFailure(e.to_string()))
+}
 
-        if Network::TaikoA7.to_string() == chain_spec.network() {
-            let de_tx_list: Vec<u8> = zlib_decompress_data(tx_list).unwrap_or_default();
+/// Extracts the tx list of a Taiko block from the blob or the calldata it was proposed with,
+/// with the limitations of taiko-client.
+fn get_tx_list(
+    chain_spec: &ChainSpec,
+    block_proposal: &BlockProposedFork,
+    tx_list: &[u8],
+) -> Result<Vec<u8>, SkipReason> {
+    if block_proposal.blob_used() {
+        let blob_data = decode_blob_data(tx_list)?;
+        let compressed_tx_list = match block_proposal.blob_tx_slice_param() {
+            // Only part of the blob contains the tx list
+            Some((offset, length)) => offset
+                .checked_add(length)
+                .and_then(|end| blob_data.get(offset..end))
+                .unwrap_or_default(),
+            None => &blob_data,
+        };
+        return decompress_tx_list(compressed_tx_list);
+    }
 
-            if validate_calldata_tx_list(&de_tx_list) {
-                return de_tx_list;
-            }
+    if Network::TaikoA7.to_string() == chain_spec.network() {
+        let de_tx_list = decompress_tx_list(tx_list)?;
+        validate_calldata_tx_list(&de_tx_list)?;
+        return Ok(de_tx_list);
+    }
 
-            println!("validate_calldata_tx_list failed, use empty tx_list");
-            return vec![];
-        }
+    validate_calldata_tx_list(tx_list)?;
+    decompress_tx_list(tx_list)
+}
 
-        if validate_calldata_tx_list(tx_list) {
-            zlib_decompress_data(tx_list).unwrap_or_default()
-        } else {
-            println!("validate_calldata_tx_list failed, use empty tx_list");
-            vec![]
-        }
-    } else {
-        // no limitation on non-taiko chains
-        zlib_decompress_data(tx_list).unwrap_or_default()
-    }
+/// Decodes the transactions of an Ethereum block from a compressed tx list, which older
+/// inputs stored them in. Unlike on Taiko, there is no limit on the tx list and it has to be
+/// valid.
+pub fn decode_eth_transactions(tx_list: &[u8]) -> Result<Vec<TxEnvelope>> {
+    decompress_tx_list(tx_list)
+        .and_then(|tx_list| try_decode_transactions(&tx_list))
+        .map_err(|reason| anyhow!("Invalid tx list: {reason:?}"))
 }
 
+/// Generates the transactions of a Taiko block from the tx list, with the anchor transaction
+/// first. Blocks with an invalid tx list only contain the anchor transaction, and the
+/// reason the tx list was dropped is returned as well.
 pub fn generate_transactions(
     chain_spec: &ChainSpec,
-    is_blob_data: bool,
+    block_proposal: &BlockProposedFork,
     tx_list: &[u8],
-    anchor_tx: Option<AlloyTransaction>,
-) -> Vec<TxEnvelope> {
-    // Decode the tx list from the raw data posted onchain
-    let tx_list = get_tx_list(chain_spec, is_blob_data, tx_list);
-
-    // Decode the transactions from the tx list
-    let mut transactions = decode_transactions(&tx_list);
-    if let Some(anchor_tx) = anchor_tx {
-        // Create a tx from the anchor tx that has the same type as the transactions encoded from
-        // the tx list
-        let signed_eip1559_tx = Signed::<TxEip1559>::new_unchecked(
-            TxEip1559 {
-                chain_id: anchor_tx.chain_id.unwrap(),
-                nonce: anchor_tx.nonce,
-                gas_limit: anchor_tx.gas,
-                max_fee_per_gas: anchor_tx.max_fee_per_gas.unwrap(),
-                max_priority_fee_per_gas: anchor_tx.max_priority_fee_per_gas.unwrap(),
-                to: TxKind::Call(anchor_tx.to.unwrap()),
-                value: anchor_tx.value,
-                access_list: Default::default(),
-                input: anchor_tx.input,
-            },
-            Signature::from_rs_and_parity(
-                anchor_tx.signature.unwrap().r,
-                anchor_tx.signature.unwrap().s,
-                anchor_tx.signature.unwrap().y_parity.unwrap().0,
-            )
-            .unwrap(),
-            anchor_tx.hash,
-        );
-        // Insert the anchor transactions generated by the node (which needs to be verified!)
-        transactions.insert(0, TxEnvelope::from(signed_eip1559_tx));
-    }
-    transactions
+    anchor_tx: AlloyTransaction,
+) -> (Vec<TxEnvelope>, Option<SkipReason>) {
+    // Decode the tx list from the raw data posted onchain and the transactions from the
+    // tx list
+    let (mut transactions, tx_list_error) = match get_tx_list(chain_spec, block_proposal, tx_list)
+        .and_then(|tx_list| try_decode_transactions(&tx_list))
+    {
+        Ok(transactions) => (transactions, None),
+        Err(reason) => (Vec::new(), Some(reason)),
+    };
+    // Create a tx from the anchor tx that has the same type as the transactions encoded from
+    // the tx list
+    let signed_eip1559_tx = Signed::<TxEip1559>::new_unchecked(
+        TxEip1559 {
+            chain_id: anchor_tx.chain_id.unwrap(),
+            nonce: anchor_tx.nonce,
+            gas_limit: anchor_tx.gas,
+            max_fee_per_gas: anchor_tx.max_fee_per_gas.unwrap(),
+            max_priority_fee_per_gas: anchor_tx.max_priority_fee_per_gas.unwrap(),
+            to: TxKind::Call(anchor_tx.to.unwrap()),
+            value: anchor_tx.value,
+            access_list: Default::default(),
+            input: anchor_tx.input,
+        },
+        Signature::from_rs_and_parity(
+            anchor_tx.signature.unwrap().r,
+            anchor_tx.signature.unwrap().s,
+            anchor_tx.signature.unwrap().y_parity.unwrap().0,
+        )
+        .unwrap(),
+        anchor_tx.hash,
+    );
+    // Insert the anchor transactions generated by the node (which needs to be verified!)
+    transactions.insert(0, TxEnvelope::from(signed_eip1559_tx));
+    (transactions, tx_list_error)
 }
 
 const BLOB_FIELD_ELEMENT_NUM: usize = 4096;
@@ -131,10 +169,12 @@ const BLOB_ENCODING_VERSION: u8 = 0;
 const MAX_BLOB_DATA_SIZE: usize = (4 * 31 + 3) * 1024 - 4;
 
 // decoding https://github.com/ethereum-optimism/optimism/blob/develop/op-service/eth/blob.go
-fn decode_blob_data(blob_buf: &[u8]) -> Vec<u8> {
+fn decode_blob_data(blob_buf: &[u8]) -> Result<Vec<u8>, SkipReason> {
     // check the version
     if blob_buf[BLOB_VERSION_OFFSET] != BLOB_ENCODING_VERSION {
-        return Vec::new();
+        return Err(SkipReason::BlobVersionMismatch {
+            version: blob_buf[BLOB_VERSION_OFFSET],
+        });
     }
 
     // decode the 3-byte big-endian length value into a 4-byte integer
@@ -143,7 +183,10 @@ fn decode_blob_data(blob_buf: &[u8]) -> Vec<u8> {
         | u32::from(blob_buf[4])) as usize;
 
     if output_len > MAX_BLOB_DATA_SIZE {
-        return Vec::new();
+        return Err(SkipReason::TxListTooLarge {
+            size: output_len,
+            capacity: MAX_BLOB_DATA_SIZE,
+        });
     }
 
     // round 0 is special cased to copy only the remaining 27 bytes of the first field element
@@ -157,9 +200,8 @@ fn decode_blob_data(blob_buf: &[u8]) -> Vec<u8> {
     let mut encoded_byte: [u8; 4] = [0; 4]; // buffer for the 4 6-bit chunks
     encoded_byte[0] = blob_buf[0];
     for encoded_byte_i in encoded_byte.iter_mut().skip(1) {
-        let Ok(res) = decode_field_element(blob_buf, opos, ipos, &mut output) else {
-            return Vec::new();
-        };
+        let res = decode_field_element(blob_buf, opos, ipos, &mut output)
+            .map_err(|e| SkipReason::InvalidBlob(e.to_string()))?;
 
         (*encoded_byte_i, opos, ipos) = res;
     }
@@ -171,26 +213,34 @@ fn decode_blob_data(blob_buf: &[u8]) -> Vec<u8> {
         if opos < output_len {
             for encoded_byte_j in &mut encoded_byte {
                 // save the first byte of each field element for later re-assembly
-                let Ok(res) = decode_field_element(blob_buf, opos, ipos, &mut output) else {
-                    return Vec::new();
-                };
+                let res = decode_field_element(blob_buf, opos, ipos, &mut output)
+                    .map_err(|e| SkipReason::InvalidBlob(e.to_string()))?;
 
                 (*encoded_byte_j, opos, ipos) = res;
             }
             opos = reassemble_bytes(opos, encoded_byte, &mut output);
         }
     }
-    for otailing in output.iter().skip(output_len) {
-        if *otailing != 0 {
-            return Vec::new();
-        }
+    if output
+        .iter()
+        .skip(output_len)
+        .any(|otailing| *otailing != 0)
+    {
+        return Err(SkipReason::InvalidBlob(
+            "non-zero data after the encoded length".to_owned(),
+        ));
     }
-    for itailing in blob_buf.iter().take(BLOB_DATA_CAPACITY).skip(ipos) {
-        if *itailing != 0 {
-            return Vec::new();
-        }
+    if blob_buf
+        .iter()
+        .take(BLOB_DATA_CAPACITY)
+        .skip(ipos)
+        .any(|itailing| *itailing != 0)
+    {
+        return Err(SkipReason::InvalidBlob(
+            "non-zero data after the last field element".to_owned(),
+        ));
     }
-    output[0..output_len].to_vec()
+    Ok(output[0..output_len].to_vec())
 }
 
 fn decode_field_element(
@@ -297,7 +347,7 @@ pub fn check_anchor_tx(input: &GuestInput, anchor: &TxEnvelope, from: &Address)
             );
             // Tx needs to have the expected gas limit
             ensure!(
-                tx.gas_limit == ANCHOR_GAS_LIMIT.into(),
+                tx.gas_limit == anchor_gas_limit(&input.taiko.block_proposed).into(),
                 "anchor transaction gas price mismatch"
             );
             // Check needs to have the base fee set to the block base fee
@@ -308,24 +358,39 @@ pub fn check_anchor_tx(input: &GuestInput, anchor: &TxEnvelope, from: &Address)
 
             // Okay now let's decode the anchor tx to verify the inputs
             let anchor_call = decode_anchor(&tx.input)?;
-            // The L1 blockhash needs to match the expected value
+            // The anchor call has to be the one of the protocol version of the block
             ensure!(
-                anchor_call.l1Hash == input.taiko.l1_header.hash(),
-                "L1 hash mismatch"
+                anchor_call.version() == input.taiko.block_proposed.version(),
+                "anchor call version mismatch"
             );
-            if input.chain_spec.network() == Network::TaikoA7.to_string() {
+            match &anchor_call {
+                // The L1 blockhash needs to match the expected value
+                AnchorCallFork::V1(call) => ensure!(
+                    call.l1Hash == input.taiko.l1_header.hash(),
+                    "L1 hash mismatch"
+                ),
+                // V2 blocks commit to the L1 block hash in their metadata instead, and the
+                // base fee config needs to be the one the block was proposed with
+                AnchorCallFork::V2(call) => ensure!(
+                    Some(&call._baseFeeConfig) == input.taiko.block_proposed.base_fee_config(),
+                    "base fee config mismatch"
+                ),
+            }
+            if anchor_call.version() == ProtocolVersion::V2
+                || input.chain_spec.network() == Network::TaikoA7.to_string()
+            {
                 ensure!(
-                    anchor_call.l1StateRoot == input.taiko.l1_header.state_root,
+                    anchor_call.l1_state_root() == input.taiko.l1_header.state_root,
                     "L1 state root mismatch"
                 );
             }
             ensure!(
-                anchor_call.l1BlockId == input.taiko.l1_header.number,
+                anchor_call.l1_block_id() == input.taiko.l1_header.number,
                 "L1 block number mismatch"
             );
             // The parent gas used input needs to match the gas used value of the parent block
             ensure!(
-                anchor_call.parentGasUsed == input.parent_header.gas_used as u32,
+                anchor_call.parent_gas_used() == input.parent_header.gas_used as u32,
                 "parentGasUsed mismatch"
             );
         }
@@ -375,3 +440,52 @@ pub fn to_header(header: &AlloyHeader) -> AlloyConsensusHeader {
         parent_beacon_block_root: header.parent_beacon_block_root,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_list_skip_reasons() {
+        let mut blob = vec![0u8; BLOB_DATA_CAPACITY];
+        blob[BLOB_VERSION_OFFSET] = 1;
+        assert_eq!(
+            decode_blob_data(&blob),
+            Err(SkipReason::BlobVersionMismatch { version: 1 })
+        );
+
+        blob[BLOB_VERSION_OFFSET] = BLOB_ENCODING_VERSION;
+        blob[2] = 0xff;
+        assert!(matches!(
+            decode_blob_data(&blob),
+            Err(SkipReason::TxListTooLarge { .. })
+        ));
+
+        blob[2] = 0;
+        assert_eq!(decode_blob_data(&blob), Ok(Vec::new()));
+
+        assert_eq!(
+            validate_calldata_tx_list(&vec![0; CALL_DATA_CAPACITY + 1]),
+            Err(SkipReason::TxListTooLarge {
+                size: CALL_DATA_CAPACITY + 1,
+                capacity: CALL_DATA_CAPACITY,
+            })
+        );
+        assert!(matches!(
+            decompress_tx_list(&[1, 2, 3]),
+            Err(SkipReason::TxListDecodeFailure(_))
+        ));
+        assert!(matches!(
+            try_decode_transactions(&[0xc1]),
+            Err(SkipReason::TxListDecodeFailure(_))
+        ));
+        assert_eq!(try_decode_transactions(&[0xc0]), Ok(Vec::new()));
+
+        // The tx list of an Ethereum block has to be valid
+        assert!(decode_eth_transactions(&[1, 2, 3]).is_err());
+        assert_eq!(
+            decode_eth_transactions(&zlib_compress_data(&[0xc0]).unwrap()).unwrap(),
+            Vec::new()
+        );
+    }
+}
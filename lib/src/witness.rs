@@ -0,0 +1,327 @@
+//! A compact encoding of the witness of a block for the zkVM guests.
+//!
+//! [GuestInput] contains every trie as a tree of [MptNode]s, so nodes shared by several
+//! storage tries are sent several times and every node is deserialized into its own
+//! allocations. The [CompactWitness] instead sends the RLP encoding of every trie node once,
+//! in a table the guest looks the nodes up in by their hash, and only decodes the nodes that
+//! are reachable from the roots. The code of the contracts is sent once per code hash.
+use anyhow::{anyhow, Result};
+use raiko_primitives::{
+    keccak::keccak,
+    mpt::{MptNode, MptNodeData, MptNodeReference, StateAccount, EMPTY_ROOT},
+    Address, Bytes, B256, U256,
+};
+use revm::primitives::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::input::GuestInput;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+
+/// How the input is sent to the zkVM guests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WitnessFormat {
+    /// The [GuestInput] as is.
+    Full,
+    /// The [CompactWitness] of the input.
+    #[default]
+    Compact,
+}
+
+#[cfg(feature = "std")]
+impl WitnessFormat {
+    /// Returns the format set in the `witness` prover param, [WitnessFormat::Compact] if it's
+    /// not set.
+    pub fn from_config(config: &serde_json::Value) -> Result<Self> {
+        config
+            .get("witness")
+            .map(|format| serde_json::from_value(format.clone()))
+            .transpose()
+            .map(Option::unwrap_or_default)
+            .map_err(|e| anyhow!("invalid witness format: {e}"))
+    }
+}
+
+/// The input as it is read by the zkVM guests.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GuestWitness {
+    Full(GuestInput),
+    Compact(CompactWitness),
+}
+
+impl GuestWitness {
+    pub fn new(input: GuestInput, format: WitnessFormat) -> Self {
+        match format {
+            WitnessFormat::Full => GuestWitness::Full(input),
+            WitnessFormat::Compact => GuestWitness::Compact(CompactWitness::new(input)),
+        }
+    }
+
+    pub fn into_input(self) -> Result<GuestInput> {
+        match self {
+            GuestWitness::Full(input) => Ok(input),
+            GuestWitness::Compact(witness) => witness.into_input(),
+        }
+    }
+}
+
+/// The input with its tries stored as a table of deduplicated nodes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CompactWitness {
+    /// The input without the tries and the contracts.
+    pub input: GuestInput,
+    /// The RLP encodings of the nodes of the state and storage tries that are referenced by
+    /// their hash, and of the roots.
+    pub nodes: Vec<Bytes>,
+    /// The root of the state trie.
+    pub state_root: B256,
+    /// The accounts and their used storage slots. The roots of the storage tries are read
+    /// from the state trie.
+    pub storage: Vec<(Address, Vec<U256>)>,
+    /// The code of the contracts, ordered by code hash.
+    pub contracts: Vec<Bytes>,
+}
+
+/// Collects the RLP encodings of the nodes of tries, every node once.
+#[derive(Default)]
+struct NodeTable {
+    hashes: HashSet<B256>,
+    nodes: Vec<Bytes>,
+}
+
+impl NodeTable {
+    /// Adds the root and all nodes below it that are referenced by hash.
+    fn add_trie(&mut self, root: &MptNode) {
+        if !root.is_empty() && !root.is_digest() {
+            self.add(root.hash(), root);
+        }
+        self.add_children(root);
+    }
+
+    fn add(&mut self, hash: B256, node: &MptNode) -> bool {
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+        self.nodes.push(alloy_rlp::encode(node).into());
+        true
+    }
+
+    fn add_children(&mut self, node: &MptNode) {
+        let children: Vec<&MptNode> = match node.as_data() {
+            MptNodeData::Branch(children) => children.iter().flatten().map(|c| &**c).collect(),
+            MptNodeData::Extension(_, child) => vec![&**child],
+            MptNodeData::Null | MptNodeData::Leaf(..) | MptNodeData::Digest(_) => return,
+        };
+        for child in children {
+            // Nodes with a short encoding are part of the encoding of their parent
+            if let MptNodeReference::Digest(hash) = child.reference() {
+                if child.is_digest() || !self.add(hash, child) {
+                    continue;
+                }
+            }
+            self.add_children(child);
+        }
+    }
+}
+
+impl CompactWitness {
+    pub fn new(mut input: GuestInput) -> Self {
+        let mut table = NodeTable::default();
+        let state_trie = core::mem::take(&mut input.parent_state_trie);
+        table.add_trie(&state_trie);
+
+        let mut storage = Vec::with_capacity(input.parent_storage.len());
+        for (address, (storage_trie, slots)) in core::mem::take(&mut input.parent_storage) {
+            table.add_trie(&storage_trie);
+            storage.push((address, slots));
+        }
+
+        let mut contracts: Vec<(B256, Bytes)> = core::mem::take(&mut input.contracts)
+            .into_iter()
+            .map(|code| (keccak(&code).into(), code))
+            .collect();
+        contracts.sort_by_key(|(hash, _)| *hash);
+        contracts.dedup_by_key(|(hash, _)| *hash);
+
+        Self {
+            input,
+            nodes: table.nodes,
+            state_root: state_trie.hash(),
+            storage,
+            contracts: contracts.into_iter().map(|(_, code)| code).collect(),
+        }
+    }
+
+    /// Rebuilds the input. The rebuild is eager: every node in the table is hashed and the
+    /// state and storage tries are fully resolved before the block is built. A node is only
+    /// used for a reference if its hash matches, so the roots of the rebuilt tries are still
+    /// checked against the parent header when the block is built. The savings over the full
+    /// input are in the encoded size and in decoding every shared node once, not in skipping
+    /// unused parts of the tries, which the host already leaves out.
+    pub fn into_input(self) -> Result<GuestInput> {
+        let nodes: HashMap<B256, &[u8]> = self
+            .nodes
+            .iter()
+            .map(|node| (B256::from(keccak(node)), node.as_ref()))
+            .collect();
+
+        let mut input = self.input;
+        input.parent_state_trie = resolve(self.state_root, &nodes)?;
+        input.parent_storage = HashMap::with_capacity(self.storage.len());
+        for (address, slots) in self.storage {
+            let storage_root = input
+                .parent_state_trie
+                .get_rlp::<StateAccount>(&keccak(address))?
+                .map_or(EMPTY_ROOT, |account| account.storage_root);
+            let storage_trie = resolve(storage_root, &nodes)?;
+            input.parent_storage.insert(address, (storage_trie, slots));
+        }
+        input.contracts = self.contracts;
+        Ok(input)
+    }
+}
+
+/// Returns the trie with the given root, with every node found in `nodes` resolved.
+fn resolve(root: B256, nodes: &HashMap<B256, &[u8]>) -> Result<MptNode> {
+    if root == EMPTY_ROOT {
+        return Ok(MptNode::default());
+    }
+    resolve_node(MptNodeData::Digest(root).into(), nodes)
+}
+
+fn resolve_node(node: MptNode, nodes: &HashMap<B256, &[u8]>) -> Result<MptNode> {
+    let data = match node.as_data() {
+        MptNodeData::Null | MptNodeData::Leaf(..) => return Ok(node),
+        MptNodeData::Digest(hash) => {
+            let Some(encoded) = nodes.get(hash) else {
+                // Parts of the trie that are not needed are not in the witness
+                return Ok(node);
+            };
+            return resolve_node(MptNode::decode(encoded)?, nodes);
+        }
+        MptNodeData::Branch(children) => {
+            let mut resolved: [Option<Box<MptNode>>; 16] = Default::default();
+            for (resolved, child) in resolved.iter_mut().zip(children) {
+                if let Some(child) = child {
+                    *resolved = Some(Box::new(resolve_node((**child).clone(), nodes)?));
+                }
+            }
+            MptNodeData::Branch(resolved)
+        }
+        MptNodeData::Extension(prefix, child) => MptNodeData::Extension(
+            prefix.clone(),
+            Box::new(resolve_node((**child).clone(), nodes)?),
+        ),
+    };
+    Ok(data.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage_trie(num_slots: u64) -> MptNode {
+        let mut trie = MptNode::default();
+        for slot in 0..num_slots {
+            trie.insert_rlp(
+                &keccak(U256::from(slot).to_be_bytes::<32>()),
+                U256::from(slot + 1),
+            )
+            .unwrap();
+        }
+        trie
+    }
+
+    fn table_len(trie: &MptNode) -> usize {
+        let mut table = NodeTable::default();
+        table.add_trie(trie);
+        table.nodes.len()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        // Two accounts with the same storage share all their storage nodes
+        let storage = storage_trie(64);
+        let code = Bytes::from(vec![0x60, 0x00, 0x56]);
+        let accounts = [Address::with_last_byte(1), Address::with_last_byte(2)];
+
+        let mut input = GuestInput::default();
+        for address in accounts {
+            let account = StateAccount {
+                storage_root: storage.hash(),
+                code_hash: keccak(&code).into(),
+                ..Default::default()
+            };
+            input
+                .parent_state_trie
+                .insert_rlp(&keccak(address), account)
+                .unwrap();
+            input
+                .parent_storage
+                .insert(address, (storage.clone(), vec![U256::from(1)]));
+            input.contracts.push(code.clone());
+        }
+        // An account that doesn't exist
+        let missing = Address::with_last_byte(3);
+        input
+            .parent_storage
+            .insert(missing, (MptNode::default(), vec![U256::from(1)]));
+
+        let witness = CompactWitness::new(input.clone());
+        assert_eq!(witness.contracts, [code]);
+        assert_eq!(
+            witness.nodes.len(),
+            table_len(&input.parent_state_trie) + table_len(&storage)
+        );
+
+        let rebuilt = witness.into_input().unwrap();
+        assert_eq!(
+            rebuilt.parent_state_trie.hash(),
+            input.parent_state_trie.hash()
+        );
+        for address in accounts {
+            let (trie, slots) = &rebuilt.parent_storage[&address];
+            assert_eq!(trie.hash(), storage.hash());
+            assert_eq!(slots, &[U256::from(1)]);
+            for slot in 0..64u64 {
+                let value: Option<U256> = trie
+                    .get_rlp(&keccak(U256::from(slot).to_be_bytes::<32>()))
+                    .unwrap();
+                assert_eq!(value, Some(U256::from(slot + 1)));
+            }
+        }
+        assert!(rebuilt.parent_storage[&missing].0.is_empty());
+    }
+
+    #[test]
+    fn test_sparse_trie() {
+        // Only the first child of the root is resolved
+        let full = storage_trie(64);
+        let MptNodeData::Branch(children) = full.as_data() else {
+            panic!("expected a branch");
+        };
+        let mut pruned = children.clone();
+        for child in pruned.iter_mut().skip(1).flatten() {
+            *child = Box::new(MptNodeData::Digest(child.hash()).into());
+        }
+        let sparse: MptNode = MptNodeData::Branch(pruned).into();
+        assert_eq!(sparse.hash(), full.hash());
+
+        let mut table = NodeTable::default();
+        table.add_trie(&sparse);
+        let nodes: HashMap<B256, &[u8]> = table
+            .nodes
+            .iter()
+            .map(|node| (B256::from(keccak(node)), node.as_ref()))
+            .collect();
+        let resolved = resolve(sparse.hash(), &nodes).unwrap();
+        assert_eq!(resolved.hash(), full.hash());
+        let MptNodeData::Branch(children) = resolved.as_data() else {
+            panic!("expected a branch");
+        };
+        let children: Vec<&MptNode> = children.iter().flatten().map(|c| &**c).collect();
+        assert!(!children[0].is_digest());
+        assert!(children[1..].iter().all(|child| child.is_digest()));
+    }
+}
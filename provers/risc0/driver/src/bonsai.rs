@@ -93,15 +93,8 @@ pub async fn maybe_prove<I: Serialize, O: Eq + Debug + Serialize + DeserializeOw
 ) -> Option<(String, Receipt)> {
     let (assumption_instances, assumption_uuids) = assumptions;
 
-    let encoded_output =
-        to_vec(expected_output).expect("Could not serialize expected proving output!");
     let computed_image_id = compute_image_id(elf).expect("Failed to compute elf image id!");
-
-    let receipt_label = format!(
-        "{}-{}",
-        hex::encode(computed_image_id),
-        hex::encode(keccak(bytemuck::cast_slice(&encoded_output)))
-    );
+    let receipt_label = receipt_label(computed_image_id, expected_output);
 
     // get receipt
     let (mut receipt_uuid, receipt, cached) =
@@ -174,6 +167,35 @@ pub async fn maybe_prove<I: Serialize, O: Eq + Debug + Serialize + DeserializeOw
     Some(result)
 }
 
+/// The label a receipt of the given program and output is cached under.
+pub fn receipt_label<O: Serialize>(image_id: Digest, output: &O) -> String {
+    let encoded_output = to_vec(output).expect("Could not serialize expected proving output!");
+    format!(
+        "{}-{}",
+        hex::encode(image_id),
+        hex::encode(keccak(bytemuck::cast_slice(&encoded_output)))
+    )
+}
+
+/// Downloads the receipt of a finished Bonsai session.
+pub async fn download_bonsai_receipt(uuid: String) -> anyhow::Result<Receipt> {
+    let client = bonsai_sdk::alpha_async::get_client_from_env(risc0_zkvm::VERSION).await?;
+    let session = bonsai_sdk::alpha::SessionId { uuid };
+    let res = session.status(&client)?;
+    if res.status != "SUCCEEDED" {
+        anyhow::bail!(
+            "Session {} has no receipt, status: {}",
+            session.uuid,
+            res.status
+        );
+    }
+    let receipt_url = res
+        .receipt_url
+        .ok_or_else(|| anyhow::anyhow!("API error, missing receipt on completed session"))?;
+    let receipt_buf = client.download(&receipt_url)?;
+    Ok(bincode::deserialize(&receipt_buf)?)
+}
+
 pub async fn upload_receipt(receipt: &Receipt) -> anyhow::Result<String> {
     let client = bonsai_sdk::alpha_async::get_client_from_env(risc0_zkvm::VERSION).await?;
     Ok(client.upload_receipt(bincode::serialize(receipt)?)?)
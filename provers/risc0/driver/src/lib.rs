@@ -7,12 +7,22 @@ use alloy_sol_types::SolValue;
 use hex::ToHex;
 
 use raiko_lib::{
-    input::{GuestInput, GuestOutput},
+    input::{
+        AggregationGuestOutput, BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput,
+        ZkAggregationGuestInput,
+    },
     protocol_instance::ProtocolInstance,
-    prover::{to_proof, Proof, Prover, ProverConfig, ProverResult},
+    prover::{
+        to_proof, GuestEstimate, Proof, Prover, ProverConfig, ProverError, ProverResult,
+        ProvingTimeModel,
+    },
+    witness::{GuestWitness, WitnessFormat},
 };
 use raiko_primitives::keccak::keccak;
-use risc0_zkvm::{serde::to_vec, sha::Digest};
+use risc0_zkvm::{
+    compute_image_id, serde::to_vec, sha::Digest, Assumption, ExecutorEnv, ExecutorImpl,
+    NullSegmentRef, Receipt,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use tracing::info as traicing_info;
@@ -23,7 +33,11 @@ pub mod snarks;
 use crate::snarks::verify_groth16_snark;
 use bonsai::maybe_prove;
 pub use bonsai::*;
-use methods::risc0_guest::{RISC0_GUEST_ELF, RISC0_GUEST_ID};
+use methods::{
+    risc0_aggregation::RISC0_AGGREGATION_ELF,
+    risc0_batch::RISC0_BATCH_ELF,
+    risc0_guest::{RISC0_GUEST_ELF, RISC0_GUEST_ID},
+};
 
 #[serde_as]
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -32,11 +46,38 @@ pub struct Risc0Param {
     pub snark: bool,
     pub profile: bool,
     pub execution_po2: u32,
+    /// Overrides the default model used to estimate the proving time.
+    #[serde(default)]
+    pub proving_time_model: Option<ProvingTimeModel>,
 }
 
+/// The segment size used for estimates when no prover params are given.
+const DEFAULT_EXECUTION_PO2: u32 = 20;
+
+/// Rough default for the proving time of a local CPU prover with `execution_po2 = 20`. It's
+/// an order of magnitude, not a benchmark result: set `proving_time_model` in the prover params
+/// after timing a few proofs on the proving machine.
+const LOCAL_PROVING_TIME_MODEL: ProvingTimeModel = ProvingTimeModel {
+    base_secs: 5.0,
+    secs_per_segment: 45.0,
+};
+
+/// Rough default for the proving time on Bonsai, which proves the segments in parallel. Like
+/// the local model it should be overridden with `proving_time_model`.
+const BONSAI_PROVING_TIME_MODEL: ProvingTimeModel = ProvingTimeModel {
+    base_secs: 60.0,
+    secs_per_segment: 1.5,
+};
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Risc0Response {
     pub proof: String,
+    /// The Groth16 seal with the image ID and post state digest in the format of the on-chain
+    /// verifier, when a SNARK was created.
+    pub seal: Option<String>,
+    /// The Bonsai session UUID of the receipt, if it's known to Bonsai.
+    #[serde(default)]
+    pub uuid: Option<String>,
 }
 pub struct Risc0Prover;
 
@@ -46,12 +87,16 @@ impl Prover for Risc0Prover {
         output: &GuestOutput,
         config: &ProverConfig,
     ) -> ProverResult<Proof> {
-        let config = Risc0Param::deserialize(config.get("risc0").unwrap()).unwrap();
+        let witness = GuestWitness::new(
+            input,
+            WitnessFormat::from_config(config).map_err(|e| e.to_string())?,
+        );
+        let config = risc0_param(config)?;
 
         println!("elf code length: {}", RISC0_GUEST_ELF.len());
-        let encoded_input = to_vec(&input).expect("Could not serialize proving input!");
+        let encoded_input = to_vec(&witness).expect("Could not serialize proving input!");
 
-        let result = maybe_prove::<GuestInput, GuestOutput>(
+        let result = maybe_prove::<GuestWitness, GuestOutput>(
             &config,
             encoded_input,
             RISC0_GUEST_ELF,
@@ -60,30 +105,325 @@ impl Prover for Risc0Prover {
         )
         .await;
 
-        let journal: String = result.clone().unwrap().1.journal.encode_hex();
+        let (uuid, receipt) = result
+            .clone()
+            .ok_or_else(|| "Risc0: failed to prove the block".to_owned())?;
+        let journal: String = receipt.journal.encode_hex();
 
         // Create/verify Groth16 SNARK
-        if config.snark {
-            let Some((stark_uuid, stark_receipt)) = result else {
-                panic!("No STARK data to snarkify!");
-            };
-            let image_id = Digest::from(RISC0_GUEST_ID);
-            let (snark_uuid, snark_receipt) =
-                snarks::stark2snark(image_id, stark_uuid, stark_receipt)
-                    .await
-                    .map_err(|err| format!("Failed to convert STARK to SNARK: {err:?}"))?;
+        let seal = if config.snark {
+            Some(maybe_snark(Digest::from(RISC0_GUEST_ID), result).await?)
+        } else {
+            None
+        };
+
+        to_proof(Ok(Risc0Response {
+            proof: journal,
+            seal,
+            uuid: (!uuid.is_empty()).then_some(uuid),
+        }))
+    }
+
+    async fn batch_run(
+        batch: BatchGuestInput,
+        output: &BatchGuestOutput,
+        config: &ProverConfig,
+    ) -> ProverResult<Proof> {
+        let config = risc0_param(config)?;
+        let encoded_input =
+            to_vec(&batch).map_err(|err| format!("Risc0: failed to encode the batch: {err:?}"))?;
+
+        let result = maybe_prove::<BatchGuestInput, BatchGuestOutput>(
+            &config,
+            encoded_input,
+            RISC0_BATCH_ELF,
+            output,
+            Default::default(),
+        )
+        .await;
 
-            traicing_info!("Validating SNARK uuid: {snark_uuid}");
+        let (uuid, receipt) = result
+            .clone()
+            .ok_or_else(|| "Risc0: failed to prove the batch".to_owned())?;
+        let journal: String = receipt.journal.encode_hex();
 
-            verify_groth16_snark(image_id, snark_receipt)
+        let seal = if config.snark {
+            let batch_image_id = compute_image_id(RISC0_BATCH_ELF)
+                .map_err(|err| format!("Failed to compute batch image id: {err:?}"))?;
+            Some(maybe_snark(batch_image_id, result).await?)
+        } else {
+            None
+        };
+
+        to_proof(Ok(Risc0Response {
+            proof: journal,
+            seal,
+            uuid: (!uuid.is_empty()).then_some(uuid),
+        }))
+    }
+
+    async fn verify(
+        _input: GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+        _config: &ProverConfig,
+    ) -> ProverResult<()> {
+        let response = Risc0Response::deserialize(proof)
+            .map_err(|err| format!("Risc0: invalid proof: {err}"))?;
+        let image_id = Digest::from(RISC0_GUEST_ID);
+
+        // The response only contains the journal, the receipt is cached or on Bonsai
+        let label = receipt_label(image_id, output);
+        let receipt = match (load_receipt::<Receipt>(&label), response.uuid) {
+            (Ok(Some((_, receipt))), _) => receipt,
+            (_, Some(uuid)) => download_bonsai_receipt(uuid.clone())
                 .await
-                .map_err(|err| format!("Failed to verify SNARK: {err:?}"))?;
+                .map_err(|err| format!("Risc0: failed to download receipt {uuid}: {err:?}"))?,
+            (Err(err), None) => {
+                return Err(format!("Risc0: failed to load receipt {label}: {err:?}").into())
+            }
+            (Ok(None), None) => return Err(format!("Risc0: no receipt {label}").into()),
+        };
+        receipt
+            .verify(image_id)
+            .map_err(|err| format!("Risc0: receipt verification failed: {err:?}"))?;
+
+        let journal: String = receipt.journal.encode_hex();
+        if journal != response.proof.trim_start_matches("0x") {
+            return Err(ProverError::GuestError(
+                "Risc0: the proof journal differs from the receipt".to_owned(),
+            ));
         }
+        let guest_output: GuestOutput = receipt
+            .journal
+            .decode()
+            .map_err(|err| format!("Risc0: failed to decode the journal: {err:?}"))?;
+        if &guest_output != output {
+            return Err(ProverError::GuestError(format!(
+                "Risc0: output mismatch, proven: {guest_output:?}, expected: {output:?}"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn estimate(input: GuestInput, config: &ProverConfig) -> ProverResult<GuestEstimate> {
+        let param = config
+            .get("risc0")
+            .map(Risc0Param::deserialize)
+            .transpose()
+            .map_err(|err| format!("Risc0: invalid prover params: {err}"))?;
+        let segment_po2 = param
+            .as_ref()
+            .map_or(DEFAULT_EXECUTION_PO2, |param| param.execution_po2);
+        let witness = GuestWitness::new(
+            input,
+            WitnessFormat::from_config(config).map_err(|e| e.to_string())?,
+        );
+        let encoded_input = to_vec(&witness).expect("Could not serialize proving input!");
 
-        to_proof(Ok(Risc0Response { proof: journal }))
+        let env = ExecutorEnv::builder()
+            .session_limit(None)
+            .segment_limit_po2(segment_po2)
+            .write_slice(&encoded_input)
+            .build()
+            .map_err(|err| format!("Risc0: failed to build executor env: {err:?}"))?;
+        let mut exec = ExecutorImpl::from_elf(env, RISC0_GUEST_ELF)
+            .map_err(|err| format!("Risc0: failed to load the guest: {err:?}"))?;
+        // Only the cycle counts are needed, so the segments are dropped right away
+        let session = exec
+            .run_with_callback(|_| Ok(Box::new(NullSegmentRef {})))
+            .map_err(|err| format!("Risc0: execution failed: {err:?}"))?;
+
+        let segments = session.segments.len() as u64;
+        let model = match &param {
+            Some(Risc0Param {
+                proving_time_model: Some(model),
+                ..
+            }) => *model,
+            Some(param) if param.bonsai => BONSAI_PROVING_TIME_MODEL,
+            _ => LOCAL_PROVING_TIME_MODEL,
+        };
+        traicing_info!(
+            "Risc0: executed the guest in {} cycles and {segments} segments",
+            session.total_cycles
+        );
+        Ok(GuestEstimate {
+            total_cycles: session.total_cycles,
+            segments,
+            segment_po2,
+            // The executor of this RISC0 version does not count the accelerated syscalls
+            precompile_calls: None,
+            peak_memory: None,
+            proving_time_secs: model.proving_time_secs(segments),
+        })
     }
 }
 
+/// Where the receipt of a block proof is taken from for aggregation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BlockReceipt {
+    /// The receipt in the local receipt cache for the block proof with this output.
+    Cached(GuestOutput),
+    /// The receipt of the Bonsai session with this UUID.
+    Bonsai(String),
+}
+
+impl BlockReceipt {
+    /// Where to take the receipt of a block proof created by [Risc0Prover::run] from.
+    pub fn from_proof(output: GuestOutput, proof: &Proof) -> ProverResult<Self> {
+        let response = Risc0Response::deserialize(proof)
+            .map_err(|err| format!("Risc0: invalid proof: {err}"))?;
+        Ok(match response.uuid {
+            Some(uuid) => BlockReceipt::Bonsai(uuid),
+            None => BlockReceipt::Cached(output),
+        })
+    }
+}
+
+impl Risc0Prover {
+    /// Aggregates the proofs of multiple blocks into a single proof. The block receipts are
+    /// verified inside the aggregation guest, which commits to the hash chain of the instance
+    /// hashes of all blocks.
+    pub async fn aggregate(
+        receipts: Vec<BlockReceipt>,
+        config: &ProverConfig,
+    ) -> ProverResult<Proof> {
+        let config = risc0_param(config)?;
+        let image_id = Digest::from(RISC0_GUEST_ID);
+
+        let mut block_outputs = Vec::with_capacity(receipts.len());
+        let mut assumption_instances: Vec<Assumption> = Vec::with_capacity(receipts.len());
+        let mut assumption_uuids = Vec::with_capacity(receipts.len());
+        for block_receipt in receipts {
+            let (uuid, receipt) = match block_receipt {
+                BlockReceipt::Cached(output) => {
+                    let label = receipt_label(image_id, &output);
+                    load_receipt::<Receipt>(&label)
+                        .map_err(|err| format!("Failed to load receipt {label}: {err:?}"))?
+                        .ok_or_else(|| format!("No cached receipt {label}"))?
+                }
+                BlockReceipt::Bonsai(uuid) => {
+                    let receipt = download_bonsai_receipt(uuid.clone())
+                        .await
+                        .map_err(|err| format!("Failed to download receipt {uuid}: {err:?}"))?;
+                    (uuid, receipt)
+                }
+            };
+            receipt
+                .verify(image_id)
+                .map_err(|err| format!("Failed to verify block receipt: {err:?}"))?;
+            let output: GuestOutput = receipt
+                .journal
+                .decode()
+                .map_err(|err| format!("Failed to decode block receipt journal: {err:?}"))?;
+            // Bonsai can only use receipts it knows about as assumptions
+            let uuid = if config.bonsai && uuid.is_empty() {
+                upload_receipt(&receipt)
+                    .await
+                    .map_err(|err| format!("Failed to upload receipt to Bonsai: {err:?}"))?
+            } else {
+                uuid
+            };
+            block_outputs.push(output);
+            assumption_instances.push(receipt.into());
+            assumption_uuids.push(uuid);
+        }
+
+        let input = ZkAggregationGuestInput {
+            image_id: RISC0_GUEST_ID,
+            block_outputs,
+        };
+        let output = AggregationGuestOutput::new(RISC0_GUEST_ID, &input.block_outputs)
+            .map_err(|err| format!("Failed to aggregate block proofs: {err:?}"))?;
+        let encoded_input = to_vec(&input)
+            .map_err(|err| format!("Failed to encode the aggregation input: {err:?}"))?;
+
+        let result = maybe_prove::<ZkAggregationGuestInput, AggregationGuestOutput>(
+            &config,
+            encoded_input,
+            RISC0_AGGREGATION_ELF,
+            &output,
+            (assumption_instances, assumption_uuids),
+        )
+        .await;
+
+        let (uuid, receipt) = result
+            .clone()
+            .ok_or_else(|| "Risc0: failed to prove the aggregation".to_owned())?;
+        let proven: AggregationGuestOutput = receipt
+            .journal
+            .decode()
+            .map_err(|err| format!("Risc0: failed to decode the aggregation journal: {err:?}"))?;
+        check_aggregation_output(&proven, &output)?;
+        let journal: String = receipt.journal.encode_hex();
+
+        // Wrap the aggregated proof in a single Groth16 SNARK
+        let seal = if config.snark {
+            let aggregation_image_id = compute_image_id(RISC0_AGGREGATION_ELF)
+                .map_err(|err| format!("Failed to compute aggregation image id: {err:?}"))?;
+            Some(maybe_snark(aggregation_image_id, result).await?)
+        } else {
+            None
+        };
+
+        to_proof(Ok(Risc0Response {
+            proof: journal,
+            seal,
+            uuid: (!uuid.is_empty()).then_some(uuid),
+        }))
+    }
+}
+
+/// Checks the output of an aggregation proof. The aggregation guest verifies the block proofs
+/// against the image id in its input, so the proof is only valid if that is the image id of
+/// the block guest.
+pub fn check_aggregation_output(
+    proven: &AggregationGuestOutput,
+    expected: &AggregationGuestOutput,
+) -> ProverResult<()> {
+    if proven.image_id != RISC0_GUEST_ID {
+        return Err(ProverError::GuestError(format!(
+            "Risc0: the block proofs were aggregated for image id {:?} instead of the block guest",
+            proven.image_id
+        )));
+    }
+    if proven != expected {
+        return Err(ProverError::GuestError(format!(
+            "Risc0: aggregation output mismatch, proven: {proven:?}, expected: {expected:?}"
+        )));
+    }
+    Ok(())
+}
+
+/// Reads the RISC0 prover params from the config.
+fn risc0_param(config: &ProverConfig) -> ProverResult<Risc0Param> {
+    let param = config
+        .get("risc0")
+        .ok_or_else(|| "Risc0: missing prover params".to_owned())?;
+    Risc0Param::deserialize(param)
+        .map_err(|err| format!("Risc0: invalid prover params: {err}").into())
+}
+
+/// Converts the STARK receipt to a Groth16 SNARK, verifies it and returns the hex encoded
+/// proof data for the on-chain verifier.
+async fn maybe_snark(image_id: Digest, result: Option<(String, Receipt)>) -> ProverResult<String> {
+    let Some((stark_uuid, stark_receipt)) = result else {
+        return Err("No STARK data to snarkify!".to_owned().into());
+    };
+    let (snark_uuid, snark_receipt) = snarks::stark2snark(image_id, stark_uuid, stark_receipt)
+        .await
+        .map_err(|err| format!("Failed to convert STARK to SNARK: {err:?}"))?;
+
+    traicing_info!("Validating SNARK uuid: {snark_uuid}");
+
+    let verifier_data = snarks::encode_verifier_data(image_id, &snark_receipt)
+        .map_err(|err| format!("Failed to encode SNARK: {err:?}"))?;
+    verify_groth16_snark(image_id, snark_receipt)
+        .await
+        .map_err(|err| format!("Failed to verify SNARK: {err:?}"))?;
+    Ok(format!("0x{}", hex::encode(verifier_data)))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -98,4 +438,21 @@ mod test {
         let receipt = prover.prove(env, TEST_RISC0_GUEST_ELF).unwrap();
         receipt.verify(TEST_RISC0_GUEST_ID).unwrap();
     }
+
+    #[test]
+    fn test_check_aggregation_output() {
+        let block_outputs = [GuestOutput::Success {
+            header: Default::default(),
+            hash: B256::with_last_byte(1),
+        }];
+        let expected = AggregationGuestOutput::new(RISC0_GUEST_ID, &block_outputs).unwrap();
+        check_aggregation_output(&expected, &expected).unwrap();
+
+        // Block proofs verified against another image id can't be trusted
+        let other_image = AggregationGuestOutput::new([0; 8], &block_outputs).unwrap();
+        assert!(check_aggregation_output(&other_image, &expected).is_err());
+
+        let other_blocks = AggregationGuestOutput::new(RISC0_GUEST_ID, &[]).unwrap();
+        assert!(check_aggregation_output(&other_blocks, &expected).is_err());
+    }
 }
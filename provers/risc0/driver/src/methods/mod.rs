@@ -4,7 +4,11 @@ cfg_if::cfg_if! {
         pub mod ecdsa;
         pub mod sha256;
         pub mod risc0_guest;
+        pub mod risc0_aggregation;
+        pub mod risc0_batch;
     } else {
         pub mod risc0_guest;
+        pub mod risc0_aggregation;
+        pub mod risc0_batch;
     }
 }
@@ -0,0 +1,4 @@
+pub const RISC0_AGGREGATION_ELF: &[u8] =
+    include_bytes!("../../../guest/target/riscv32im-risc0-zkvm-elf/release/risc0-aggregation");
+pub const RISC0_AGGREGATION_PATH: &str =
+    r#"provers/risc0/guest/target/riscv32im-risc0-zkvm-elf/release/risc0-aggregation"#;
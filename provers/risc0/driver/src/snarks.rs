@@ -14,7 +14,7 @@
 
 use std::{str::FromStr, sync::Arc};
 
-use alloy_primitives::U256;
+use alloy_primitives::{Bytes, B256, U256};
 use alloy_sol_types::{sol, SolValue};
 use bonsai_sdk::alpha::responses::SnarkReceipt;
 use ethers_contract::abigen;
@@ -88,6 +88,22 @@ impl From<Groth16Seal> for Seal {
 use raiko_primitives::keccak::keccak;
 use risc0_zkvm::Receipt;
 
+/// Encodes the proof data expected by the on-chain RISC0 verifier:
+/// `abi.encode(seal, imageId, postStateDigest)`.
+pub fn encode_verifier_data(
+    image_id: Digest,
+    snark_receipt: &SnarkReceipt,
+) -> anyhow::Result<Vec<u8>> {
+    let seal = <Groth16Seal as Into<Seal>>::into(snark_receipt.snark.clone()).abi_encode();
+    let post_state_digest = B256::try_from(snark_receipt.post_state_digest.as_slice())?;
+    Ok((
+        Bytes::from(seal),
+        B256::from_slice(image_id.as_bytes()),
+        post_state_digest,
+    )
+        .abi_encode_params())
+}
+
 pub async fn stark2snark(
     image_id: Digest,
     stark_uuid: String,
@@ -0,0 +1,24 @@
+#![no_main]
+harness::entrypoint!(main);
+use risc0_zkvm::{guest::env, serde::to_vec};
+
+use raiko_lib::input::{AggregationGuestOutput, ZkAggregationGuestInput};
+
+fn main() {
+    let input: ZkAggregationGuestInput = env::read();
+
+    // Verify the proofs of all blocks, the receipts are passed in as assumptions
+    for block_output in &input.block_outputs {
+        let journal = to_vec(block_output)
+            .expect("Failed to serialize the block output")
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<u8>>();
+        env::verify(input.image_id, &journal).expect("Failed to verify the block proof");
+    }
+
+    let output = AggregationGuestOutput::new(input.image_id, &input.block_outputs)
+        .expect("Failed to aggregate the block proofs");
+
+    env::commit(&output);
+}
@@ -0,0 +1,36 @@
+#![no_main]
+harness::entrypoint!(main);
+use risc0_zkvm::guest::env;
+
+use raiko_lib::{
+    builder::ChainStrategy,
+    consts::VerifierType,
+    input::{BatchGuestInput, BatchGuestOutput},
+    protocol_instance::BatchProtocolInstance,
+};
+use revm_precompile::zk_op::ZkOperation;
+use zk_op::Risc0Operator;
+
+pub mod mem;
+pub use mem::*;
+
+fn main() {
+    let batch: BatchGuestInput = env::read();
+
+    revm_precompile::zk_op::ZKVM_OPERATOR.get_or_init(|| Box::new(Risc0Operator {}));
+    revm_precompile::zk_op::ZKVM_OPERATIONS
+        .set(Box::new(vec![ZkOperation::Sha256, ZkOperation::Secp256k1]))
+        .expect("Failed to set ZkvmOperations");
+
+    let output = match ChainStrategy::build_batch_from(&batch) {
+        Ok(headers) => {
+            let hash = BatchProtocolInstance::new(&batch, &headers, VerifierType::RISC0)
+                .expect("Failed to assemble the batch protocol instance")
+                .instance_hash();
+            BatchGuestOutput::Success { headers, hash }
+        }
+        Err(_) => BatchGuestOutput::Failure,
+    };
+
+    env::commit(&output);
+}
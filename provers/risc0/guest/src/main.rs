@@ -5,8 +5,9 @@ use risc0_zkvm::guest::env;
 use raiko_lib::protocol_instance::ProtocolInstance;
 use raiko_lib::{
     consts::VerifierType,
-    builder::{BlockBuilderStrategy, TaikoStrategy},
-    input::{GuestInput, GuestOutput},
+    builder::ChainStrategy,
+    input::GuestOutput,
+    witness::GuestWitness,
 };
 use revm_precompile::zk_op::ZkOperation;
 use zk_op::Risc0Operator;
@@ -18,14 +19,15 @@ use harness::*;
 pub use mem::*;
 
 fn main() {
-    let input: GuestInput = env::read();
+    let witness: GuestWitness = env::read();
+    let input = witness.into_input().expect("Failed to rebuild the input");
 
     revm_precompile::zk_op::ZKVM_OPERATOR.get_or_init(|| Box::new(Risc0Operator {}));
     revm_precompile::zk_op::ZKVM_OPERATIONS
         .set(Box::new(vec![ZkOperation::Sha256, ZkOperation::Secp256k1]))
         .expect("Failed to set ZkvmOperations");
 
-    let build_result = TaikoStrategy::build_from(&input);
+    let build_result = ChainStrategy::build_from(&input);
 
     let output = match &build_result {
         Ok((header, _mpt_node)) => {
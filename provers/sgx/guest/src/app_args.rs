@@ -17,6 +17,8 @@ pub struct App {
 pub enum Command {
     /// Prove (i.e. sign) a single block and exit.
     OneShot(OneShotArgs),
+    /// Prove (i.e. sign) a batch of consecutive blocks with a single signature and exit.
+    OneShotBatch(OneShotArgs),
     /// Bootstrap the application and then exit. The bootstrapping process generates the
     /// initial public-private key pair and stores it on the disk in an encrypted
     /// format using SGX encryption primitives.
@@ -8,7 +8,7 @@ extern crate secp256k1;
 use anyhow::{anyhow, Result};
 use app_args::{App, Command};
 use clap::Parser;
-use one_shot::{bootstrap, load_bootstrap, one_shot};
+use one_shot::{bootstrap, load_bootstrap, one_shot, one_shot_batch};
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
@@ -19,6 +19,10 @@ pub async fn main() -> Result<()> {
             println!("Starting one shot mode");
             one_shot(args.global_opts, one_shot_args).await?
         }
+        Command::OneShotBatch(one_shot_args) => {
+            println!("Starting one shot batch mode");
+            one_shot_batch(args.global_opts, one_shot_args).await?
+        }
         Command::Bootstrap => {
             println!("Bootstrapping the app");
             bootstrap(args.global_opts)?
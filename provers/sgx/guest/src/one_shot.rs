@@ -8,12 +8,12 @@ use std::{
 use anyhow::{anyhow, bail, Context, Error, Result};
 use base64_serde::base64_serde_type;
 use raiko_lib::{
-    builder::{BlockBuilderStrategy, TaikoStrategy},
+    builder::ChainStrategy,
     consts::VerifierType,
-    input::GuestInput,
-    protocol_instance::ProtocolInstance,
+    input::{BatchGuestInput, GuestInput},
+    protocol_instance::{BatchProtocolInstance, ProtocolInstance},
 };
-use raiko_primitives::Address;
+use raiko_primitives::{Address, B256};
 use secp256k1::{KeyPair, SecretKey};
 use serde::Serialize;
 
@@ -134,7 +134,7 @@ pub async fn one_shot(global_opts: GlobalOpts, args: OneShotArgs) -> Result<()>
 
     // Process the block
     let (header, _mpt_node) =
-        TaikoStrategy::build_from(&input).expect("Failed to build the resulting block");
+        ChainStrategy::build_from(&input).expect("Failed to build the resulting block");
 
     // Calculate the public input hash
     let pi = ProtocolInstance::new(&input, &header, VerifierType::SGX)?.sgx_instance(new_instance);
@@ -145,8 +145,53 @@ pub async fn one_shot(global_opts: GlobalOpts, args: OneShotArgs) -> Result<()>
         input.block_number
     );
 
+    sign_and_report(&prev_privkey, &args, pi_hash)
+}
+
+pub async fn one_shot_batch(global_opts: GlobalOpts, args: OneShotArgs) -> Result<()> {
+    // Make sure this SGX instance was bootstrapped
+    let prev_privkey = load_bootstrap(&global_opts.secrets_dir)
+        .or_else(|_| bail!("Application was not bootstrapped or has a deprecated bootstrap."))
+        .unwrap();
+
+    println!("Global options: {global_opts:?}, OneShotBatch options: {args:?}");
+
+    let new_pubkey = public_key(&prev_privkey);
+    let new_instance = public_key_to_address(&new_pubkey);
+
+    let batch: BatchGuestInput =
+        bincode::deserialize_from(std::io::stdin()).expect("unable to deserialize input");
+    assert!(batch
+        .inputs
+        .iter()
+        .all(|input| !input.taiko.skip_verify_blob));
+
+    // Process the blocks
+    let headers =
+        ChainStrategy::build_batch_from(&batch).expect("Failed to build the resulting blocks");
+
+    // Calculate the hash chain of the public input hashes of all blocks
+    let pi_hash = BatchProtocolInstance::new(&batch, &headers, VerifierType::SGX)?
+        .sgx_instance(new_instance)
+        .instance_hash();
+
+    println!(
+        "Blocks {}..={}. PI data to be signed: {pi_hash}",
+        batch.inputs[0].block_number,
+        batch.inputs[batch.inputs.len() - 1].block_number
+    );
+
+    sign_and_report(&prev_privkey, &args, pi_hash)
+}
+
+/// Signs the public input hash with the instance key and prints the proof for the onchain SGX
+/// verifier together with the attestation.
+fn sign_and_report(prev_privkey: &SecretKey, args: &OneShotArgs, pi_hash: B256) -> Result<()> {
+    let new_pubkey = public_key(prev_privkey);
+    let new_instance = public_key_to_address(&new_pubkey);
+
     // Sign the public input hash which contains all required block inputs and outputs
-    let sig = sign_message(&prev_privkey, pi_hash)?;
+    let sig = sign_message(prev_privkey, pi_hash)?;
 
     // Create the proof for the onchain SGX verifier
     const SGX_PROOF_LEN: usize = 89;
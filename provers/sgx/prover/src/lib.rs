@@ -9,20 +9,27 @@ use std::{
 
 use once_cell::sync::Lazy;
 use raiko_lib::{
-    input::{GuestInput, GuestOutput},
+    consts::VerifierType,
+    input::{BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput},
+    protocol_instance::ProtocolInstance,
     prover::{to_proof, Proof, Prover, ProverConfig, ProverError, ProverResult},
 };
+use raiko_primitives::{hex, Address, Signature, B256};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_with::serde_as;
 use tokio::{process::Command, sync::OnceCell};
 
 pub use crate::sgx_register_utils::{
-    get_instance_id, register_sgx_instance, remove_instance_id, set_instance_id,
+    get_instance_id, get_registered_instance, register_sgx_instance, remove_instance_id,
+    set_instance_id,
 };
 
 pub const PRIV_KEY_FILENAME: &str = "priv.key";
 
+/// Length of a SGX proof: 4b(id)+20b(pubkey)+65b(signature).
+const SGX_PROOF_LEN: usize = 89;
+
 // to register the instance id
 mod sgx_register_utils;
 
@@ -63,58 +70,8 @@ impl Prover for SgxProver {
     ) -> ProverResult<Proof> {
         let sgx_param = SgxParam::deserialize(config.get("sgx").unwrap()).unwrap();
 
-        // Support both SGX and the direct backend for testing
-        let direct_mode = match env::var("SGX_DIRECT") {
-            Ok(value) => value == "1",
-            Err(_) => false,
-        };
-
-        println!(
-            "WARNING: running SGX in {} mode!",
-            if direct_mode {
-                "direct (a.k.a. simulation)"
-            } else {
-                "hardware"
-            }
-        );
-
-        // The working directory
-        let mut cur_dir = env::current_exe()
-            .expect("Fail to get current directory")
-            .parent()
-            .unwrap()
-            .to_path_buf();
-
-        // When running in tests we might be in a child folder
-        if cur_dir.ends_with("deps") {
-            cur_dir = cur_dir.parent().unwrap().to_path_buf();
-        }
-
-        println!("Current directory: {cur_dir:?}\n");
-        // Working paths
-        PRIVATE_KEY
-            .get_or_init(|| async { cur_dir.join("secrets").join(PRIV_KEY_FILENAME) })
-            .await;
-        GRAMINE_MANIFEST_TEMPLATE
-            .get_or_init(|| async {
-                cur_dir
-                    .join(CONFIG)
-                    .join("sgx-guest.local.manifest.template")
-            })
-            .await;
-
-        // The gramine command (gramine or gramine-direct for testing in non-SGX environment)
-        let gramine_cmd = || -> StdCommand {
-            let mut cmd = if direct_mode {
-                StdCommand::new("gramine-direct")
-            } else {
-                let mut cmd = StdCommand::new("sudo");
-                cmd.arg("gramine-sgx");
-                cmd
-            };
-            cmd.current_dir(&cur_dir).arg(ELF_NAME);
-            cmd
-        };
+        let (cur_dir, direct_mode) = prepare_env().await;
+        let gramine = || gramine_cmd(&cur_dir, direct_mode);
 
         // Setup: run this once while setting up your SGX instance
         if sgx_param.setup {
@@ -122,7 +79,7 @@ impl Prover for SgxProver {
         }
 
         let mut sgx_proof = if sgx_param.bootstrap {
-            bootstrap(cur_dir.clone().join("secrets"), gramine_cmd()).await
+            bootstrap(cur_dir.clone().join("secrets"), gramine()).await
         } else {
             // Dummy proof: it's ok when only setup/bootstrap was requested
             Ok(SgxResponse::default())
@@ -130,11 +87,169 @@ impl Prover for SgxProver {
 
         if sgx_param.prove {
             // overwrite sgx_proof as the bootstrap quote stays the same in bootstrap & prove.
-            sgx_proof = prove(gramine_cmd(), input.clone(), sgx_param.instance_id).await
+            sgx_proof = prove(gramine(), "one-shot", input.clone(), sgx_param.instance_id).await
         }
 
         to_proof(sgx_proof)
     }
+
+    async fn batch_run(
+        batch: BatchGuestInput,
+        _output: &BatchGuestOutput,
+        config: &ProverConfig,
+    ) -> ProverResult<Proof> {
+        let sgx_param = config
+            .get("sgx")
+            .map(SgxParam::deserialize)
+            .transpose()
+            .map_err(|e| ProverError::GuestError(format!("Invalid SGX params: {e}")))?
+            .ok_or_else(|| ProverError::GuestError("Missing SGX params".to_owned()))?;
+
+        // The instance has to be set up and bootstrapped with a block proof request first
+        let (cur_dir, direct_mode) = prepare_env().await;
+        let sgx_proof = prove(
+            gramine_cmd(&cur_dir, direct_mode),
+            "one-shot-batch",
+            batch,
+            sgx_param.instance_id,
+        )
+        .await;
+
+        to_proof(sgx_proof)
+    }
+
+    async fn verify(
+        input: GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+        config: &ProverConfig,
+    ) -> ProverResult<()> {
+        let GuestOutput::Success { header, .. } = output else {
+            return Err(ProverError::GuestError(
+                "Cannot verify a proof of a block that failed to build".to_owned(),
+            ));
+        };
+        let response = SgxResponse::deserialize(proof)
+            .map_err(|e| ProverError::GuestError(format!("Invalid SGX proof: {e}")))?;
+        let proof = hex::decode(&response.proof)
+            .map_err(|e| ProverError::GuestError(format!("Invalid SGX proof: {e}")))?;
+        if proof.len() != SGX_PROOF_LEN {
+            return Err(ProverError::GuestError(format!(
+                "Invalid SGX proof length {}, expected {SGX_PROOF_LEN}",
+                proof.len()
+            )));
+        }
+        // The proof can only be trusted if it is signed by the instance registered on L1
+        // under the id in the proof
+        let instance_id = u32::from_be_bytes(proof[..4].try_into().unwrap());
+        let l1_rpc = config
+            .get("l1_rpc")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ProverError::GuestError("Missing L1 RPC".to_owned()))?;
+        let sgx_verifier = input
+            .chain_spec
+            .verifier_address
+            .get(&VerifierType::SGX)
+            .copied()
+            .flatten()
+            .ok_or_else(|| ProverError::GuestError("Missing SGX verifier address".to_owned()))?;
+        let registered = get_registered_instance(l1_rpc, sgx_verifier, instance_id)
+            .await
+            .map_err(|e| {
+                ProverError::GuestError(format!(
+                    "Could not resolve SGX instance {instance_id}: {e}"
+                ))
+            })?;
+
+        // The instance address is part of the signed public input
+        let instance = Address::from_slice(&proof[4..24]);
+        let instance_hash = ProtocolInstance::new(&input, header, VerifierType::SGX)
+            .map_err(|e| ProverError::GuestError(e.to_string()))?
+            .sgx_instance(instance)
+            .instance_hash();
+        verify_signature(&proof, instance_hash, registered)
+    }
+}
+
+/// Initializes the working paths and returns the working directory together with whether
+/// the guest runs in direct mode.
+async fn prepare_env() -> (PathBuf, bool) {
+    // Support both SGX and the direct backend for testing
+    let direct_mode = match env::var("SGX_DIRECT") {
+        Ok(value) => value == "1",
+        Err(_) => false,
+    };
+
+    println!(
+        "WARNING: running SGX in {} mode!",
+        if direct_mode {
+            "direct (a.k.a. simulation)"
+        } else {
+            "hardware"
+        }
+    );
+
+    // The working directory
+    let mut cur_dir = env::current_exe()
+        .expect("Fail to get current directory")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+    // When running in tests we might be in a child folder
+    if cur_dir.ends_with("deps") {
+        cur_dir = cur_dir.parent().unwrap().to_path_buf();
+    }
+
+    println!("Current directory: {cur_dir:?}\n");
+    // Working paths
+    PRIVATE_KEY
+        .get_or_init(|| async { cur_dir.join("secrets").join(PRIV_KEY_FILENAME) })
+        .await;
+    GRAMINE_MANIFEST_TEMPLATE
+        .get_or_init(|| async {
+            cur_dir
+                .join(CONFIG)
+                .join("sgx-guest.local.manifest.template")
+        })
+        .await;
+
+    (cur_dir, direct_mode)
+}
+
+/// The gramine command (gramine or gramine-direct for testing in non-SGX environment)
+fn gramine_cmd(cur_dir: &Path, direct_mode: bool) -> StdCommand {
+    let mut cmd = if direct_mode {
+        StdCommand::new("gramine-direct")
+    } else {
+        let mut cmd = StdCommand::new("sudo");
+        cmd.arg("gramine-sgx");
+        cmd
+    };
+    cmd.current_dir(cur_dir).arg(ELF_NAME);
+    cmd
+}
+
+/// Checks that the proof is for the registered instance and that its signature over the
+/// instance hash was created by that instance.
+fn verify_signature(proof: &[u8], instance_hash: B256, registered: Address) -> ProverResult<()> {
+    let instance = Address::from_slice(&proof[4..24]);
+    if instance != registered {
+        return Err(ProverError::GuestError(format!(
+            "The proof is for {instance} instead of the registered SGX instance {registered}"
+        )));
+    }
+    let signature = Signature::try_from(&proof[24..SGX_PROOF_LEN])
+        .map_err(|e| ProverError::GuestError(format!("Invalid SGX signature: {e}")))?;
+    let signer = signature
+        .recover_address_from_prehash(&instance_hash)
+        .map_err(|e| ProverError::GuestError(format!("Invalid SGX signature: {e}")))?;
+    if signer != registered {
+        return Err(ProverError::GuestError(format!(
+            "The proof is signed by {signer} instead of the registered SGX instance {registered}"
+        )));
+    }
+    Ok(())
 }
 
 async fn setup(cur_dir: &Path, direct_mode: bool) -> ProverResult<(), String> {
@@ -252,14 +367,17 @@ pub async fn bootstrap(
     .map_err(|e| ProverError::GuestError(e.to_string()))?
 }
 
-async fn prove(
+/// Runs the guest `command` with the input on stdin, `one-shot` for a block and
+/// `one-shot-batch` for a batch.
+async fn prove<I: Serialize + Send + 'static>(
     mut gramine_cmd: StdCommand,
-    input: GuestInput,
+    command: &'static str,
+    input: I,
     instance_id: u64,
 ) -> ProverResult<SgxResponse, ProverError> {
     tokio::task::spawn_blocking(move || {
         let mut child = gramine_cmd
-            .arg("one-shot")
+            .arg(command)
             .arg("--sgx-instance-id")
             .arg(instance_id.to_string())
             .stdin(Stdio::piped())
@@ -333,3 +451,33 @@ fn handle_output(output: &Output, name: &str) -> ProverResult<(), String> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature() {
+        // A proof created by the SGX guest: the first 4 bytes are the big-endian instance id
+        // (16777216), followed by the instance address and the signature
+        let proof = hex::decode("01000000c13bd882edb37ffbabc9f9e34a0d9789633b850fe55e625b768cc8e5feed7d9f7ab536cbc210c2fcc1385aaf88d8a91d8adc2740245f9deee5fd3d61dd2a71662fb6639515f1e2f3354361a82d86c1952352c1a81b").unwrap();
+        assert_eq!(u32::from_be_bytes(proof[..4].try_into().unwrap()), 16777216);
+        let registered = Address::from_slice(&proof[4..24]);
+        let instance_hash: B256 =
+            "0x216ac5cd5a5e13b0c9a81efb1ad04526b9f4ddd2fe6ebc02819c5097dfb0958c"
+                .parse()
+                .unwrap();
+        verify_signature(&proof, instance_hash, registered).unwrap();
+
+        // Signed over a different instance hash
+        assert!(verify_signature(&proof, B256::with_last_byte(1), registered).is_err());
+
+        // Claiming to be a different instance
+        let mut forged = proof.clone();
+        forged[4] ^= 1;
+        assert!(verify_signature(&forged, instance_hash, registered).is_err());
+
+        // A different instance is registered under the id
+        assert!(verify_signature(&proof, instance_hash, Address::with_last_byte(1)).is_err());
+    }
+}
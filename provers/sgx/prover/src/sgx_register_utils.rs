@@ -121,6 +121,12 @@ sol! {
         function registerInstance(ParsedV3QuoteStruct calldata _attestation)
             external
             returns (uint256);
+
+        #[derive(Debug)]
+        function instances(uint256 id)
+            external
+            view
+            returns (address addr, uint64 validSince);
     }
 }
 
@@ -333,6 +339,26 @@ pub async fn register_sgx_instance(
     Ok(sgx_id)
 }
 
+/// Looks up the address the instance with the given id is registered with in the SGX verifier
+/// on L1.
+pub async fn get_registered_instance(
+    l1_rpc_url: &str,
+    sgx_verifier_addr: Address,
+    instance_id: u32,
+) -> Result<Address> {
+    let http = Http::new(Url::parse(l1_rpc_url)?);
+    let provider: RootProvider<_> = RootProvider::new(RpcClient::new(http, false));
+    let sgx_verifier_contract = SgxVerifier::new(sgx_verifier_addr, &provider);
+    let instance = sgx_verifier_contract
+        .instances(U256::from(instance_id))
+        .call()
+        .await?;
+    if instance.addr.is_zero() {
+        anyhow::bail!("SGX instance {instance_id} is not registered");
+    }
+    Ok(instance.addr)
+}
+
 #[cfg(test)]
 mod test {
 
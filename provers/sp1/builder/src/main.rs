@@ -5,7 +5,7 @@ use std::path::PathBuf;
 
 fn main() {
     let pipeline = Sp1Pipeline::new("provers/sp1/guest", "release");
-    pipeline.bins(&["sp1-guest"], "provers/sp1/guest/elf");
+    pipeline.bins(&["sp1-guest", "sp1-batch"], "provers/sp1/guest/elf");
     #[cfg(feature = "test")]
     pipeline.tests(&["sp1-guest"], "provers/sp1/guest/elf");
     #[cfg(feature = "bench")]
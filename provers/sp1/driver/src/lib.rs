@@ -1,23 +1,118 @@
 #![cfg(feature = "enable")]
-use std::env;
+use std::path::PathBuf;
 
-use alloy_primitives::B256;
+use alloy_primitives::{hex, B256};
 use alloy_sol_types::SolValue;
 use raiko_lib::{
-    input::{GuestInput, GuestOutput},
+    input::{BatchGuestInput, BatchGuestOutput, GuestInput, GuestOutput},
     protocol_instance::ProtocolInstance,
-    prover::{to_proof, Proof, Prover, ProverConfig, ProverResult},
+    prover::{
+        to_proof, GuestEstimate, Proof, Prover, ProverConfig, ProverError, ProverResult,
+        ProvingTimeModel,
+    },
+    witness::{GuestWitness, WitnessFormat},
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sha3::{self, Digest};
-use sp1_sdk::{ProverClient, SP1Stdin};
+use sp1_sdk::{HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin};
+use tracing::info;
 
 const ELF: &[u8] = include_bytes!("../../guest/elf/sp1-guest");
+const BATCH_ELF: &[u8] = include_bytes!("../../guest/elf/sp1-batch");
+
+/// The default shard size of the prover, overridable with the `SHARD_SIZE` env variable.
+const DEFAULT_SHARD_SIZE: u64 = 1 << 22;
+
+/// Rough default for the proving time of a local CPU prover with the default shard size, to
+/// be overridden with `proving_time_model` after timing a few proofs on the proving machine.
+const PROVING_TIME_MODEL: ProvingTimeModel = ProvingTimeModel {
+    base_secs: 10.0,
+    secs_per_segment: 60.0,
+};
+
+/// The kind of proof to generate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecursionMode {
+    /// A proof per shard, only verifiable off-chain.
+    #[default]
+    Core,
+    /// The shard proofs recursively compressed into a single proof.
+    Compressed,
+    /// The compressed proof wrapped in a Groth16 SNARK, verifiable on-chain.
+    Groth16,
+    /// The compressed proof wrapped in a PLONK SNARK, verifiable on-chain.
+    Plonk,
+}
+
+/// What a proof proves, which determines the program and verification key it is checked with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sp1ProofKind {
+    /// A proof of a single block by the block program.
+    #[default]
+    Block,
+    /// A proof of a batch of blocks by the batch program.
+    Batch,
+}
+
+impl Sp1ProofKind {
+    /// The program that creates proofs of this kind.
+    fn elf(self) -> &'static [u8] {
+        match self {
+            Sp1ProofKind::Block => ELF,
+            Sp1ProofKind::Batch => BATCH_ELF,
+        }
+    }
+}
+
+/// What the prover does with the guest program.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sp1Mode {
+    /// Only run the program and report the cycle count.
+    Execute,
+    /// Skip proving, but return a mock proof with the correct public values.
+    Mock,
+    /// Generate the proof without verifying it.
+    Prove,
+    /// Generate the proof and verify it.
+    #[default]
+    ProveAndVerify,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sp1Param {
+    pub mode: Sp1Mode,
+    pub recursion: RecursionMode,
+    /// Where to save the proof with its public values, if at all.
+    pub proof_path: Option<PathBuf>,
+    /// Overrides the default model used to estimate the proving time.
+    pub proving_time_model: Option<ProvingTimeModel>,
+}
 
 #[derive(Clone, Serialize, Deserialize)]
-pub struct Sp1Response {
+pub struct Sp1Response<O = GuestOutput> {
+    /// The serialized proof, empty when only executing the program.
     pub proof: String,
-    pub output: GuestOutput,
+    /// The output of the block, or the [BatchGuestOutput] of a batch proof.
+    pub output: O,
+    /// The hash of the verification key, as expected by the SP1 verifier contract.
+    pub vkey_hash: Option<String>,
+    /// The ABI-encoded public values committed to by the program.
+    pub public_values: String,
+    /// The number of cycles the program took, when only executing it.
+    pub cycles: Option<u64>,
+    /// The verification key hash followed by the encoded SNARK proof, in the format of the
+    /// on-chain verifier. Only available for Groth16 and PLONK proofs.
+    pub wrapped_proof: Option<String>,
+    /// The kind of the serialized proof.
+    #[serde(default)]
+    pub recursion: RecursionMode,
+    /// Whether this is a block or a batch proof.
+    #[serde(default)]
+    pub kind: Sp1ProofKind,
 }
 
 pub struct Sp1Prover;
@@ -25,45 +120,384 @@ pub struct Sp1Prover;
 impl Prover for Sp1Prover {
     async fn run(
         input: GuestInput,
-        _output: &GuestOutput,
-        _config: &ProverConfig,
+        output: &GuestOutput,
+        config: &ProverConfig,
     ) -> ProverResult<Proof> {
+        let param = match config.get("sp1") {
+            Some(value) => Sp1Param::deserialize(value)
+                .map_err(|e| format!("Sp1: invalid prover params: {e}"))?,
+            None => Sp1Param::default(),
+        };
+
+        // The guest panics on blocks that fail to build, so they can't be proven
+        let GuestOutput::Success { hash, .. } = output else {
+            return Err("Sp1: cannot prove a block that failed to build"
+                .to_owned()
+                .into());
+        };
+
         // Write the input.
+        let format = WitnessFormat::from_config(config).map_err(|e| e.to_string())?;
         let mut stdin = SP1Stdin::new();
-        stdin.write(&input);
+        stdin.write(&GuestWitness::new(input, format));
 
-        // Generate the proof for the given program.
-        let client = ProverClient::new();
-        let (pk, vk) = client.setup(ELF);
-        let mut proof = client.prove(&pk, stdin).expect("Sp1: proving failed");
+        let proof = prove(ELF, stdin, &param)?;
 
-        // Read the output.
-        let output = proof.public_values.read::<GuestOutput>();
-        // Verify proof.
-        client
-            .verify(&proof, &vk)
-            .expect("Sp1: verification failed");
+        // The guest commits to the ABI-encoded instance hash
+        if proof.public_values != hash.abi_encode() {
+            return Err(format!(
+                "Sp1: output mismatch, proven: 0x{}, expected: {hash}",
+                hex::encode(&proof.public_values)
+            )
+            .into());
+        }
 
-        // Save the proof.
-        let proof_dir = env::current_dir().expect("Sp1: dir error");
-        proof
-            .save(
-                proof_dir
-                    .as_path()
-                    .join("proof-with-io.json")
-                    .to_str()
-                    .unwrap(),
+        to_proof(Ok(proof.into_response(
+            output.clone(),
+            param.recursion,
+            Sp1ProofKind::Block,
+        )))
+    }
+
+    async fn batch_run(
+        batch: BatchGuestInput,
+        output: &BatchGuestOutput,
+        config: &ProverConfig,
+    ) -> ProverResult<Proof> {
+        let param = match config.get("sp1") {
+            Some(value) => Sp1Param::deserialize(value)
+                .map_err(|e| format!("Sp1: invalid prover params: {e}"))?,
+            None => Sp1Param::default(),
+        };
+        let BatchGuestOutput::Success { hash, .. } = output else {
+            return Err("Sp1: cannot prove a batch that failed to build"
+                .to_owned()
+                .into());
+        };
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&batch);
+
+        let proof = prove(BATCH_ELF, stdin, &param)?;
+
+        // The batch guest commits to the ABI-encoded hash chain of the instance hashes
+        if proof.public_values != hash.abi_encode() {
+            return Err(format!(
+                "Sp1: batch output mismatch, proven: 0x{}, expected: {hash}",
+                hex::encode(&proof.public_values)
             )
-            .expect("Sp1: saving proof failed");
+            .into());
+        }
+
+        to_proof(Ok(proof.into_response(
+            output.clone(),
+            param.recursion,
+            Sp1ProofKind::Batch,
+        )))
+    }
+
+    async fn verify(
+        _input: GuestInput,
+        output: &GuestOutput,
+        proof: &Proof,
+        _config: &ProverConfig,
+    ) -> ProverResult<()> {
+        let response = Sp1Response::<GuestOutput>::deserialize(proof)
+            .map_err(|e| format!("Sp1: invalid proof: {e}"))?;
+        let public_values = verify_response(&response, Sp1ProofKind::Block)?;
+
+        // The guest commits to the ABI-encoded instance hash
+        let GuestOutput::Success { hash, .. } = output else {
+            return Err("Sp1: cannot verify a proof of a block that failed to build"
+                .to_owned()
+                .into());
+        };
+        if public_values != hash.abi_encode() {
+            return Err(format!(
+                "Sp1: output mismatch, proven: 0x{}, expected: {hash}",
+                hex::encode(&public_values)
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn estimate(input: GuestInput, config: &ProverConfig) -> ProverResult<GuestEstimate> {
+        let param = match config.get("sp1") {
+            Some(value) => Sp1Param::deserialize(value)
+                .map_err(|e| format!("Sp1: invalid prover params: {e}"))?,
+            None => Sp1Param::default(),
+        };
+
+        let format = WitnessFormat::from_config(config).map_err(|e| e.to_string())?;
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&GuestWitness::new(input, format));
+        let (_, report) = ProverClient::new()
+            .execute(ELF, stdin)
+            .map_err(|e| format!("Sp1: execution failed: {e}"))?;
 
-        println!("succesfully generated and verified proof for the program!");
-        to_proof(Ok(Sp1Response {
-            proof: serde_json::to_string(&proof).unwrap(),
+        let total_cycles = report.total_instruction_count();
+        let shard_size = std::env::var("SHARD_SIZE")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(DEFAULT_SHARD_SIZE);
+        let segments = total_cycles.div_ceil(shard_size);
+        let precompile_calls = report
+            .syscall_counts
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(syscall, count)| (format!("{syscall:?}"), *count))
+            .collect();
+        let model = param.proving_time_model.unwrap_or(PROVING_TIME_MODEL);
+        info!("Sp1: executed the program in {total_cycles} cycles and {segments} shards");
+        Ok(GuestEstimate {
+            total_cycles,
+            segments,
+            segment_po2: shard_size.ilog2(),
+            precompile_calls: Some(precompile_calls),
+            // Every touched memory address is a 4-byte word
+            peak_memory: Some(report.touched_memory_addresses * 4),
+            proving_time_secs: model.proving_time_secs(segments),
+        })
+    }
+}
+
+impl Sp1Prover {
+    /// Checks that a proof created by `batch_run` is valid and proves the expected output of
+    /// the batch.
+    pub fn verify_batch(output: &BatchGuestOutput, proof: &Proof) -> ProverResult<()> {
+        let response = Sp1Response::<BatchGuestOutput>::deserialize(proof)
+            .map_err(|e| format!("Sp1: invalid proof: {e}"))?;
+        let public_values = verify_response(&response, Sp1ProofKind::Batch)?;
+
+        // The batch guest commits to the ABI-encoded hash chain of the instance hashes
+        let BatchGuestOutput::Success { hash, .. } = output else {
+            return Err("Sp1: cannot verify a proof of a batch that failed to build"
+                .to_owned()
+                .into());
+        };
+        if public_values != hash.abi_encode() {
+            return Err(format!(
+                "Sp1: batch output mismatch, proven: 0x{}, expected: {hash}",
+                hex::encode(&public_values)
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Verifies the proof in the response with the verification key of the program of `kind`
+/// and returns the public values.
+fn verify_response<O>(response: &Sp1Response<O>, kind: Sp1ProofKind) -> ProverResult<Vec<u8>> {
+    if response.kind != kind {
+        return Err(format!(
+            "Sp1: expected a {kind:?} proof, got a {:?} proof",
+            response.kind
+        )
+        .into());
+    }
+    if response.proof.is_empty() {
+        return Err("Sp1: the program was only executed, there is no proof"
+            .to_owned()
+            .into());
+    }
+
+    let client = ProverClient::new();
+    let (_, vk) = client.setup(kind.elf());
+    let public_values = match response.recursion {
+        RecursionMode::Core => {
+            let proof = decode_proof(&response.proof)?;
+            client
+                .verify(&proof, &vk)
+                .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            proof.public_values.to_vec()
+        }
+        RecursionMode::Compressed => {
+            let proof = decode_proof(&response.proof)?;
+            client
+                .verify_compressed(&proof, &vk)
+                .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            proof.public_values.to_vec()
+        }
+        RecursionMode::Groth16 => {
+            let proof = decode_proof(&response.proof)?;
+            client
+                .verify_groth16(&proof, &vk)
+                .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            proof.public_values.to_vec()
+        }
+        RecursionMode::Plonk => {
+            let proof = decode_proof(&response.proof)?;
+            client
+                .verify_plonk(&proof, &vk)
+                .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            proof.public_values.to_vec()
+        }
+    };
+    Ok(public_values)
+}
+
+fn to_proving_error(e: anyhow::Error) -> ProverError {
+    ProverError::GuestError(format!("Sp1: proving failed: {e}"))
+}
+
+/// The result of running a guest program with [prove].
+struct Sp1Proof {
+    proof: String,
+    vkey_hash: Option<String>,
+    public_values: Vec<u8>,
+    cycles: Option<u64>,
+    wrapped_proof: Option<String>,
+}
+
+impl Sp1Proof {
+    fn into_response<O>(
+        self,
+        output: O,
+        recursion: RecursionMode,
+        kind: Sp1ProofKind,
+    ) -> Sp1Response<O> {
+        Sp1Response {
+            proof: self.proof,
             output,
-        }))
+            vkey_hash: self.vkey_hash,
+            public_values: format!("0x{}", hex::encode(self.public_values)),
+            cycles: self.cycles,
+            wrapped_proof: self.wrapped_proof,
+            recursion,
+            kind,
+        }
     }
 }
 
+/// Executes or proves the guest program with the input in `stdin`, as set by the mode of
+/// the prover params.
+fn prove(elf: &[u8], stdin: SP1Stdin, param: &Sp1Param) -> ProverResult<Sp1Proof> {
+    if param.mode == Sp1Mode::Execute {
+        let (public_values, report) = ProverClient::new()
+            .execute(elf, stdin)
+            .map_err(|e| format!("Sp1: execution failed: {e}"))?;
+        let cycles = report.total_instruction_count();
+        info!("Sp1: executed the program in {cycles} cycles");
+        return Ok(Sp1Proof {
+            proof: String::new(),
+            vkey_hash: None,
+            public_values: public_values.to_vec(),
+            cycles: Some(cycles),
+            wrapped_proof: None,
+        });
+    }
+
+    // Generate the proof for the given program.
+    let client = if param.mode == Sp1Mode::Mock {
+        ProverClient::mock()
+    } else {
+        ProverClient::new()
+    };
+    let verify = param.mode == Sp1Mode::ProveAndVerify;
+    let (pk, vk) = client.setup(elf);
+    let vkey_hash = vk.bytes32();
+    let (proof, public_values, wrapped_proof) = match param.recursion {
+        RecursionMode::Core => {
+            let proof = client.prove(&pk, stdin).map_err(to_proving_error)?;
+            if verify {
+                client
+                    .verify(&proof, &vk)
+                    .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            }
+            let (proof, public_values) = encode_proof(&proof, param.proof_path.as_ref())?;
+            (proof, public_values, None)
+        }
+        RecursionMode::Compressed => {
+            let proof = client
+                .prove_compressed(&pk, stdin)
+                .map_err(to_proving_error)?;
+            if verify {
+                client
+                    .verify_compressed(&proof, &vk)
+                    .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            }
+            let (proof, public_values) = encode_proof(&proof, param.proof_path.as_ref())?;
+            (proof, public_values, None)
+        }
+        RecursionMode::Groth16 => {
+            let proof = client.prove_groth16(&pk, stdin).map_err(to_proving_error)?;
+            if verify {
+                client
+                    .verify_groth16(&proof, &vk)
+                    .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            }
+            let wrapped_proof = encode_wrapped_proof(&proof, &vkey_hash)?;
+            let (proof, public_values) = encode_proof(&proof, param.proof_path.as_ref())?;
+            (proof, public_values, Some(wrapped_proof))
+        }
+        RecursionMode::Plonk => {
+            let proof = client.prove_plonk(&pk, stdin).map_err(to_proving_error)?;
+            if verify {
+                client
+                    .verify_plonk(&proof, &vk)
+                    .map_err(|e| format!("Sp1: verification failed: {e:?}"))?;
+            }
+            let wrapped_proof = encode_wrapped_proof(&proof, &vkey_hash)?;
+            let (proof, public_values) = encode_proof(&proof, param.proof_path.as_ref())?;
+            (proof, public_values, Some(wrapped_proof))
+        }
+    };
+
+    info!("Sp1: successfully generated the proof");
+    Ok(Sp1Proof {
+        proof,
+        vkey_hash: Some(vkey_hash),
+        public_values,
+        cycles: None,
+        wrapped_proof,
+    })
+}
+
+/// Serializes the proof and returns it together with its public values. The proof is also
+/// saved to `proof_path` if given.
+fn encode_proof<P: Serialize + DeserializeOwned>(
+    proof: &SP1ProofWithPublicValues<P>,
+    proof_path: Option<&PathBuf>,
+) -> ProverResult<(String, Vec<u8>)> {
+    if let Some(proof_path) = proof_path {
+        let proof_path = proof_path
+            .to_str()
+            .ok_or_else(|| format!("Sp1: invalid proof path {}", proof_path.display()))?;
+        proof
+            .save(proof_path)
+            .map_err(|e| format!("Sp1: saving proof failed: {e}"))?;
+    }
+    let encoded =
+        serde_json::to_string(proof).map_err(|e| format!("Sp1: encoding proof failed: {e}"))?;
+    Ok((encoded, proof.public_values.to_vec()))
+}
+
+/// Deserializes a proof serialized by `encode_proof`.
+fn decode_proof<P: DeserializeOwned>(proof: &str) -> ProverResult<SP1ProofWithPublicValues<P>> {
+    serde_json::from_str(proof).map_err(|e| format!("Sp1: decoding proof failed: {e}").into())
+}
+
+/// Encodes a SNARK wrapped proof for the on-chain verifier: the 32 byte verification key hash
+/// followed by the encoded proof.
+fn encode_wrapped_proof<P: Serialize>(
+    proof: &SP1ProofWithPublicValues<P>,
+    vkey_hash: &str,
+) -> ProverResult<String> {
+    let snark = serde_json::to_value(&proof.proof)
+        .map_err(|e| format!("Sp1: encoding proof failed: {e}"))?;
+    let encoded_proof = snark
+        .get("encoded_proof")
+        .and_then(|proof| proof.as_str())
+        .ok_or_else(|| "Sp1: no encoded SNARK proof".to_owned())?;
+    let mut data = hex::decode(vkey_hash).map_err(|e| format!("Sp1: invalid vkey hash: {e}"))?;
+    data.extend(
+        hex::decode(encoded_proof).map_err(|e| format!("Sp1: invalid encoded proof: {e}"))?,
+    );
+    Ok(format!("0x{}", hex::encode(data)))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -80,4 +514,46 @@ mod test {
             .verify(&proof, &vk)
             .expect("Sp1: verification failed");
     }
+
+    #[test]
+    fn sp1_param_defaults() {
+        let param: Sp1Param = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(param.mode, Sp1Mode::ProveAndVerify);
+        assert_eq!(param.recursion, RecursionMode::Core);
+        assert!(param.proof_path.is_none());
+        assert!(param.proving_time_model.is_none());
+
+        let param: Sp1Param = serde_json::from_value(serde_json::json!({
+            "mode": "execute",
+            "recursion": "groth16",
+            "proof_path": "proof.json",
+        }))
+        .unwrap();
+        assert_eq!(param.mode, Sp1Mode::Execute);
+        assert_eq!(param.recursion, RecursionMode::Groth16);
+        assert_eq!(param.proof_path, Some(PathBuf::from("proof.json")));
+    }
+
+    #[test]
+    fn verify_checks_proof_kind() {
+        let response = |kind: &str| {
+            Sp1Response::<BatchGuestOutput>::deserialize(serde_json::json!({
+                "proof": "{}",
+                "output": "Failure",
+                "vkey_hash": null,
+                "public_values": "0x",
+                "cycles": null,
+                "wrapped_proof": null,
+                "kind": kind,
+            }))
+            .unwrap()
+        };
+        // A batch proof is not checked with the verification key of the block program
+        assert!(verify_response(&response("batch"), Sp1ProofKind::Block)
+            .unwrap_err()
+            .to_string()
+            .contains("expected a Block proof"));
+        assert_eq!(Sp1ProofKind::Batch.elf(), BATCH_ELF);
+        assert_eq!(Sp1ProofKind::Block.elf(), ELF);
+    }
 }
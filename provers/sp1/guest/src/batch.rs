@@ -0,0 +1,36 @@
+#![no_main]
+harness::entrypoint!(main);
+
+use raiko_lib::{
+    builder::ChainStrategy,
+    consts::VerifierType,
+    input::BatchGuestInput,
+    protocol_instance::BatchProtocolInstance,
+};
+use revm_precompile::zk_op::ZkOperation;
+use zk_op::Sp1Operator;
+
+pub mod mem;
+pub use mem::*;
+
+pub fn main() {
+    let batch = sp1_zkvm::io::read::<BatchGuestInput>();
+
+    revm_precompile::zk_op::ZKVM_OPERATOR.get_or_init(|| Box::new(Sp1Operator {}));
+    revm_precompile::zk_op::ZKVM_OPERATIONS
+        .set(Box::new(vec![
+            ZkOperation::Bn128Add,
+            ZkOperation::Bn128Mul,
+            ZkOperation::Secp256k1,
+        ]))
+        .expect("Failed to set ZkvmOperations");
+
+    let headers = ChainStrategy::build_batch_from(&batch).expect("Failed to build the batch");
+    let hash = BatchProtocolInstance::new(&batch, &headers, VerifierType::SP1)
+        .expect("Failed to assemble the batch protocol instance")
+        .instance_hash();
+
+    // Like the block guest, only the bytes32 hash is committed so the SP1 verifier contract
+    // can use the public values as is
+    sp1_zkvm::io::commit_slice(hash.as_slice());
+}
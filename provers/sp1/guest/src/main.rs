@@ -4,9 +4,9 @@ harness::entrypoint!(main, tests, zk_op::tests);
 
 use raiko_lib::{
     consts::VerifierType,
-    builder::{BlockBuilderStrategy, TaikoStrategy},
-    input::{GuestInput, GuestOutput},
+    builder::ChainStrategy,
     protocol_instance::ProtocolInstance,
+    witness::GuestWitness,
 };
 use revm_precompile::zk_op::ZkOperation;
 use zk_op::Sp1Operator;
@@ -15,7 +15,9 @@ pub mod mem;
 pub use mem::*;
 
 pub fn main() {
-    let input = sp1_zkvm::io::read::<GuestInput>();
+    let input = sp1_zkvm::io::read::<GuestWitness>()
+        .into_input()
+        .expect("Failed to rebuild the input");
 
     revm_precompile::zk_op::ZKVM_OPERATOR.get_or_init(|| Box::new(Sp1Operator {}));
     revm_precompile::zk_op::ZKVM_OPERATIONS
@@ -26,22 +28,17 @@ pub fn main() {
         ]))
         .expect("Failed to set ZkvmOperations");
 
-    let build_result = TaikoStrategy::build_from(&input);
-
-    let output = match &build_result {
-        Ok((header, _mpt_node)) => {
-            let pi = ProtocolInstance::new(&input, header, VerifierType::SP1)
-                .expect("Failed to assemble protocol instance")
-                .instance_hash();
-            GuestOutput::Success {
-                header: header.clone(),
-                hash: pi,
-            }
-        }
-        Err(_) => GuestOutput::Failure,
-    };
-
-    sp1_zkvm::io::commit(&output);
+    // Only the instance hash is committed, so there is no `GuestOutput::Failure` to commit for
+    // a block that fails to build and the guest panics instead. The driver rejects such blocks
+    // before proving them.
+    let (header, _mpt_node) = ChainStrategy::build_from(&input).expect("Failed to build the block");
+    let pi = ProtocolInstance::new(&input, &header, VerifierType::SP1)
+        .expect("Failed to assemble protocol instance")
+        .instance_hash();
+
+    // The ABI encoding of the bytes32 instance hash is the hash itself, which lets the SP1
+    // verifier contract use the public values as is
+    sp1_zkvm::io::commit_slice(pi.as_slice());
 }
 
 harness::zk_suits!(